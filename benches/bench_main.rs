@@ -1,5 +1,27 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use valve_kv_tools::{format_keyvalue, lint_keyvalue, serialize_keyvalue, FormatterConfig};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pest::Parser;
+use pest_derive::Parser;
+use valve_kv_tools::{
+    format_keyvalue, lint_keyvalue, parse_document, read_events, serialize_keyvalue,
+    FormatterConfig,
+};
+
+/// Pest grammar of the format, the reference the hand-written parser is compared to.
+#[derive(Parser)]
+#[grammar = "valve-kv.pest"]
+struct KeyValueParser;
+
+/// Phrases file with `count` entries, each with a few translations and a comment.
+fn generated_phrases(count: usize) -> String {
+    let mut input = String::from("\"Phrases\"\n{\n");
+    for i in 0..count {
+        input.push_str(&format!(
+            "\t// Phrase {i}\n\t\"Phrase_{i}\"\n\t{{\n\t\t\"#format\"\t\"{{1:s}},{{2:d}}\"\n\t\t\"en\"\t\"Player {{1}} scored {{2}} points\"\n\t\t\"fr\"\t\"Le joueur {{1}} a marqué {{2}} points\"\n\t}}\n"
+        ));
+    }
+    input.push_str("}\n");
+    input
+}
 
 fn criterion_linter_benchmark(c: &mut Criterion) {
     let response = minreq::get("https://raw.githubusercontent.com/surftimer/SurfTimer/dev/addons/sourcemod/translations/surftimer.phrases.txt")
@@ -34,10 +56,43 @@ fn criterion_serializer_benchmark(c: &mut Criterion) {
     });
 }
 
+fn criterion_parser_benchmark(c: &mut Criterion) {
+    let input = generated_phrases(10_000);
+    let mut group = c.benchmark_group("generated phrases");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("pest reference", |b| {
+        b.iter(|| {
+            let _res = black_box(KeyValueParser::parse(Rule::json_document, &input));
+        })
+    });
+    group.bench_function("parser", |b| {
+        b.iter(|| {
+            let _res = black_box(parse_document(&input));
+        })
+    });
+    group.bench_function("events", |b| {
+        b.iter(|| {
+            let _res = black_box(read_events(&input).count());
+        })
+    });
+    group.bench_function("linter", |b| {
+        b.iter(|| {
+            let _res = black_box(lint_keyvalue(&input));
+        })
+    });
+    group.bench_function("formatter", |b| {
+        b.iter(|| {
+            let _res = black_box(format_keyvalue(&input, FormatterConfig::default()));
+        })
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     criterion_linter_benchmark,
     criterion_formatter_benchmark,
-    criterion_serializer_benchmark
+    criterion_serializer_benchmark,
+    criterion_parser_benchmark
 );
 criterion_main!(benches);
@@ -2,6 +2,8 @@ import { useState, useRef } from "react";
 import {
   FormatterConfig,
   KvErrorKind,
+  ParserConfig,
+  PositionEncoding,
   Range,
   lintKeyvalue,
 } from "valve_kv_tools";
@@ -33,15 +35,22 @@ function App() {
       return;
     }
     setCode(value);
-    const lintResults = lintKeyvalue(value);
+    const lintResults = lintKeyvalue(
+      value,
+      new ParserConfig(true, settings.escapeMode, PositionEncoding.Utf16)
+    );
     let errorMarkers: Monaco.editor.IMarkerData[] = [];
     lintResults.forEach((e) => {
       let severity;
       switch (e.kind) {
         case KvErrorKind.SyntaxError:
+        case KvErrorKind.MissingFileError:
+        case KvErrorKind.IncludeCycleError:
+        case KvErrorKind.EncodingError:
           severity = Monaco.MarkerSeverity.Error;
           break;
         case KvErrorKind.DuplicateError:
+        case KvErrorKind.ConversionWarning:
           severity = Monaco.MarkerSeverity.Warning;
       }
       errorMarkers.push({
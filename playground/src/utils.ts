@@ -1,5 +1,5 @@
-import { FormatterConfig } from "valve_kv_tools";
+import { EscapeMode, FormatterConfig, QuoteStyle } from "valve_kv_tools";
 
 export function makeDefaultSettings(): FormatterConfig {
-  return new FormatterConfig(false, 4, 1);
+  return new FormatterConfig(false, 4, 1, QuoteStyle.Preserve, EscapeMode.Json);
 }
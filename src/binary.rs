@@ -0,0 +1,200 @@
+use std::fmt;
+
+use crate::{KeyValue, Value};
+
+const TYPE_SECTION: u8 = 0;
+const TYPE_STRING: u8 = 1;
+const TYPE_INT32: u8 = 2;
+const TYPE_FLOAT32: u8 = 3;
+const TYPE_POINTER: u8 = 4;
+const TYPE_WSTRING: u8 = 5;
+const TYPE_COLOR: u8 = 6;
+const TYPE_UINT64: u8 = 7;
+const TYPE_END: u8 = 8;
+/// End marker written by some Steam files instead of [`TYPE_END`].
+const TYPE_ALTERNATE_END: u8 = 11;
+
+/// Maximum depth of nested sections, to bound the recursion on malformed input.
+const MAX_DEPTH: usize = 256;
+
+/// Error raised when binary keyvalues cannot be read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryError {
+    /// Byte offset of the error in the input, or in the output being written.
+    pub offset: usize,
+
+    /// Error message of the error.
+    pub message: String,
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+/// Read binary keyvalues, as found in Steam's `shortcuts.vdf` or `appinfo.vdf` payloads.
+///
+/// Every node is a type byte, a null-terminated key and a value whose layout depends on
+/// the type. Numbers are little endian. Sections end with an end marker, and so does the
+/// list of root entries, although a missing final marker is accepted.
+///
+/// Wide strings are read as a 16-bit count of UTF-16 code units followed by the code units.
+pub fn read_binary(input: &[u8]) -> Result<Vec<KeyValue>, BinaryError> {
+    let mut reader = Reader { input, pos: 0 };
+    let roots = reader.read_section(0, true)?;
+    if reader.pos < input.len() {
+        return Err(reader.error("unexpected data after the end of the root entries"));
+    }
+
+    Ok(roots)
+}
+
+/// Write entries as binary keyvalues, followed by an end marker. Inverse of [`read_binary`].
+///
+/// Fails if a key or a string contains a null character, as it would end it early.
+pub fn write_binary(roots: &[KeyValue]) -> Result<Vec<u8>, BinaryError> {
+    let mut output = vec![];
+    write_section(&mut output, roots)?;
+
+    Ok(output)
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    /// Read nodes up to the end marker of a section. At the root, the input may also end
+    /// without a marker.
+    fn read_section(&mut self, depth: usize, root: bool) -> Result<Vec<KeyValue>, BinaryError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("sections are nested too deeply"));
+        }
+        let mut kvs = vec![];
+        loop {
+            let kind = match self.input.get(self.pos) {
+                Some(&kind) => kind,
+                None if root => return Ok(kvs),
+                None => return Err(self.error("expected the end of the section")),
+            };
+            self.pos += 1;
+            if kind == TYPE_END || kind == TYPE_ALTERNATE_END {
+                return Ok(kvs);
+            }
+            if kind > TYPE_UINT64 {
+                return Err(self.error_at(self.pos - 1, &format!("unknown node type {kind}")));
+            }
+            let key = self.read_string()?;
+            let value = match kind {
+                TYPE_SECTION => Value::Section(self.read_section(depth + 1, false)?),
+                TYPE_STRING => Value::String(self.read_string()?),
+                TYPE_INT32 => Value::Int32(i32::from_le_bytes(self.read_array()?)),
+                TYPE_FLOAT32 => Value::Float32(f32::from_le_bytes(self.read_array()?)),
+                TYPE_POINTER => Value::Pointer(u32::from_le_bytes(self.read_array()?)),
+                TYPE_WSTRING => Value::WString(self.read_wstring()?),
+                TYPE_COLOR => Value::Color(self.read_array()?),
+                _ => Value::UInt64(u64::from_le_bytes(self.read_array()?)),
+            };
+            kvs.push(KeyValue::new(key, value));
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, BinaryError> {
+        let rest = &self.input[self.pos..];
+        let Some(len) = rest.iter().position(|b| *b == 0) else {
+            return Err(self.error("unterminated string"));
+        };
+        let text = std::str::from_utf8(&rest[..len])
+            .map_err(|err| self.error_at(self.pos + err.valid_up_to(), "invalid UTF-8"))?;
+        self.pos += len + 1;
+
+        Ok(text.to_string())
+    }
+
+    fn read_wstring(&mut self) -> Result<String, BinaryError> {
+        let len = u16::from_le_bytes(self.read_array()?) as usize;
+        let start = self.pos;
+        let units: Vec<u16> = (0..len)
+            .map(|_| self.read_array().map(u16::from_le_bytes))
+            .collect::<Result<_, _>>()?;
+        String::from_utf16(&units).map_err(|_| self.error_at(start, "invalid UTF-16"))
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BinaryError> {
+        let bytes = self
+            .input
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += N;
+
+        Ok(bytes.try_into().expect("the slice has N bytes"))
+    }
+
+    fn error(&self, message: &str) -> BinaryError {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, offset: usize, message: &str) -> BinaryError {
+        BinaryError {
+            offset,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn write_section(output: &mut Vec<u8>, kvs: &[KeyValue]) -> Result<(), BinaryError> {
+    for kv in kvs {
+        let kind = match &kv.value {
+            Value::Section(_) => TYPE_SECTION,
+            Value::String(_) => TYPE_STRING,
+            Value::Int32(_) => TYPE_INT32,
+            Value::Float32(_) => TYPE_FLOAT32,
+            Value::Pointer(_) => TYPE_POINTER,
+            Value::WString(_) => TYPE_WSTRING,
+            Value::Color(_) => TYPE_COLOR,
+            Value::UInt64(_) => TYPE_UINT64,
+        };
+        output.push(kind);
+        write_string(output, &kv.key)?;
+        match &kv.value {
+            Value::Section(section) => write_section(output, section)?,
+            Value::String(value) => write_string(output, value)?,
+            Value::Int32(value) => output.extend(value.to_le_bytes()),
+            Value::Float32(value) => output.extend(value.to_le_bytes()),
+            Value::Pointer(value) => output.extend(value.to_le_bytes()),
+            Value::WString(value) => {
+                let units: Vec<u16> = value.encode_utf16().collect();
+                let Ok(len) = u16::try_from(units.len()) else {
+                    return Err(BinaryError {
+                        offset: output.len(),
+                        message: "wide string is too long".to_string(),
+                    });
+                };
+                output.extend(len.to_le_bytes());
+                output.extend(units.into_iter().flat_map(u16::to_le_bytes));
+            }
+            Value::Color(value) => output.extend(value),
+            Value::UInt64(value) => output.extend(value.to_le_bytes()),
+        }
+    }
+    output.push(TYPE_END);
+
+    Ok(())
+}
+
+fn write_string(output: &mut Vec<u8>, text: &str) -> Result<(), BinaryError> {
+    if let Some(index) = text.find('\0') {
+        return Err(BinaryError {
+            offset: output.len() + index,
+            message: "strings cannot contain null characters".to_string(),
+        });
+    }
+    output.extend(text.as_bytes());
+    output.push(0);
+
+    Ok(())
+}
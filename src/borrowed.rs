@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+
+use crate::{
+    parser::{parse_tree, Parse},
+    serializer::Serializer,
+    Directive, Document, KeyValue, KvError, ParserConfig, Range, Span, Value,
+};
+
+/// Entry borrowing its text from the input, see [`serialize_document_borrowed`].
+///
+/// Keys and values only own their text when they contain escape sequences to decode.
+/// Fields are as in [`KeyValue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowedKeyValue<'a> {
+    pub key: Cow<'a, str>,
+    pub value: BorrowedValue<'a>,
+    pub key_range: Range,
+    pub key_span: Span,
+    pub value_range: Range,
+    pub value_span: Span,
+    pub range: Range,
+    pub span: Span,
+    pub l_brace_range: Option<Range>,
+    pub r_brace_range: Option<Range>,
+    pub key_quoted: bool,
+    pub value_quoted: bool,
+    pub conditional: Option<&'a str>,
+    pub comments: Vec<&'a str>,
+}
+
+/// Value of a [`BorrowedKeyValue`]. Text files only contain strings and sections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BorrowedValue<'a> {
+    String(Cow<'a, str>),
+    Section(Vec<BorrowedKeyValue<'a>>),
+}
+
+/// A keyvalue file whose entries borrow their text from the input.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BorrowedDocument<'a> {
+    pub directives: Vec<Directive>,
+    pub roots: Vec<BorrowedKeyValue<'a>>,
+}
+
+impl BorrowedKeyValue<'_> {
+    /// Copy the borrowed text to get an owned [`KeyValue`].
+    pub fn into_owned(self) -> KeyValue {
+        KeyValue {
+            key: self.key.into_owned(),
+            value: self.value.into_owned(),
+            key_range: self.key_range,
+            key_span: self.key_span,
+            value_range: self.value_range,
+            value_span: self.value_span,
+            range: self.range,
+            span: self.span,
+            l_brace_range: self.l_brace_range,
+            r_brace_range: self.r_brace_range,
+            key_quoted: self.key_quoted,
+            value_quoted: self.value_quoted,
+            conditional: self.conditional.map(str::to_string),
+            comments: self.comments.into_iter().map(str::to_string).collect(),
+        }
+    }
+}
+
+impl BorrowedValue<'_> {
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::String(value) => Value::String(value.into_owned()),
+            BorrowedValue::Section(section) => Value::Section(
+                section
+                    .into_iter()
+                    .map(BorrowedKeyValue::into_owned)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl BorrowedDocument<'_> {
+    pub fn into_owned(self) -> Document {
+        Document {
+            directives: self.directives,
+            roots: self
+                .roots
+                .into_iter()
+                .map(BorrowedKeyValue::into_owned)
+                .collect(),
+        }
+    }
+}
+
+/// Serialize a string containing any number of root keyvalues, without copying the keys
+/// and values that have no escape sequences.
+///
+/// Fails with the first syntax error of the input, as [`serialize_document`](crate::serialize_document).
+pub fn serialize_document_borrowed(input: &str) -> Result<BorrowedDocument<'_>, KvError> {
+    serialize_document_borrowed_with_config(input, ParserConfig::default())
+}
+
+pub fn serialize_document_borrowed_with_config(
+    input: &str,
+    config: ParserConfig,
+) -> Result<BorrowedDocument<'_>, KvError> {
+    let Parse {
+        tree,
+        errors,
+        line_index,
+    } = parse_tree(input, config);
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    let serializer = Serializer::new(input, line_index, config);
+
+    Ok(BorrowedDocument {
+        directives: serializer.serialize_directives(&tree.root),
+        roots: serializer.serialize_roots(&tree.root),
+    })
+}
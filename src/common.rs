@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 /// Position in a text document expressed as zero-based line and character offset.
@@ -44,6 +45,160 @@ impl Range {
     }
 }
 
+/// Byte offsets of a part of a text document. The end offset is exclusive.
+#[wasm_bindgen]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct Span {
+    /// Offset of the first byte.
+    #[wasm_bindgen(readonly)]
+    pub start: usize,
+
+    /// Offset after the last byte.
+    #[wasm_bindgen(readonly)]
+    pub end: usize,
+}
+
+#[wasm_bindgen]
+impl Span {
+    #[wasm_bindgen(constructor)]
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// Unit of the character offset of a [`Position`].
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PositionEncoding {
+    /// Bytes of the UTF-8 encoded text.
+    Utf8,
+
+    /// UTF-16 code units, as used by the Language Server Protocol and JavaScript strings.
+    #[default]
+    Utf16,
+
+    /// Unicode scalar values, i.e. Rust `char`s.
+    Scalars,
+}
+
+impl PositionEncoding {
+    /// Length of a character in this encoding.
+    pub(crate) fn len(self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+            PositionEncoding::Scalars => 1,
+        }
+    }
+}
+
+/// Convert byte offsets of a text to line and character positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LineIndex {
+    encoding: PositionEncoding,
+
+    /// Offsets of the start of each line.
+    line_starts: Vec<usize>,
+
+    /// Offsets of the characters whose length in the encoding differs from their length in
+    /// bytes, with the number of bytes they take in excess.
+    wide_chars: Vec<(usize, usize)>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(input: &str, encoding: PositionEncoding) -> Self {
+        let mut line_index = Self::empty(encoding);
+        line_index.push_text(0, input);
+
+        line_index
+    }
+
+    /// Index of an empty text, to be filled with [`LineIndex::push_text`].
+    pub(crate) fn empty(encoding: PositionEncoding) -> Self {
+        Self {
+            encoding,
+            line_starts: vec![0],
+            wide_chars: vec![],
+        }
+    }
+
+    /// Index the part of the text at `offset`. Parts must be pushed in order, which lets
+    /// the lexer build the index as it goes.
+    pub(crate) fn push_text(&mut self, offset: usize, text: &str) {
+        if text.is_ascii() || self.encoding == PositionEncoding::Utf8 {
+            self.line_starts
+                .extend(text.match_indices('\n').map(|(i, _)| offset + i + 1));
+            return;
+        }
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                self.line_starts.push(offset + i + 1);
+            } else if !c.is_ascii() {
+                self.wide_chars
+                    .push((offset + i, c.len_utf8() - self.encoding.len(c)));
+            }
+        }
+    }
+
+    /// Update the index after `start..end` of its text is replaced with `text`.
+    pub(crate) fn replace(&mut self, start: usize, end: usize, text: &str) {
+        let delta = text.len() as isize - (end - start) as isize;
+        let mut inserted = Self {
+            encoding: self.encoding,
+            line_starts: vec![],
+            wide_chars: vec![],
+        };
+        inserted.push_text(start, text);
+
+        // Lines start after a line break: the replaced ones start in `start + 1..=end`.
+        let first = self.line_starts.partition_point(|i| *i <= start);
+        let last = self.line_starts.partition_point(|i| *i <= end);
+        let len = inserted.line_starts.len();
+        self.line_starts.splice(first..last, inserted.line_starts);
+        for i in self.line_starts[first + len..].iter_mut() {
+            *i = i.saturating_add_signed(delta);
+        }
+
+        let first = self.wide_chars.partition_point(|(i, _)| *i < start);
+        let last = self.wide_chars.partition_point(|(i, _)| *i < end);
+        let len = inserted.wide_chars.len();
+        self.wide_chars.splice(first..last, inserted.wide_chars);
+        for (i, _) in self.wide_chars[first + len..].iter_mut() {
+            *i = i.saturating_add_signed(delta);
+        }
+    }
+
+    pub(crate) fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let first = self.wide_chars.partition_point(|(i, _)| *i < line_start);
+        let last = self.wide_chars.partition_point(|(i, _)| *i < offset);
+        let excess: usize = self.wide_chars[first..last].iter().map(|(_, n)| n).sum();
+        Position::new(line as u32, (offset - line_start - excess) as u32)
+    }
+
+    /// Byte offset of a position of `text`, the text of the index. Positions past the end
+    /// of a line are clamped to it.
+    pub(crate) fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let mut character = 0;
+        for (i, c) in text[line_start..].char_indices() {
+            if c == '\n' || character >= position.character as usize {
+                return line_start + i;
+            }
+            character += self.encoding.len(c);
+        }
+
+        text.len()
+    }
+
+    pub(crate) fn range(&self, start: usize, end: usize) -> Range {
+        Range::new(self.position(start), self.position(end))
+    }
+}
+
 #[test]
 fn test_range() {
     let range = Range::new(Position::new(1, 2), Position::new(3, 4));
@@ -52,3 +207,37 @@ fn test_range() {
     assert_eq!(range.end.line, 3);
     assert_eq!(range.end.character, 4);
 }
+
+#[test]
+fn test_line_index_encodings() {
+    let input = "\"clé\" \"値\"\n\"😀\" \"x\"";
+    let end = input.len();
+    let utf8 = LineIndex::new(input, PositionEncoding::Utf8);
+    assert_eq!(utf8.position(end), Position::new(1, 10));
+    let utf16 = LineIndex::new(input, PositionEncoding::Utf16);
+    assert_eq!(
+        utf16.position(input.find('値').unwrap()),
+        Position::new(0, 7)
+    );
+    assert_eq!(utf16.position(end), Position::new(1, 8));
+    let scalars = LineIndex::new(input, PositionEncoding::Scalars);
+    assert_eq!(scalars.position(end), Position::new(1, 7));
+}
+
+#[test]
+fn test_line_index_replace() {
+    let input = "\"clé\" \"値\"\n\"😀\" \"x\"\n\"y\" \"é\"";
+    for encoding in [
+        PositionEncoding::Utf8,
+        PositionEncoding::Utf16,
+        PositionEncoding::Scalars,
+    ] {
+        for (start, end, text) in [(5, 18, "\n"), (0, 0, "é\n"), (11, 29, ""), (22, 22, "😀")] {
+            let mut expected = input.to_string();
+            expected.replace_range(start..end, text);
+            let mut line_index = LineIndex::new(input, encoding);
+            line_index.replace(start, end, text);
+            assert_eq!(line_index, LineIndex::new(&expected, encoding));
+        }
+    }
+}
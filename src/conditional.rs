@@ -0,0 +1,224 @@
+use std::{collections::HashSet, fmt, iter::Peekable, str::CharIndices};
+
+use crate::{KeyValue, Value};
+
+/// Error raised when a conditional expression cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionalError {
+    /// Expression that failed to parse, without its brackets.
+    pub expression: String,
+
+    /// Error message of the error.
+    pub message: String,
+}
+
+impl fmt::Display for ConditionalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid conditional [{}]: {}",
+            self.expression, self.message
+        )
+    }
+}
+
+impl std::error::Error for ConditionalError {}
+
+/// Evaluate the conditionals of a keyvalue against a set of active symbols.
+///
+/// Entries whose conditional does not match are removed from the tree, and the
+/// conditionals of the remaining entries are cleared. Returns `None` if the root itself
+/// does not match.
+///
+/// Symbols are matched case-insensitively and can be given with or without their `$`
+/// prefix, e.g. `WIN32` or `$WIN32`.
+pub fn evaluate_conditionals(
+    kv: &KeyValue,
+    symbols: &[&str],
+) -> Result<Option<KeyValue>, ConditionalError> {
+    let symbols = normalize_symbols(symbols);
+    evaluate_kv(kv, &symbols)
+}
+
+/// Evaluate the conditionals of the roots of a document, see [`evaluate_conditionals`].
+/// Roots whose conditional does not match are removed.
+pub fn evaluate_conditionals_roots(
+    roots: &[KeyValue],
+    symbols: &[&str],
+) -> Result<Vec<KeyValue>, ConditionalError> {
+    let symbols = normalize_symbols(symbols);
+    let mut kvs = Vec::with_capacity(roots.len());
+    for kv in roots {
+        if let Some(kv) = evaluate_kv(kv, &symbols)? {
+            kvs.push(kv);
+        }
+    }
+
+    Ok(kvs)
+}
+
+/// Evaluate a single conditional expression, e.g. `$WIN32 || $OSX`, against a set of
+/// active symbols.
+///
+/// Supports `!`, `&&`, `||` and parentheses, with `&&` binding tighter than `||`.
+pub fn evaluate_conditional(expression: &str, symbols: &[&str]) -> Result<bool, ConditionalError> {
+    let symbols = normalize_symbols(symbols);
+    ExpressionParser::new(expression, &symbols).parse()
+}
+
+fn normalize_symbols(symbols: &[&str]) -> HashSet<String> {
+    symbols
+        .iter()
+        .map(|symbol| symbol.trim_start_matches('$').to_uppercase())
+        .collect()
+}
+
+fn evaluate_kv(
+    kv: &KeyValue,
+    symbols: &HashSet<String>,
+) -> Result<Option<KeyValue>, ConditionalError> {
+    if let Some(conditional) = &kv.conditional {
+        if !ExpressionParser::new(conditional, symbols).parse()? {
+            return Ok(None);
+        }
+    }
+    let value = match &kv.value {
+        Value::Section(section) => {
+            let mut kvs = Vec::with_capacity(section.len());
+            for kv in section {
+                if let Some(kv) = evaluate_kv(kv, symbols)? {
+                    kvs.push(kv);
+                }
+            }
+            Value::Section(kvs)
+        }
+        value => value.clone(),
+    };
+
+    Ok(Some(KeyValue {
+        key: kv.key.clone(),
+        value,
+        key_range: kv.key_range,
+        key_span: kv.key_span,
+        value_range: kv.value_range,
+        value_span: kv.value_span,
+        range: kv.range,
+        span: kv.span,
+        l_brace_range: kv.l_brace_range,
+        r_brace_range: kv.r_brace_range,
+        key_quoted: kv.key_quoted,
+        value_quoted: kv.value_quoted,
+        conditional: None,
+        comments: kv.comments.clone(),
+    }))
+}
+
+/// Recursive descent parser for conditional expressions.
+///
+/// ```text
+/// or    = and ("||" and)*
+/// and   = unary ("&&" unary)*
+/// unary = "!" unary | "(" or ")" | "$" symbol
+/// ```
+struct ExpressionParser<'a> {
+    expression: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    symbols: &'a HashSet<String>,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn new(expression: &'a str, symbols: &'a HashSet<String>) -> Self {
+        Self {
+            expression,
+            chars: expression.char_indices().peekable(),
+            symbols,
+        }
+    }
+
+    fn parse(mut self) -> Result<bool, ConditionalError> {
+        let result = self.parse_or()?;
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Ok(result),
+            Some((_, c)) => Err(self.error(format!("unexpected character '{}'", c))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<bool, ConditionalError> {
+        let mut result = self.parse_and()?;
+        while self.eat_operator('|')? {
+            // Both sides are always parsed so that syntax errors are not hidden by short-circuiting.
+            result |= self.parse_and()?;
+        }
+
+        Ok(result)
+    }
+
+    fn parse_and(&mut self) -> Result<bool, ConditionalError> {
+        let mut result = self.parse_unary()?;
+        while self.eat_operator('&')? {
+            result &= self.parse_unary()?;
+        }
+
+        Ok(result)
+    }
+
+    fn parse_unary(&mut self) -> Result<bool, ConditionalError> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((_, '!')) => Ok(!self.parse_unary()?),
+            Some((_, '(')) => {
+                let result = self.parse_or()?;
+                self.skip_whitespace();
+                match self.chars.next() {
+                    Some((_, ')')) => Ok(result),
+                    _ => Err(self.error("expected ')'".to_string())),
+                }
+            }
+            Some((start, '$')) => {
+                let start = start + 1;
+                let mut end = start;
+                while let Some((i, c)) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || *c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    self.chars.next();
+                }
+                if start == end {
+                    return Err(self.error("expected a symbol name after '$'".to_string()));
+                }
+                let symbol = self.expression[start..end].to_uppercase();
+                Ok(self.symbols.contains(&symbol))
+            }
+            Some((_, c)) => Err(self.error(format!("unexpected character '{}'", c))),
+            None => Err(self.error("unexpected end of expression".to_string())),
+        }
+    }
+
+    /// Consume a doubled operator such as `&&` or `||`, if it is next.
+    fn eat_operator(&mut self, op: char) -> Result<bool, ConditionalError> {
+        self.skip_whitespace();
+        if !matches!(self.chars.peek(), Some((_, c)) if *c == op) {
+            return Ok(false);
+        }
+        self.chars.next();
+        match self.chars.next() {
+            Some((_, c)) if c == op => Ok(true),
+            _ => Err(self.error(format!("expected '{}{}'", op, op))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn error(&self, message: String) -> ConditionalError {
+        ConditionalError {
+            expression: self.expression.to_string(),
+            message,
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{
+    KeyValue, Kv3Document, Kv3Header, Kv3Member, Kv3Value, KvError, KvErrorKind, Range, Value,
+};
+
+/// How keys repeated in a section are converted to KeyValues3, where keys are unique.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Group the values of a repeated key in an array, in order.
+    #[default]
+    Array,
+
+    /// Keep the first value of a repeated key.
+    First,
+
+    /// Keep the last value of a repeated key.
+    Last,
+}
+
+/// Configuration options for the conversion from KeyValues to KeyValues3.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConversionConfig {
+    pub duplicate_keys: DuplicateKeys,
+
+    /// Should strings that are numbers or booleans be converted to typed values.
+    /// Only strings that are written back identically are converted, e.g. `12` and `1.5`
+    /// but not `012` or `1.0`.
+    pub infer_types: bool,
+}
+
+/// Result of a conversion, with the parts of the input that could not be converted
+/// exactly as [`KvErrorKind::ConversionWarning`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion<T> {
+    pub output: T,
+    pub diagnostics: Vec<KvError>,
+}
+
+/// Convert the root entries of a KeyValues file to a KeyValues3 document.
+///
+/// Every root entry becomes a member of the root object and sections become objects.
+/// Comments are kept, before the member they are attached to.
+pub fn kv1_to_kv3(roots: &[KeyValue], config: ConversionConfig) -> Conversion<Kv3Document> {
+    let mut converter = Converter {
+        config,
+        diagnostics: vec![],
+    };
+    let root = Kv3Value::Object(converter.convert_section(roots));
+
+    Conversion {
+        output: Kv3Document {
+            header: Kv3Header::default(),
+            root,
+        },
+        diagnostics: converter.diagnostics,
+    }
+}
+
+/// Convert a KeyValues3 document to the root entries of a KeyValues file.
+///
+/// Objects become sections, and arrays become repeated keys. Values are written as
+/// strings, e.g. `true` becomes `"1"`.
+pub fn kv3_to_kv1(document: &Kv3Document) -> Conversion<Vec<KeyValue>> {
+    let mut converter = Converter {
+        config: ConversionConfig::default(),
+        diagnostics: vec![],
+    };
+    let roots = match &document.root {
+        Kv3Value::Object(members) => converter.convert_members(members),
+        _ => {
+            converter.warn(
+                Range::default(),
+                "the root of the document is not an object",
+            );
+            vec![]
+        }
+    };
+
+    Conversion {
+        output: roots,
+        diagnostics: converter.diagnostics,
+    }
+}
+
+struct Converter {
+    config: ConversionConfig,
+    diagnostics: Vec<KvError>,
+}
+
+impl Converter {
+    fn convert_section(&mut self, kvs: &[KeyValue]) -> Vec<Kv3Member> {
+        let mut members: Vec<Kv3Member> = vec![];
+        // Whether the value of each member is an array of repeated keys.
+        let mut grouped: Vec<bool> = vec![];
+        for kv in kvs {
+            if let Some(conditional) = &kv.conditional {
+                self.warn(
+                    kv.key_range,
+                    &format!(
+                        "the conditional [{conditional}] of \"{}\" is dropped",
+                        kv.key
+                    ),
+                );
+            }
+            let value = self.convert_value(&kv.value);
+            let Some(index) = members.iter().position(|member| member.key == kv.key) else {
+                members.push(Kv3Member {
+                    key: kv.key.clone(),
+                    key_range: kv.key_range,
+                    value,
+                    comments: kv.comments.clone(),
+                });
+                grouped.push(false);
+                continue;
+            };
+            let member = &mut members[index];
+            match self.config.duplicate_keys {
+                DuplicateKeys::Array => {
+                    match &mut member.value {
+                        Kv3Value::Array(values) if grouped[index] => values.push(value),
+                        first => *first = Kv3Value::Array(vec![std::mem::take(first), value]),
+                    }
+                    grouped[index] = true;
+                    member.comments.extend(kv.comments.iter().cloned());
+                }
+                DuplicateKeys::First => {
+                    self.warn(
+                        kv.key_range,
+                        &format!("duplicate key \"{}\" is dropped", kv.key),
+                    );
+                }
+                DuplicateKeys::Last => {
+                    let range = std::mem::replace(&mut member.key_range, kv.key_range);
+                    member.value = value;
+                    member.comments = kv.comments.clone();
+                    self.warn(range, &format!("duplicate key \"{}\" is dropped", kv.key));
+                }
+            }
+        }
+
+        members
+    }
+
+    fn convert_value(&mut self, value: &Value) -> Kv3Value {
+        match value {
+            Value::Section(section) => Kv3Value::Object(self.convert_section(section)),
+            Value::String(text) if self.config.infer_types => infer_type(text),
+            Value::String(text) | Value::WString(text) => Kv3Value::String(text.clone()),
+            Value::Int32(value) => Kv3Value::Int(i64::from(*value)),
+            Value::Float32(value) => Kv3Value::Double(f64::from(*value)),
+            Value::Pointer(value) => Kv3Value::Int(i64::from(*value)),
+            Value::Color(color) => {
+                Kv3Value::Array(color.iter().map(|c| Kv3Value::Int(i64::from(*c))).collect())
+            }
+            Value::UInt64(value) => match i64::try_from(*value) {
+                Ok(value) => Kv3Value::Int(value),
+                Err(_) => Kv3Value::UInt(*value),
+            },
+        }
+    }
+
+    fn convert_members(&mut self, members: &[Kv3Member]) -> Vec<KeyValue> {
+        let mut kvs = vec![];
+        for member in members {
+            let values = match &member.value {
+                Kv3Value::Array(values) => {
+                    if values.is_empty() {
+                        self.warn(
+                            member.key_range,
+                            &format!("the empty array \"{}\" is dropped", member.key),
+                        );
+                    }
+                    values.iter().collect()
+                }
+                value => vec![value],
+            };
+            for (i, value) in values.into_iter().enumerate() {
+                let mut kv =
+                    KeyValue::new(member.key.clone(), self.convert_kv3_value(member, value));
+                kv.key_range = member.key_range;
+                if i == 0 {
+                    kv.comments = member.comments.clone();
+                }
+                kvs.push(kv);
+            }
+        }
+
+        kvs
+    }
+
+    fn convert_kv3_value(&mut self, member: &Kv3Member, value: &Kv3Value) -> Value {
+        let text = match value {
+            Kv3Value::Object(members) => return Value::Section(self.convert_members(members)),
+            Kv3Value::Null => {
+                self.warn(
+                    member.key_range,
+                    &format!("null \"{}\" is converted to an empty string", member.key),
+                );
+                String::new()
+            }
+            Kv3Value::Bool(value) => u8::from(*value).to_string(),
+            Kv3Value::Int(value) => value.to_string(),
+            Kv3Value::UInt(value) => value.to_string(),
+            Kv3Value::Double(value) => value.to_string(),
+            Kv3Value::String(value) => value.clone(),
+            Kv3Value::Binary(bytes) => {
+                self.warn(
+                    member.key_range,
+                    &format!(
+                        "binary blob \"{}\" is converted to a hexadecimal string",
+                        member.key
+                    ),
+                );
+                bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+            }
+            Kv3Value::Array(values) => {
+                self.warn(
+                    member.key_range,
+                    &format!("nested array \"{}\" is converted to a string", member.key),
+                );
+                values
+                    .iter()
+                    .map(|value| match self.convert_kv3_value(member, value) {
+                        Value::Section(_) => String::new(),
+                        value => value.to_text().unwrap_or_default(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            Kv3Value::Flagged(flag, value) => {
+                self.warn(
+                    member.key_range,
+                    &format!("the flag {flag} of \"{}\" is dropped", member.key),
+                );
+                return self.convert_kv3_value(member, value);
+            }
+        };
+
+        Value::String(text)
+    }
+
+    fn warn(&mut self, range: Range, message: &str) {
+        self.diagnostics.push(KvError {
+            range,
+            additional_ranges: vec![],
+            message: message.to_string(),
+            kind: KvErrorKind::ConversionWarning,
+        });
+    }
+}
+
+/// Typed value of a string, if it is written back identically.
+fn infer_type(text: &str) -> Kv3Value {
+    match text {
+        "true" => return Kv3Value::Bool(true),
+        "false" => return Kv3Value::Bool(false),
+        _ => (),
+    }
+    if let Ok(value) = text.parse::<i64>() {
+        if value.to_string() == text {
+            return Kv3Value::Int(value);
+        }
+    }
+    // `inf` and `NaN` are read as floats, but cannot be written in KeyValues3.
+    if let Ok(value) = text.parse::<f64>() {
+        if value.is_finite() && value.to_string() == text {
+            return Kv3Value::Double(value);
+        }
+    }
+
+    Kv3Value::String(text.to_string())
+}
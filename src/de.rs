@@ -0,0 +1,687 @@
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+
+use crate::{
+    serialize_document_borrowed_with_config, BorrowedKeyValue, BorrowedValue, ParserConfig, Range,
+    SequenceStyle,
+};
+
+/// Error raised when keyvalue text cannot be deserialized into a Rust type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeserializeError {
+    /// Range of the offending key or value, or of the section missing a field. Empty for
+    /// errors about the root entries themselves.
+    pub range: Range,
+
+    /// Error message of the error.
+    pub message: String,
+
+    /// Whether the range was set, so that outer values do not override it.
+    located: bool,
+}
+
+impl DeserializeError {
+    fn at(self, range: Range) -> Self {
+        if self.located {
+            return self;
+        }
+        Self {
+            range,
+            located: true,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.range.start.line + 1,
+            self.range.start.character + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self {
+            range: Range::default(),
+            message: message.to_string(),
+            located: false,
+        }
+    }
+}
+
+/// Deserialize a Rust type from a string of keyvalue.
+///
+/// The root entries are read as a map, so `T` is usually a struct with a field per root
+/// key. The data model is the following:
+///
+/// - Strings can be read as strings, numbers, booleans (`0`, `1`, `true` or `false`) and
+///   unit enum variants. Numbers are parsed from the whole text, without its surrounding
+///   whitespace.
+/// - Sections are read as maps or structs, or as an enum variant when they hold a single
+///   entry whose key is the variant.
+/// - Keys that appear several times in a section are read as a sequence, and so is a
+///   single entry. Other types cannot be read from a duplicated key.
+/// - With [`SequenceStyle::Indexed`] in the configuration, a single entry is read as a
+///   sequence from its section, whose keys must be the indices of the elements, as
+///   [`to_string_with_config`](crate::to_string_with_config) writes them.
+/// - Missing entries are read as `None` for `Option` fields.
+///
+/// Keys are matched case-sensitively, and conditionals are not evaluated: entries are read
+/// whatever their conditional. Strings without escape sequences are borrowed from the input.
+pub fn from_str<'a, T: de::Deserialize<'a>>(input: &'a str) -> Result<T, DeserializeError> {
+    from_str_with_config(input, DeserializerConfig::default())
+}
+
+/// Configuration options for [`from_str_with_config`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeserializerConfig {
+    /// How the input is parsed.
+    pub parser: ParserConfig,
+
+    /// How sequences were written, see [`SerializerConfig`](crate::SerializerConfig).
+    pub sequence_style: SequenceStyle,
+}
+
+pub fn from_str_with_config<'a, T: de::Deserialize<'a>>(
+    input: &'a str,
+    config: DeserializerConfig,
+) -> Result<T, DeserializeError> {
+    let document =
+        serialize_document_borrowed_with_config(input, config.parser).map_err(|error| {
+            DeserializeError {
+                range: error.range,
+                message: error.message,
+                located: true,
+            }
+        })?;
+
+    T::deserialize(SectionDeserializer {
+        entries: &document.roots,
+        style: config.sequence_style,
+    })
+}
+
+/// Entries of a section, or the root entries, read as a map.
+struct SectionDeserializer<'b, 'de> {
+    entries: &'b [BorrowedKeyValue<'de>],
+    style: SequenceStyle,
+}
+
+impl<'de> de::Deserializer<'de> for SectionDeserializer<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(Entries::new(self.entries, self.style))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.entries {
+            [entry] => visitor.visit_enum(EntryDeserializer {
+                entry,
+                style: self.style,
+            }),
+            _ => Err(de::Error::invalid_type(
+                Unexpected::Map,
+                &"a section with a single entry",
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Entries of a section, grouped by key in the order of their first appearance.
+struct Entries<'b, 'de> {
+    groups: std::vec::IntoIter<Vec<&'b BorrowedKeyValue<'de>>>,
+    value: Option<Vec<&'b BorrowedKeyValue<'de>>>,
+    style: SequenceStyle,
+}
+
+impl<'b, 'de> Entries<'b, 'de> {
+    fn new(entries: &'b [BorrowedKeyValue<'de>], style: SequenceStyle) -> Self {
+        let mut groups: Vec<Vec<&BorrowedKeyValue>> = vec![];
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        for entry in entries {
+            match indices.get(entry.key.as_ref()) {
+                Some(&index) => groups[index].push(entry),
+                None => {
+                    indices.insert(entry.key.as_ref(), groups.len());
+                    groups.push(vec![entry]);
+                }
+            }
+        }
+
+        Self {
+            groups: groups.into_iter(),
+            value: None,
+            style,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Entries<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some(group) = self.groups.next() else {
+            return Ok(None);
+        };
+        let entry = group[0];
+        self.value = Some(group);
+
+        seed.deserialize(StrDeserializer { text: &entry.key })
+            .map(Some)
+            .map_err(|error| error.at(entry.key_range))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let entries = self.value.take().expect("a key was read");
+        seed.deserialize(GroupDeserializer {
+            entries,
+            style: self.style,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.groups.len())
+    }
+}
+
+/// The entries of a section sharing a key. Only sequences can hold several entries.
+struct GroupDeserializer<'b, 'de> {
+    entries: Vec<&'b BorrowedKeyValue<'de>>,
+    style: SequenceStyle,
+}
+
+impl<'b, 'de> GroupDeserializer<'b, 'de> {
+    fn single(self) -> Result<EntryDeserializer<'b, 'de>, DeserializeError> {
+        match self.entries[..] {
+            [entry] => Ok(EntryDeserializer {
+                entry,
+                style: self.style,
+            }),
+            [_, duplicate, ..] => {
+                let error: DeserializeError = de::Error::custom(format!(
+                    "duplicate key \"{}\", expected a single value",
+                    duplicate.key
+                ));
+                Err(error.at(duplicate.key_range))
+            }
+            [] => unreachable!("groups are not empty"),
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for GroupDeserializer<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.entries.len() > 1 {
+            return self.deserialize_seq(visitor);
+        }
+        self.single()?.deserialize_any(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.entries.len() == 1 {
+            return self.single()?.deserialize_seq(visitor);
+        }
+        visitor.visit_seq(EntrySeq {
+            entries: self.entries.into_iter(),
+            style: self.style,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_map deserialize_identifier
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// Entries read as a sequence, each of them being an element.
+struct EntrySeq<'b, 'de> {
+    entries: std::vec::IntoIter<&'b BorrowedKeyValue<'de>>,
+    style: SequenceStyle,
+}
+
+impl<'de> SeqAccess<'de> for EntrySeq<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.entries
+            .next()
+            .map(|entry| {
+                seed.deserialize(EntryDeserializer {
+                    entry,
+                    style: self.style,
+                })
+            })
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Forward the methods of the value of an entry to the deserializer of its string, or of
+/// its section, and locate their errors at the value.
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match &self.entry.value {
+                    BorrowedValue::String(text) => StrDeserializer { text }.$method(visitor),
+                    BorrowedValue::Section(entries) => SectionDeserializer {
+                        entries,
+                        style: self.style,
+                    }
+                    .$method(visitor),
+                }
+                .map_err(|error| error.at(self.entry.value_range))
+            }
+        )*
+    };
+}
+
+/// The value of an entry.
+#[derive(Clone, Copy)]
+struct EntryDeserializer<'b, 'de> {
+    entry: &'b BorrowedKeyValue<'de>,
+    style: SequenceStyle,
+}
+
+impl<'b, 'de> EntryDeserializer<'b, 'de> {
+    /// Elements of a sequence written with [`SequenceStyle::Indexed`]: the entries of the
+    /// section of the entry, keyed by their indices.
+    fn indexed(self, start: u32) -> Result<Vec<&'b BorrowedKeyValue<'de>>, DeserializeError> {
+        let entries = match &self.entry.value {
+            BorrowedValue::Section(entries) => entries,
+            BorrowedValue::String(text) => {
+                let error: DeserializeError =
+                    de::Error::invalid_type(Unexpected::Str(text), &"a section of indexed entries");
+                return Err(error.at(self.entry.value_range));
+            }
+        };
+        for (entry, index) in entries.iter().zip(u64::from(start)..) {
+            if entry.key != index.to_string() {
+                let error: DeserializeError = de::Error::custom(format!(
+                    "expected index \"{index}\", found \"{}\"",
+                    entry.key
+                ));
+                return Err(error.at(entry.key_range));
+            }
+        }
+
+        Ok(entries.iter().collect())
+    }
+}
+
+impl<'de> de::Deserializer<'de> for EntryDeserializer<'_, 'de> {
+    type Error = DeserializeError;
+
+    forward_to_value! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// With repeated keys, an entry is a sequence of one element.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let entries = match self.style {
+            SequenceStyle::RepeatedKeys => vec![self.entry],
+            SequenceStyle::Indexed(start) => self.indexed(start)?,
+        };
+        visitor.visit_seq(EntrySeq {
+            entries: entries.into_iter(),
+            style: self.style,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match &self.entry.value {
+            BorrowedValue::String(text) => {
+                StrDeserializer { text }.deserialize_enum(name, variants, visitor)
+            }
+            BorrowedValue::Section(entries) => SectionDeserializer {
+                entries,
+                style: self.style,
+            }
+            .deserialize_enum(name, variants, visitor),
+        }
+        .map_err(|error| error.at(self.entry.value_range))
+    }
+}
+
+/// An entry of a section read as an enum variant: its key is the variant, its value the
+/// content of the variant.
+impl<'b, 'de> EnumAccess<'de> for EntryDeserializer<'b, 'de> {
+    type Error = DeserializeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed
+            .deserialize(StrDeserializer {
+                text: &self.entry.key,
+            })
+            .map_err(|error| error.at(self.entry.key_range))?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for EntryDeserializer<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+/// A key or a string value. Numbers and booleans are parsed from the string.
+struct StrDeserializer<'b, 'de> {
+    text: &'b Cow<'de, str>,
+}
+
+impl StrDeserializer<'_, '_> {
+    fn parse<T: std::str::FromStr>(
+        &self,
+        expected: &dyn de::Expected,
+    ) -> Result<T, DeserializeError> {
+        self.text
+            .trim()
+            .parse()
+            .map_err(|_| de::Error::invalid_value(Unexpected::Str(self.text), expected))
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                let value = self.parse(&visitor)?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for StrDeserializer<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text {
+            Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+            Cow::Owned(text) => visitor.visit_str(text),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text.trim() {
+            "1" => visitor.visit_bool(true),
+            "0" => visitor.visit_bool(false),
+            text if text.eq_ignore_ascii_case("true") => visitor.visit_bool(true),
+            text if text.eq_ignore_ascii_case("false") => visitor.visit_bool(false),
+            _ => Err(de::Error::invalid_value(
+                Unexpected::Str(self.text),
+                &visitor,
+            )),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let value = self.parse(&visitor)?;
+        visitor.visit_char(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if !self.text.is_empty() {
+            return Err(de::Error::invalid_value(
+                Unexpected::Str(self.text),
+                &visitor,
+            ));
+        }
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Strings are unit variants.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.text.as_ref().into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{KvError, KvErrorKind, LineIndex, ParserConfig, Range};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Encoding of a keyvalue file, detected from its byte order mark.
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    /// UTF-8 without a byte order mark.
+    #[default]
+    Utf8,
+
+    /// UTF-8 with a byte order mark.
+    Utf8Bom,
+
+    /// UTF-16 little endian with a byte order mark, as used by the localization files of
+    /// Source games.
+    Utf16Le,
+
+    /// UTF-16 big endian with a byte order mark.
+    Utf16Be,
+}
+
+impl TextEncoding {
+    /// Detect the encoding of a file from its byte order mark. Files without one are UTF-8.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            TextEncoding::Utf8Bom
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            TextEncoding::Utf16Le
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            TextEncoding::Utf16Be
+        } else {
+            TextEncoding::Utf8
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            TextEncoding::Utf8 => &[],
+            TextEncoding::Utf8Bom => UTF8_BOM,
+            TextEncoding::Utf16Le => UTF16LE_BOM,
+            TextEncoding::Utf16Be => UTF16BE_BOM,
+        }
+    }
+
+    /// Decode bytes in this encoding. A leading byte order mark is skipped.
+    pub fn decode(self, bytes: &[u8]) -> Result<String, KvError> {
+        self.decode_with_config(bytes, ParserConfig::default())
+    }
+
+    /// Decode bytes in this encoding, locating errors with the position encoding of the
+    /// configuration.
+    pub fn decode_with_config(self, bytes: &[u8], config: ParserConfig) -> Result<String, KvError> {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        match self {
+            TextEncoding::Utf8 | TextEncoding::Utf8Bom => match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(text.to_string()),
+                Err(err) => {
+                    // The prefix before the error is valid.
+                    let valid = std::str::from_utf8(&bytes[..err.valid_up_to()])
+                        .expect("the prefix is valid UTF-8");
+                    Err(encoding_error(valid, "invalid UTF-8", config))
+                }
+            },
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    match self {
+                        TextEncoding::Utf16Le => u16::from_le_bytes(pair),
+                        _ => u16::from_be_bytes(pair),
+                    }
+                });
+                let mut text = String::with_capacity(bytes.len() / 2);
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => text.push(c),
+                        Err(_) => {
+                            return Err(encoding_error(&text, "unpaired UTF-16 surrogate", config))
+                        }
+                    }
+                }
+                if !bytes.chunks_exact(2).remainder().is_empty() {
+                    return Err(encoding_error(
+                        &text,
+                        "odd number of bytes in UTF-16 text",
+                        config,
+                    ));
+                }
+                Ok(text)
+            }
+        }
+    }
+
+    /// Encode a string in this encoding, with its byte order mark.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        let mut bytes = self.bom().to_vec();
+        match self {
+            TextEncoding::Utf8 | TextEncoding::Utf8Bom => bytes.extend_from_slice(text.as_bytes()),
+            TextEncoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            TextEncoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+        }
+
+        bytes
+    }
+}
+
+/// Decode the bytes of a keyvalue file, detecting its encoding from its byte order mark.
+///
+/// Returns the text without its byte order mark, and the encoding to write it back with.
+pub fn decode_text(bytes: &[u8]) -> Result<(String, TextEncoding), KvError> {
+    decode_text_with_config(bytes, ParserConfig::default())
+}
+
+pub fn decode_text_with_config(
+    bytes: &[u8],
+    config: ParserConfig,
+) -> Result<(String, TextEncoding), KvError> {
+    let encoding = TextEncoding::detect(bytes);
+    let text = encoding.decode_with_config(bytes, config)?;
+
+    Ok((text, encoding))
+}
+
+/// Error at the end of the text that could be decoded.
+fn encoding_error(decoded: &str, message: &str, config: ParserConfig) -> KvError {
+    let end = LineIndex::new(decoded, config.position_encoding).position(decoded.len());
+    KvError {
+        range: Range::new(end, end),
+        additional_ranges: vec![],
+        message: message.to_string(),
+        kind: KvErrorKind::EncodingError,
+    }
+}
+
+#[test]
+fn test_detect_encoding() {
+    assert_eq!(TextEncoding::detect(b"\"key\""), TextEncoding::Utf8);
+    assert_eq!(
+        TextEncoding::detect(b"\xEF\xBB\xBFkey"),
+        TextEncoding::Utf8Bom
+    );
+    assert_eq!(TextEncoding::detect(b"\xFF\xFEk\0"), TextEncoding::Utf16Le);
+    assert_eq!(TextEncoding::detect(b"\xFE\xFF\0k"), TextEncoding::Utf16Be);
+}
+
+#[test]
+fn test_encoding_round_trip() {
+    let text = "\"lang\" { \"Tokens\" { \"name\" \"Größe 😀\" } }";
+    for encoding in [
+        TextEncoding::Utf8,
+        TextEncoding::Utf8Bom,
+        TextEncoding::Utf16Le,
+        TextEncoding::Utf16Be,
+    ] {
+        let bytes = encoding.encode(text);
+        assert_eq!(decode_text(&bytes).unwrap(), (text.to_string(), encoding));
+    }
+}
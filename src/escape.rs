@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Escape sequences recognized in quoted strings
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscapeMode {
+    /// JSON-style escapes: `\"`, `\\`, `\/`, `\b`, `\f`, `\n`, `\r`, `\t` and `\uXXXX`.
+    /// Any other escape sequence is a syntax error.
+    #[default]
+    Json,
+
+    /// Escapes of the Source engine: `\n`, `\t`, `\\` and `\"`.
+    /// Any other backslash is kept as is.
+    Valve,
+
+    /// Backslashes are literal characters, as in `KeyValues::LoadFromBuffer`.
+    /// Quoted strings cannot contain quotes.
+    Disabled,
+}
+
+impl EscapeMode {
+    /// Decode the escape sequences of the inner text of a quoted string.
+    pub fn unescape(self, raw: &str) -> String {
+        match self {
+            EscapeMode::Json => unescape_string(raw),
+            EscapeMode::Valve => unescape_valve(raw),
+            EscapeMode::Disabled => raw.to_string(),
+        }
+    }
+
+    /// Escape a string so that it can be written between quotes and read back by
+    /// [`EscapeMode::unescape`]. The surrounding quotes are not added.
+    ///
+    /// With [`EscapeMode::Disabled`], the text is returned as is and must not contain quotes.
+    pub fn escape(self, text: &str) -> String {
+        match self {
+            EscapeMode::Json => escape_string(text),
+            EscapeMode::Valve => escape_valve(text),
+            EscapeMode::Disabled => text.to_string(),
+        }
+    }
+}
+
+/// Decode the escape sequences of the inner text of a quoted string.
+///
+/// Supports the JSON-style escapes accepted by the grammar: `\"`, `\\`, `\/`, `\b`, `\f`,
+/// `\n`, `\r`, `\t` and `\uXXXX`, including surrogate pairs. Invalid sequences are kept as is.
+pub fn unescape_string(raw: &str) -> String {
+    if !raw.contains('\\') {
+        return raw.to_string();
+    }
+    let mut res = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => res.push('"'),
+            Some('\\') => res.push('\\'),
+            Some('/') => res.push('/'),
+            Some('b') => res.push('\u{8}'),
+            Some('f') => res.push('\u{c}'),
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some('t') => res.push('\t'),
+            Some('u') => match read_hex(&mut chars) {
+                Some(high @ 0xD800..=0xDBFF) => {
+                    let mut lookahead = chars.clone();
+                    let low = match (lookahead.next(), lookahead.next()) {
+                        (Some('\\'), Some('u')) => read_hex(&mut lookahead),
+                        _ => None,
+                    };
+                    match low {
+                        Some(low @ 0xDC00..=0xDFFF) => {
+                            chars = lookahead;
+                            let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                            res.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        _ => res.push(char::REPLACEMENT_CHARACTER),
+                    }
+                }
+                Some(code) => res.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)),
+                None => res.push_str("\\u"),
+            },
+            Some(c) => {
+                res.push('\\');
+                res.push(c);
+            }
+            None => res.push('\\'),
+        }
+    }
+
+    res
+}
+
+/// Escape a string so that it can be written between quotes and read back by
+/// [`unescape_string`]. The surrounding quotes are not added.
+pub fn escape_string(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\u{8}' => res.push_str("\\b"),
+            '\u{c}' => res.push_str("\\f"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+fn unescape_valve(raw: &str) -> String {
+    if !raw.contains('\\') {
+        return raw.to_string();
+    }
+    let mut res = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('\\') => res.push('\\'),
+            Some('"') => res.push('"'),
+            Some(c) => {
+                res.push('\\');
+                res.push(c);
+            }
+            None => res.push('\\'),
+        }
+    }
+
+    res
+}
+
+fn escape_valve(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// Read the 4 hexadecimal digits of a `\u` escape, consuming them only if they are valid.
+fn read_hex(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<u32> {
+    let digits: String = chars.clone().take(4).collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    for _ in 0..4 {
+        chars.next();
+    }
+    u32::from_str_radix(&digits, 16).ok()
+}
@@ -2,16 +2,17 @@ use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use self::emitter::Emitter;
-use crate::Rule;
+use crate::{decode_text, EscapeMode, KvError};
 
-pub(crate) mod collectors;
+mod collectors;
 mod emitter;
 mod tokens;
+mod writer;
 
-pub fn format_keyvalue(
-    input: &str,
-    config: FormatterConfig,
-) -> Result<String, Box<pest::error::Error<Rule>>> {
+pub(crate) use self::writer::TokenWriter;
+
+/// Format a string of keyvalue. Fails with the first syntax error of the input.
+pub fn format_keyvalue(input: &str, config: FormatterConfig) -> Result<String, KvError> {
     let mut emitter = Emitter::new(config);
     emitter.collect_tokens(input)?;
     emitter.emit();
@@ -19,6 +20,17 @@ pub fn format_keyvalue(
     Ok(emitter.buffer.join("\n"))
 }
 
+/// Format the bytes of a keyvalue file, such as a UTF-16 localization file.
+///
+/// The encoding is detected from the byte order mark of the input, and the output is written
+/// back in the same encoding, with the same byte order mark.
+pub fn format_keyvalue_bytes(input: &[u8], config: FormatterConfig) -> Result<Vec<u8>, KvError> {
+    let (text, encoding) = decode_text(input)?;
+    let output = format_keyvalue(&text, config)?;
+
+    Ok(encoding.encode(&output))
+}
+
 /// Configuration options for the formatter
 #[wasm_bindgen]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -34,6 +46,29 @@ pub struct FormatterConfig {
     /// Maximum number of consecutive empty lines
     #[wasm_bindgen(js_name = maxEmptyLines)]
     pub max_empty_lines: u32,
+
+    /// How the formatter should quote keys and values
+    #[wasm_bindgen(js_name = quoteStyle)]
+    pub quote_style: QuoteStyle,
+
+    /// Escape sequences recognized in quoted strings
+    #[wasm_bindgen(js_name = escapeMode)]
+    pub escape_mode: EscapeMode,
+}
+
+/// Quoting strategy for keys and values
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuoteStyle {
+    /// Keep keys and values quoted or unquoted as they are in the input
+    #[default]
+    Preserve,
+
+    /// Quote every key and value
+    Always,
+
+    /// Only quote keys and values that cannot be written without quotes
+    Minimal,
 }
 
 impl Default for FormatterConfig {
@@ -42,6 +77,8 @@ impl Default for FormatterConfig {
             use_tabs: false,
             indent_size: 4,
             max_empty_lines: 1,
+            quote_style: QuoteStyle::Preserve,
+            escape_mode: EscapeMode::Json,
         }
     }
 }
@@ -58,17 +95,31 @@ fn test_formatter_config() {
     assert_eq!(config.max_empty_lines, 1);
     config.max_empty_lines = 2;
     assert_eq!(config.max_empty_lines, 2);
+    assert_eq!(config.quote_style, QuoteStyle::Preserve);
+    config.quote_style = QuoteStyle::Always;
+    assert_eq!(config.quote_style, QuoteStyle::Always);
+    assert_eq!(config.escape_mode, EscapeMode::Json);
+    config.escape_mode = EscapeMode::Disabled;
+    assert_eq!(config.escape_mode, EscapeMode::Disabled);
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 impl FormatterConfig {
     #[wasm_bindgen(constructor)]
-    pub fn new(use_tabs: bool, indent_size: u32, max_empty_lines: u32) -> Self {
+    pub fn new(
+        use_tabs: bool,
+        indent_size: u32,
+        max_empty_lines: u32,
+        quote_style: QuoteStyle,
+        escape_mode: EscapeMode,
+    ) -> Self {
         Self {
             use_tabs,
             indent_size,
             max_empty_lines,
+            quote_style,
+            escape_mode,
         }
     }
 }
@@ -1,151 +1,103 @@
-use pest::{iterators::Pair, Parser, Span};
-
-use crate::{KeyValueParser, Position, Range, Rule};
+use crate::{
+    parser::{parse_tree, Parse},
+    KvError, LineIndex, ParserConfig, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken,
+    SyntaxTree,
+};
 
 use super::{
     emitter::Emitter,
     tokens::{KvToken, TokenKind},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
-pub(crate) struct RangeCollector {
-    /// Indexes of line breaks in the input string, in reverse order.
-    pub(super) line_breaks: Vec<usize>,
-    pub(super) line_nb: usize,
-    pub(super) first_col_line: usize,
+/// Walks a syntax tree to collect the tokens of the emitter, in source order.
+struct TokenCollector<'a> {
+    tree: &'a SyntaxTree,
+    line_index: LineIndex,
+    tokens: Vec<TokenKind>,
 }
 
-impl RangeCollector {
-    pub(crate) fn collect_linebreaks(&mut self, input: &str) {
-        for (i, c) in input.chars().enumerate() {
-            if c == '\n' {
-                self.line_breaks.push(i);
-            }
-        }
-        self.line_breaks.reverse();
-    }
-
-    pub(crate) fn span_to_range(&mut self, span: Span) -> Range {
-        let mut line_nb = self.line_nb;
-        let mut first_col_line = self.first_col_line;
-        while let Some(line_break) = self.line_breaks.last() {
-            if *line_break >= span.end() {
-                break;
-            }
-            if *line_break < span.start() {
-                first_col_line = *line_break + 1;
-                line_nb += 1;
-            }
-            self.first_col_line = *line_break + 1;
-            self.line_nb += 1;
-            self.line_breaks.pop();
+impl Emitter {
+    pub(super) fn collect_tokens(&mut self, input: &str) -> Result<(), KvError> {
+        let config = ParserConfig {
+            escape_mode: self.config.escape_mode,
+            ..Default::default()
+        };
+        let Parse {
+            tree,
+            errors,
+            line_index,
+        } = parse_tree(input, config);
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error);
         }
+        let mut collector = TokenCollector {
+            tree: &tree,
+            line_index,
+            tokens: vec![],
+        };
+        collector.collect_node(&tree.root);
+        self.tokens = collector.tokens;
 
-        Range {
-            start: Position {
-                line: line_nb as u32,
-                character: (span.start() - first_col_line) as u32,
-            },
-            end: Position {
-                line: self.line_nb as u32,
-                character: (span.end() - self.first_col_line) as u32,
-            },
-        }
+        Ok(())
     }
 }
 
-impl Emitter {
-    pub(super) fn collect_tokens(
-        &mut self,
-        input: &str,
-    ) -> Result<(), Box<pest::error::Error<Rule>>> {
-        self.range_collector.collect_linebreaks(input);
-        let pairs = KeyValueParser::parse(Rule::start, input)?;
-
-        for pair in pairs {
-            if let Rule::start = pair.as_rule() {
-                for sub_pair in pair.into_inner() {
-                    match sub_pair.as_rule() {
-                        Rule::keyvalue => self.collect_keyvalue(sub_pair),
-                        Rule::COMMENT => self.collect_comment(sub_pair),
-                        Rule::EOI => (),
-                        _ => eprintln!(
-                            "unhandled rule in token collection: {:?}",
-                            sub_pair.as_rule()
-                        ),
-                    }
+impl TokenCollector<'_> {
+    fn collect_node(&mut self, node: &SyntaxNode) {
+        if node.kind == SyntaxKind::Directive {
+            self.collect_directive(node);
+            return;
+        }
+        // The first string of a keyvalue is its key, the second one its value.
+        let mut strings = 0;
+        for child in node.children.iter() {
+            match child {
+                SyntaxElement::Node(node) => self.collect_node(node),
+                SyntaxElement::Token(token) => {
+                    let kv_token = self.kv_token(token);
+                    let token_kind = match token.kind {
+                        SyntaxKind::Whitespace => continue,
+                        SyntaxKind::LineComment => TokenKind::LineComment(kv_token),
+                        SyntaxKind::BlockComment => TokenKind::BlockComment(kv_token),
+                        SyntaxKind::LBrace => TokenKind::LBrace(kv_token),
+                        SyntaxKind::RBrace => TokenKind::RBrace(kv_token),
+                        SyntaxKind::Conditional => TokenKind::Conditional(kv_token),
+                        _ if strings == 0 => {
+                            strings += 1;
+                            TokenKind::Key(kv_token)
+                        }
+                        _ => TokenKind::Value(kv_token),
+                    };
+                    self.tokens.push(token_kind);
                 }
             }
         }
-
-        Ok(())
     }
 
-    fn collect_keyvalue(&mut self, pair: Pair<Rule>) {
-        let pair_inner = pair.into_inner();
-        for sub_pair in pair_inner {
-            match sub_pair.as_rule() {
-                Rule::key => {
-                    let token = KvToken {
-                        text: sub_pair.as_str().to_string(),
-                        range: self.range_collector.span_to_range(sub_pair.as_span()),
-                    };
-                    self.tokens.push(TokenKind::Key(token))
-                }
-                Rule::value => {
-                    let token = KvToken {
-                        text: sub_pair.as_str().to_string(),
-                        range: self.range_collector.span_to_range(sub_pair.as_span()),
-                    };
-                    self.tokens.push(TokenKind::Value(token))
+    fn collect_directive(&mut self, node: &SyntaxNode) {
+        let mut text = String::new();
+        for token in node.child_tokens() {
+            match token.kind {
+                SyntaxKind::Whitespace => text.push(' '),
+                SyntaxKind::UnquotedString if text.is_empty() => {
+                    text.push_str(&self.tree.text(token).to_lowercase())
                 }
-                Rule::section => self.collect_section(sub_pair),
-                Rule::COMMENT => self.collect_comment(sub_pair),
-                _ => eprintln!(
-                    "unhandled rule in keyvalue collection: {:?}",
-                    sub_pair.as_rule()
-                ),
+                _ => text.push_str(self.tree.text(token)),
             }
         }
-    }
-
-    fn collect_comment(&mut self, pair: Pair<Rule>) {
         let token = KvToken {
-            text: pair.as_str().to_string(),
-            range: self.range_collector.span_to_range(pair.as_span()),
+            text,
+            range: self.line_index.range(node.start, node.end),
+            ..Default::default()
         };
-        if pair.as_str().starts_with("//") {
-            self.tokens.push(TokenKind::LineComment(token))
-        } else {
-            self.tokens.push(TokenKind::BlockComment(token))
-        }
+        self.tokens.push(TokenKind::Directive(token));
     }
 
-    fn collect_section(&mut self, pair: Pair<Rule>) {
-        let pair_inner = pair.into_inner();
-        for sub_pair in pair_inner {
-            match sub_pair.as_rule() {
-                Rule::l_brace => {
-                    let token = KvToken {
-                        text: "{".to_string(),
-                        range: self.range_collector.span_to_range(sub_pair.as_span()),
-                    };
-                    self.tokens.push(TokenKind::LBrace(token));
-                }
-                Rule::r_brace => {
-                    let token = KvToken {
-                        text: "}".to_string(),
-                        range: self.range_collector.span_to_range(sub_pair.as_span()),
-                    };
-                    self.tokens.push(TokenKind::RBrace(token));
-                }
-                Rule::keyvalue => self.collect_keyvalue(sub_pair),
-                Rule::COMMENT => self.collect_comment(sub_pair),
-                _ => eprintln!(
-                    "unhandled rule in section collection: {:?}",
-                    sub_pair.as_rule()
-                ),
-            }
+    fn kv_token(&self, token: &SyntaxToken) -> KvToken {
+        KvToken {
+            text: self.tree.text(token).to_string(),
+            range: self.line_index.range(token.start, token.end),
+            quoted: token.kind == SyntaxKind::QuotedString,
         }
     }
 }
@@ -1,19 +1,15 @@
-use super::{
-    collectors::RangeCollector,
-    tokens::{KvToken, TokenKind},
-};
-use crate::FormatterConfig;
+use super::tokens::{KvToken, TokenKind};
+use crate::{FormatterConfig, QuoteStyle};
 
 use std::cmp::min;
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub(super) struct Emitter {
     pub(super) buffer: Vec<String>,
-    config: FormatterConfig,
+    pub(super) config: FormatterConfig,
     indent: usize,
     current_line: String,
     pub(super) tokens: Vec<TokenKind>,
-    pub(super) range_collector: RangeCollector,
     prev_token: Option<TokenKind>,
 }
 
@@ -35,18 +31,18 @@ impl Emitter {
                 TokenKind::Value(token) => self.emit_value(token),
                 TokenKind::LineComment(token) => self.emit_line_comment(token),
                 TokenKind::BlockComment(token) => self.emit_block_comment(token),
+                TokenKind::Conditional(token) => self.emit_conditional(token),
+                TokenKind::Directive(token) => self.emit_directive(token),
                 TokenKind::LBrace(token) => {
                     if let Some(prev_token_kind) = &self.prev_token {
                         match prev_token_kind {
                             TokenKind::BlockComment(prev_token)
-                            | TokenKind::LineComment(prev_token) => {
-                                let diff =
-                                    token.range.start.line.abs_diff(prev_token.range.start.line);
-                                for _ in 0..min(diff, self.config.max_empty_lines) {
-                                    self.push_line();
-                                }
+                            | TokenKind::LineComment(prev_token)
+                                if token.range.start.line != prev_token.range.start.line =>
+                            {
+                                self.start_line(token);
                             }
-                            TokenKind::Key(_) => {
+                            TokenKind::Key(_) | TokenKind::Conditional(_) => {
                                 self.push_line();
                             }
                             _ => (),
@@ -57,23 +53,17 @@ impl Emitter {
                     self.indent += 1;
                 }
                 TokenKind::RBrace(token) => {
-                    if let Some(prev_token_kind) = &self.prev_token {
-                        match prev_token_kind {
+                    match &self.prev_token {
+                        Some(
                             TokenKind::BlockComment(prev_token)
                             | TokenKind::LineComment(prev_token)
-                            | TokenKind::Value(prev_token) => {
-                                let diff =
-                                    token.range.start.line.abs_diff(prev_token.range.start.line);
-                                if diff == 0 {
-                                    self.current_line.push_str("  ");
-                                } else {
-                                    for _ in 0..min(diff, self.config.max_empty_lines) {
-                                        self.push_line();
-                                    }
-                                }
-                            }
-                            _ => (),
+                            | TokenKind::Value(prev_token)
+                            | TokenKind::Conditional(prev_token),
+                        ) if token.range.start.line == prev_token.range.start.line => {
+                            self.current_line.push_str("  ");
                         }
+                        // Closing braces go on their own line, even after another brace.
+                        _ => self.start_line(token),
                     }
                     self.indent -= 1;
                     self.current_line.push('}');
@@ -85,15 +75,16 @@ impl Emitter {
     }
 
     fn emit_key(&mut self, token: &KvToken) {
-        self.push_line();
-        self.current_line.push_str(token.text.as_str());
+        self.start_line(token);
+        let text = self.string_text(token, self.indent == 0);
+        self.current_line.push_str(text.as_str());
     }
 
     fn emit_value(&mut self, token: &KvToken) {
         if let Some(prev_token_kind) = &self.prev_token {
             match prev_token_kind {
                 TokenKind::Key(_) => {
-                    self.current_line.push_str(self.indent_string().as_str());
+                    self.current_line.push_str(self.separator().as_str());
                 }
                 TokenKind::LineComment(prev_token) => {
                     // This should not be possible, but just in case.
@@ -101,9 +92,7 @@ impl Emitter {
                     if diff == 0 {
                         self.current_line.push_str("  ");
                     } else {
-                        for _ in 0..min(diff, self.config.max_empty_lines) {
-                            self.push_line();
-                        }
+                        self.start_line(token);
                     }
                 }
                 TokenKind::BlockComment(prev_token) => {
@@ -111,9 +100,33 @@ impl Emitter {
                     if diff == 0 {
                         self.current_line.push_str("  ");
                     } else {
-                        for _ in 0..min(diff, self.config.max_empty_lines) {
-                            self.push_line();
-                        }
+                        self.start_line(token);
+                    }
+                }
+                _ => (),
+            }
+        }
+        let text = self.string_text(token, false);
+        self.current_line.push_str(text.as_str());
+    }
+
+    fn emit_directive(&mut self, token: &KvToken) {
+        self.start_line(token);
+        self.current_line.push_str(token.text.as_str());
+    }
+
+    fn emit_conditional(&mut self, token: &KvToken) {
+        if let Some(prev_token_kind) = &self.prev_token {
+            match prev_token_kind {
+                TokenKind::Key(_) | TokenKind::Value(_) => {
+                    self.current_line.push_str(self.separator().as_str());
+                }
+                TokenKind::LineComment(prev_token) | TokenKind::BlockComment(prev_token) => {
+                    let diff = token.range.start.line.abs_diff(prev_token.range.start.line);
+                    if diff == 0 {
+                        self.current_line.push_str("  ");
+                    } else {
+                        self.start_line(token);
                     }
                 }
                 _ => (),
@@ -122,6 +135,25 @@ impl Emitter {
         self.current_line.push_str(token.text.as_str());
     }
 
+    /// Text of a key or value token, quoted according to the configured [`QuoteStyle`].
+    fn string_text(&self, token: &KvToken, root_key: bool) -> String {
+        match self.config.quote_style {
+            QuoteStyle::Preserve => token.text.clone(),
+            QuoteStyle::Always if !token.quoted => {
+                format!("\"{}\"", self.config.escape_mode.escape(&token.text))
+            }
+            QuoteStyle::Minimal if token.quoted => {
+                let inner = &token.text[1..token.text.len() - 1];
+                if can_be_unquoted(inner, root_key) {
+                    inner.to_string()
+                } else {
+                    token.text.clone()
+                }
+            }
+            _ => token.text.clone(),
+        }
+    }
+
     fn emit_line_comment(&mut self, token: &KvToken) {
         if let Some(prev_token_kind) = &self.prev_token {
             match prev_token_kind {
@@ -132,14 +164,14 @@ impl Emitter {
                 | TokenKind::RBrace(prev_token)
                 | TokenKind::BlockComment(prev_token)
                 | TokenKind::LineComment(prev_token)
-                | TokenKind::Value(prev_token) => {
+                | TokenKind::Value(prev_token)
+                | TokenKind::Conditional(prev_token)
+                | TokenKind::Directive(prev_token) => {
                     let diff = token.range.start.line.abs_diff(prev_token.range.start.line);
                     if diff == 0 {
                         self.current_line.push_str("  ");
                     } else {
-                        for _ in 0..min(diff, self.config.max_empty_lines) {
-                            self.push_line();
-                        }
+                        self.start_line(token);
                     }
                 }
             }
@@ -158,14 +190,14 @@ impl Emitter {
                 | TokenKind::RBrace(prev_token)
                 | TokenKind::BlockComment(prev_token)
                 | TokenKind::LineComment(prev_token)
-                | TokenKind::Value(prev_token) => {
+                | TokenKind::Value(prev_token)
+                | TokenKind::Conditional(prev_token)
+                | TokenKind::Directive(prev_token) => {
                     let diff = token.range.start.line.abs_diff(prev_token.range.start.line);
                     if diff == 0 {
                         self.current_line.push_str("  ");
                     } else {
-                        for _ in 0..min(diff, self.config.max_empty_lines) {
-                            self.push_line();
-                        }
+                        self.start_line(token);
                     }
                 }
             }
@@ -181,10 +213,35 @@ impl Emitter {
         }
     }
 
+    /// Whitespace between a key and its value or conditional. Strings must be separated
+    /// even when there is no indentation.
+    fn separator(&self) -> String {
+        match self.indent_string() {
+            separator if separator.is_empty() => " ".to_string(),
+            separator => separator,
+        }
+    }
+
     fn indent(&self) -> String {
         self.indent_string().repeat(self.indent)
     }
 
+    /// Start a new line for a token, keeping up to `max_empty_lines` empty lines from the
+    /// input before it.
+    fn start_line(&mut self, token: &KvToken) {
+        self.push_line();
+        if let Some(prev_token_kind) = &self.prev_token {
+            let empty_lines = token
+                .range
+                .start
+                .line
+                .saturating_sub(prev_token_kind.token().range.end.line + 1);
+            for _ in 0..min(empty_lines, self.config.max_empty_lines) {
+                self.buffer.push(String::new());
+            }
+        }
+    }
+
     fn push_line(&mut self) {
         if self.current_line.is_empty() {
             return;
@@ -194,3 +251,17 @@ impl Emitter {
         self.current_line.clear();
     }
 }
+
+/// Whether a string can be written without quotes and still be read back as the same token.
+pub(super) fn can_be_unquoted(text: &str, root_key: bool) -> bool {
+    // An unquoted root key starting with `#` is read as a directive, e.g. `#base`, and an
+    // unquoted `[` starts a conditional.
+    if text.is_empty() || text.starts_with('[') || (root_key && text.starts_with('#')) {
+        return false;
+    }
+    !text.contains("//")
+        && !text.contains("/*")
+        && !text
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '{' | '}' | '\\'))
+}
@@ -4,6 +4,9 @@ use crate::Range;
 pub(super) struct KvToken {
     pub(super) text: String,
     pub(super) range: Range,
+    /// Whether the token was surrounded by quotes in the source.
+    /// Only meaningful for keys and values.
+    pub(super) quoted: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,4 +17,21 @@ pub(super) enum TokenKind {
     BlockComment(KvToken),
     LBrace(KvToken),
     RBrace(KvToken),
+    Conditional(KvToken),
+    Directive(KvToken),
+}
+
+impl TokenKind {
+    pub(super) fn token(&self) -> &KvToken {
+        match self {
+            TokenKind::Key(token)
+            | TokenKind::Value(token)
+            | TokenKind::LineComment(token)
+            | TokenKind::BlockComment(token)
+            | TokenKind::LBrace(token)
+            | TokenKind::RBrace(token)
+            | TokenKind::Conditional(token)
+            | TokenKind::Directive(token) => token,
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use crate::{EscapeMode, FormatterConfig, Position, Range};
+
+use super::{
+    emitter::{can_be_unquoted, Emitter},
+    tokens::{KvToken, TokenKind},
+};
+
+/// Collects the tokens of entries built in memory rather than read from a text, to lay
+/// them out with the rules of the emitter.
+///
+/// Quoted strings are escaped, and the quotes are then kept, added or removed according to
+/// the [`QuoteStyle`](crate::QuoteStyle) of the configuration.
+pub(crate) struct TokenWriter {
+    emitter: Emitter,
+    /// Line given to the next token. Every token is put on its own line, so that the
+    /// emitter breaks the lines between entries and around braces.
+    line: u32,
+    /// Number of sections opened and not closed yet.
+    depth: usize,
+}
+
+impl TokenWriter {
+    pub(crate) fn new(config: FormatterConfig) -> Self {
+        Self {
+            emitter: Emitter::new(config),
+            line: 0,
+            depth: 0,
+        }
+    }
+
+    /// Write a key, quoted or not. Keys that cannot be written without quotes are quoted
+    /// anyway.
+    ///
+    /// Fails with the text of the key if it cannot be written in the escape mode of the
+    /// configuration, i.e. if it contains a quote while escapes are disabled.
+    pub(crate) fn key(&mut self, text: &str, quoted: bool) -> Result<(), String> {
+        let token = self.string(text, quoted, self.depth == 0)?;
+        self.emitter.tokens.push(TokenKind::Key(token));
+        Ok(())
+    }
+
+    /// Write the value of the last key, see [`TokenWriter::key`].
+    pub(crate) fn value(&mut self, text: &str, quoted: bool) -> Result<(), String> {
+        let token = self.string(text, quoted, false)?;
+        self.emitter.tokens.push(TokenKind::Value(token));
+        Ok(())
+    }
+
+    /// Write a conditional, without its brackets.
+    pub(crate) fn conditional(&mut self, text: &str) {
+        let token = self.token(format!("[{text}]"));
+        self.emitter.tokens.push(TokenKind::Conditional(token));
+    }
+
+    /// Write a comment, with its delimiters.
+    pub(crate) fn comment(&mut self, text: &str) {
+        let token = self.token(text.to_string());
+        if text.starts_with("//") {
+            self.emitter.tokens.push(TokenKind::LineComment(token));
+        } else {
+            self.emitter.tokens.push(TokenKind::BlockComment(token));
+        }
+    }
+
+    pub(crate) fn open_section(&mut self) {
+        let token = self.token("{".to_string());
+        self.emitter.tokens.push(TokenKind::LBrace(token));
+        self.depth += 1;
+    }
+
+    pub(crate) fn close_section(&mut self) {
+        let token = self.token("}".to_string());
+        self.emitter.tokens.push(TokenKind::RBrace(token));
+        self.depth -= 1;
+    }
+
+    /// Lay out the tokens written so far.
+    pub(crate) fn finish(mut self) -> String {
+        self.emitter.emit();
+        self.emitter.buffer.join("\n")
+    }
+
+    fn string(&mut self, text: &str, quoted: bool, root_key: bool) -> Result<KvToken, String> {
+        if !quoted && can_be_unquoted(text, root_key) {
+            return Ok(self.token(text.to_string()));
+        }
+        let escape_mode = self.emitter.config.escape_mode;
+        if escape_mode == EscapeMode::Disabled && text.contains('"') {
+            return Err(text.to_string());
+        }
+        let mut token = self.token(format!("\"{}\"", escape_mode.escape(text)));
+        token.quoted = true;
+
+        Ok(token)
+    }
+
+    fn token(&mut self, text: String) -> KvToken {
+        let position = Position::new(self.line, 0);
+        self.line += 1;
+
+        KvToken {
+            text,
+            range: Range::new(position, position),
+            quoted: false,
+        }
+    }
+}
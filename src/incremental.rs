@@ -0,0 +1,320 @@
+use crate::{
+    parse_document_with_config,
+    parser::{lex_token, parse_section_content, SyntaxError},
+    serializer::Serializer,
+    Directive, KeyValue, KvError, KvErrorKind, LineIndex, ParseResult, ParserConfig, Range, Span,
+    SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, Value,
+};
+
+/// Replacement of a range of a text document, as sent by editors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Range of the replaced text, in the position encoding of the parser configuration.
+    pub range: Range,
+
+    /// Text inserted in place of the range.
+    pub text: String,
+}
+
+/// Apply an edit to a parsed document, and parse the edited text as
+/// [`parse_document_with_config`] would.
+///
+/// Only the innermost section holding the edit between its braces is parsed again, the rest
+/// of the tree and of the document is moved to its new position. The whole text is parsed
+/// again when no section holds the edit, or when the edited section does not parse on its
+/// own, e.g. when the edit removes one of its braces.
+pub fn reparse_document(
+    previous: ParseResult,
+    edit: &TextEdit,
+    config: ParserConfig,
+) -> ParseResult {
+    let mut result = previous;
+    let mut source = std::mem::take(&mut result.tree.source);
+    let mut line_index = LineIndex::new(&source, config.position_encoding);
+    let start = line_index.offset(&source, edit.range.start);
+    let end = line_index.offset(&source, edit.range.end).max(start);
+    // Offsets of the errors in the text before the edit.
+    let error_spans: Vec<_> = result
+        .errors
+        .iter()
+        .map(|error| {
+            (
+                line_index.offset(&source, error.range.start),
+                line_index.offset(&source, error.range.end),
+            )
+        })
+        .collect();
+    source.replace_range(start..end, &edit.text);
+    line_index.replace(start, end, &edit.text);
+    let delta = edit.text.len() as isize - (end - start) as isize;
+
+    let mut paths = vec![];
+    find_sections(&result.tree.root, start, end, &mut vec![], &mut paths);
+    // Try the innermost section first.
+    for path in paths.iter().rev() {
+        let section = node_at(&result.tree.root, path);
+        // An unterminated string is cut where the text after it stops matching, which
+        // could be anywhere after it.
+        if error_spans.iter().any(|(error_start, _)| {
+            *error_start < section.start
+                && token_at(&result.tree.root, *error_start)
+                    .is_some_and(|token| token.kind == SyntaxKind::UnterminatedString)
+        }) {
+            break;
+        }
+        let shift = Shift {
+            threshold: section.end - 1,
+            delta,
+        };
+        let content_start = section.start + 1;
+        let Some((children, errors)) =
+            reparse_content(&source, content_start, shift.apply(section.end - 1), config)
+        else {
+            continue;
+        };
+
+        let mut new_errors = vec![];
+        let mut after = vec![];
+        for (error, (error_start, error_end)) in std::mem::take(&mut result.errors)
+            .into_iter()
+            .zip(&error_spans)
+        {
+            if *error_start < content_start {
+                new_errors.push(error);
+            } else if *error_start >= shift.threshold {
+                after.push(KvError {
+                    range: line_index.range(shift.apply(*error_start), shift.apply(*error_end)),
+                    ..error
+                });
+            }
+        }
+        new_errors.extend(errors.into_iter().map(|error| KvError {
+            range: line_index.range(error.start, error.end),
+            additional_ranges: vec![],
+            message: error.message,
+            kind: KvErrorKind::SyntaxError,
+        }));
+        new_errors.extend(after);
+        result.errors = new_errors;
+
+        shift.node(&mut result.tree.root);
+        let section = node_at_mut(&mut result.tree.root, path);
+        let len = section.children.len();
+        section.children.splice(1..len - 1, children);
+
+        for kv in result.document.roots.iter_mut() {
+            shift.keyvalue(kv, &line_index);
+        }
+        for directive in result.document.directives.iter_mut() {
+            shift.directive(directive, &line_index);
+        }
+        // Serialize the entry of the section again, with the comments around it.
+        let (parent_path, [index, _]) = path.split_at(path.len() - 2) else {
+            unreachable!("paths lead to sections of entries");
+        };
+        let parent = node_at(&result.tree.root, parent_path);
+        let entry = Serializer::new(&source, line_index, config)
+            .serialize_entry(parent, *index)
+            .into_owned();
+        *keyvalue_at(&result.tree.root, &mut result.document.roots, path)
+            .expect("paths lead to sections of entries") = entry;
+        result.tree.source = source;
+
+        return result;
+    }
+
+    parse_document_with_config(&source, config)
+}
+
+/// Lex and parse the text between the braces of a section of the edited `source`.
+fn reparse_content(
+    source: &str,
+    start: usize,
+    end: usize,
+    config: ParserConfig,
+) -> Option<(Vec<SyntaxElement>, Vec<SyntaxError>)> {
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    let mut pos = start;
+    while pos < end {
+        let lexed =
+            lex_token(source, pos, config.escape_mode).expect("the position is in the text");
+        pos = lexed.token.end;
+        tokens.push(lexed.token);
+        errors.extend(lexed.errors);
+    }
+    // The last token goes past the closing brace, e.g. an opened block comment.
+    if pos != end {
+        return None;
+    }
+
+    parse_section_content(source, tokens, errors)
+}
+
+/// Collect the paths of child indices to the closed sections holding the range between their
+/// braces, outermost first. Sections are only searched through entries.
+fn find_sections(
+    node: &SyntaxNode,
+    start: usize,
+    end: usize,
+    path: &mut Vec<usize>,
+    paths: &mut Vec<Vec<usize>>,
+) {
+    for (i, child) in node.children.iter().enumerate() {
+        let SyntaxElement::Node(kv) = child else {
+            continue;
+        };
+        if kv.kind != SyntaxKind::KeyValue || kv.end < end || kv.start > start {
+            continue;
+        }
+        for (j, child) in kv.children.iter().enumerate() {
+            let SyntaxElement::Node(section) = child else {
+                continue;
+            };
+            let closed = matches!(
+                section.children.last(),
+                Some(SyntaxElement::Token(SyntaxToken {
+                    kind: SyntaxKind::RBrace,
+                    ..
+                }))
+            );
+            if section.kind != SyntaxKind::Section
+                || !closed
+                || start <= section.start
+                || end >= section.end
+            {
+                continue;
+            }
+            path.extend([i, j]);
+            paths.push(path.clone());
+            find_sections(section, start, end, path, paths);
+            path.truncate(path.len() - 2);
+        }
+    }
+}
+
+/// Token at an offset of the tree.
+fn token_at(node: &SyntaxNode, offset: usize) -> Option<&SyntaxToken> {
+    node.children.iter().find_map(|child| match child {
+        SyntaxElement::Node(node) if node.start <= offset && offset < node.end => {
+            token_at(node, offset)
+        }
+        SyntaxElement::Token(token) if token.start <= offset && offset < token.end => Some(token),
+        _ => None,
+    })
+}
+
+fn node_at<'a>(root: &'a SyntaxNode, path: &[usize]) -> &'a SyntaxNode {
+    path.iter().fold(root, |node, i| match &node.children[*i] {
+        SyntaxElement::Node(child) => child,
+        SyntaxElement::Token(_) => unreachable!("paths lead to nodes"),
+    })
+}
+
+fn node_at_mut<'a>(root: &'a mut SyntaxNode, path: &[usize]) -> &'a mut SyntaxNode {
+    path.iter()
+        .fold(root, |node, i| match &mut node.children[*i] {
+            SyntaxElement::Node(child) => child,
+            SyntaxElement::Token(_) => unreachable!("paths lead to nodes"),
+        })
+}
+
+/// Entry of the section at `path` in the tree, `None` for the root.
+fn keyvalue_at<'a>(
+    node: &SyntaxNode,
+    kvs: &'a mut [KeyValue],
+    path: &[usize],
+) -> Option<&'a mut KeyValue> {
+    let [i, j, rest @ ..] = path else {
+        return None;
+    };
+    // Entries are the keyvalue nodes, error nodes are skipped.
+    let index = node.children[..*i]
+        .iter()
+        .filter(
+            |child| matches!(child, SyntaxElement::Node(node) if node.kind == SyntaxKind::KeyValue),
+        )
+        .count();
+    let kv = &mut kvs[index];
+    if rest.is_empty() {
+        return Some(kv);
+    }
+    let Value::Section(section) = &mut kv.value else {
+        unreachable!("paths lead to sections");
+    };
+
+    keyvalue_at(node_at(node, &[*i, *j]), section, rest)
+}
+
+/// Moves the offsets at or after `threshold`, the closing brace of the edited section.
+#[derive(Clone, Copy)]
+struct Shift {
+    threshold: usize,
+    delta: isize,
+}
+
+impl Shift {
+    fn apply(self, offset: usize) -> usize {
+        if offset >= self.threshold {
+            offset.saturating_add_signed(self.delta)
+        } else {
+            offset
+        }
+    }
+
+    fn span(self, span: Span) -> Span {
+        Span::new(self.apply(span.start), self.apply(span.end))
+    }
+
+    fn node(self, node: &mut SyntaxNode) {
+        if node.end < self.threshold {
+            return;
+        }
+        node.start = self.apply(node.start);
+        node.end = self.apply(node.end);
+        for child in node.children.iter_mut() {
+            match child {
+                SyntaxElement::Node(node) => self.node(node),
+                SyntaxElement::Token(token) => {
+                    token.start = self.apply(token.start);
+                    token.end = self.apply(token.end);
+                }
+            }
+        }
+    }
+
+    fn keyvalue(self, kv: &mut KeyValue, line_index: &LineIndex) {
+        if kv.span.end < self.threshold {
+            return;
+        }
+        let range = |span: Span| line_index.range(span.start, span.end);
+        kv.key_span = self.span(kv.key_span);
+        kv.value_span = self.span(kv.value_span);
+        kv.span = self.span(kv.span);
+        kv.key_range = range(kv.key_span);
+        kv.value_range = range(kv.value_span);
+        kv.range = range(kv.span);
+        let value_span = kv.value_span;
+        if let Some(l_brace_range) = kv.l_brace_range.as_mut() {
+            *l_brace_range = line_index.range(value_span.start, value_span.start + 1);
+        }
+        if let Some(r_brace_range) = kv.r_brace_range.as_mut() {
+            *r_brace_range = line_index.range(value_span.end - 1, value_span.end);
+        }
+        if let Value::Section(section) = &mut kv.value {
+            for kv in section.iter_mut() {
+                self.keyvalue(kv, line_index);
+            }
+        }
+    }
+
+    fn directive(self, directive: &mut Directive, line_index: &LineIndex) {
+        if directive.span.end < self.threshold {
+            return;
+        }
+        directive.path_span = self.span(directive.path_span);
+        directive.span = self.span(directive.span);
+        directive.path_range = line_index.range(directive.path_span.start, directive.path_span.end);
+        directive.range = line_index.range(directive.span.start, directive.span.end);
+    }
+}
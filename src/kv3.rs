@@ -0,0 +1,395 @@
+use pest::{iterators::Pair, Parser};
+
+use self::grammar::{Kv3Parser, Rule};
+use crate::{EscapeMode, FormatterConfig, KvError, KvErrorKind, LineIndex, ParserConfig, Range};
+
+pub use self::{
+    formatter::format_kv3,
+    linter::{lint_kv3, lint_kv3_with_config},
+};
+
+mod formatter;
+mod linter;
+
+mod grammar {
+    use pest_derive::Parser;
+
+    /// Pest grammar of KeyValues3, the text format of Source 2.
+    #[derive(Parser)]
+    #[grammar = "kv3.pest"]
+    pub(super) struct Kv3Parser;
+}
+
+const TEXT_ENCODING: &str = "text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d}";
+const GENERIC_FORMAT: &str = "generic:version{7412167c-06e9-4698-aff2-e63eb59037e7}";
+
+/// A KeyValues3 file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Kv3Document {
+    pub header: Kv3Header,
+
+    /// Root value, usually an object.
+    pub root: Kv3Value,
+}
+
+/// Header comment of a KeyValues3 file, e.g.
+/// `<!-- kv3 encoding:text:version{...} format:generic:version{...} -->`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kv3Header {
+    /// Encoding of the file, without its `encoding:` prefix.
+    pub encoding: String,
+
+    /// Format of the file, without its `format:` prefix.
+    pub format: String,
+}
+
+impl Default for Kv3Header {
+    /// Header of a generic text file.
+    fn default() -> Self {
+        Self {
+            encoding: TEXT_ENCODING.to_string(),
+            format: GENERIC_FORMAT.to_string(),
+        }
+    }
+}
+
+impl Kv3Header {
+    /// Text of the header comment.
+    pub fn to_text(&self) -> String {
+        format!(
+            "<!-- kv3 encoding:{} format:{} -->",
+            self.encoding, self.format
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Kv3Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    /// Integer too large for an `i64`.
+    UInt(u64),
+    Double(f64),
+    /// Quoted or multi-line string, with its escape sequences decoded.
+    String(String),
+    /// Binary blob, e.g. `#[ 00 FF ]`.
+    Binary(Vec<u8>),
+    Array(Vec<Kv3Value>),
+    Object(Vec<Kv3Member>),
+    /// Value with a flag, e.g. `resource:"path"`. The flag is stored without its colon.
+    Flagged(String, Box<Kv3Value>),
+}
+
+/// Entry of a KeyValues3 object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kv3Member {
+    pub key: String,
+    pub key_range: Range,
+    pub value: Kv3Value,
+    /// Comments of the member, with their delimiters, as in
+    /// [`KeyValue::comments`](crate::KeyValue::comments).
+    pub comments: Vec<String>,
+}
+
+impl Kv3Document {
+    /// Write the document as KeyValues3 text, formatted according to `config`.
+    ///
+    /// Fails if the document cannot be written as valid KeyValues3: a header field that is
+    /// empty or holds whitespace, a flag that is not an identifier, a comment without its
+    /// `//` or `/* */` delimiters, or a double that is not finite.
+    pub fn to_text(&self, config: FormatterConfig) -> Result<String, KvError> {
+        for field in [&self.header.encoding, &self.header.format] {
+            if field.is_empty() || field.contains(char::is_whitespace) || field.contains("-->") {
+                return Err(write_error(format!("invalid header field \"{field}\"")));
+            }
+        }
+        let mut text = self.header.to_text();
+        text.push('\n');
+        write_value(&mut text, &self.root)?;
+        format_kv3(&text, config)
+    }
+}
+
+/// Serialize a string of KeyValues3. Fails with the first syntax error of the input.
+pub fn serialize_kv3(input: &str) -> Result<Kv3Document, KvError> {
+    serialize_kv3_with_config(input, ParserConfig::default())
+}
+
+/// Serialize a string of KeyValues3, with positions in the position encoding of the
+/// configuration. The other options of the configuration only apply to KeyValues: strings
+/// of KeyValues3 are always unescaped, with the escape sequences of the engine.
+pub fn serialize_kv3_with_config(
+    input: &str,
+    config: ParserConfig,
+) -> Result<Kv3Document, KvError> {
+    let document = parse_kv3(input, config)?;
+    let line_index = LineIndex::new(input, config.position_encoding);
+    let mut header = Kv3Header::default();
+    let mut root = Kv3Value::Null;
+    for pair in document.into_inner() {
+        match pair.as_rule() {
+            Rule::header => {
+                for field in pair.into_inner() {
+                    let mut parts = field.into_inner();
+                    let name = parts.next().expect("fields have a name").as_str();
+                    let value = parts.next().expect("fields have a value").as_str();
+                    match name {
+                        "encoding" => header.encoding = value.to_string(),
+                        "format" => header.format = value.to_string(),
+                        _ => (),
+                    }
+                }
+            }
+            Rule::value => root = serialize_value(pair, &line_index),
+            _ => (),
+        }
+    }
+
+    Ok(Kv3Document { header, root })
+}
+
+/// Parse the `document` rule of the grammar, converting pest errors.
+pub(crate) fn parse_kv3(input: &str, config: ParserConfig) -> Result<Pair<'_, Rule>, KvError> {
+    match Kv3Parser::parse(Rule::document, input) {
+        Ok(mut pairs) => Ok(pairs.next().expect("the document rule always matches")),
+        Err(err) => {
+            let line_index = LineIndex::new(input, config.position_encoding);
+            let (start, end) = match err.location {
+                pest::error::InputLocation::Pos(pos) => (pos, pos),
+                pest::error::InputLocation::Span(span) => span,
+            };
+            Err(KvError {
+                range: line_index.range(start, end),
+                additional_ranges: vec![],
+                message: err.variant.message().to_string(),
+                kind: KvErrorKind::SyntaxError,
+            })
+        }
+    }
+}
+
+fn serialize_value(pair: Pair<Rule>, line_index: &LineIndex) -> Kv3Value {
+    let pair = match pair.as_rule() {
+        Rule::value => pair
+            .into_inner()
+            .find(|pair| pair.as_rule() != Rule::COMMENT)
+            .expect("values have an inner rule"),
+        _ => pair,
+    };
+    match pair.as_rule() {
+        Rule::flagged => {
+            let mut inner = pair
+                .into_inner()
+                .filter(|pair| pair.as_rule() != Rule::COMMENT);
+            let flag = inner.next().expect("flagged values have a flag").as_str();
+            let value = inner.next().expect("flagged values have a value");
+            Kv3Value::Flagged(
+                flag.trim_end_matches(':').to_string(),
+                Box::new(serialize_value(value, line_index)),
+            )
+        }
+        Rule::object => Kv3Value::Object(serialize_members(pair, line_index)),
+        Rule::array => Kv3Value::Array(
+            pair.into_inner()
+                .filter(|pair| pair.as_rule() == Rule::value)
+                .map(|value| serialize_value(value, line_index))
+                .collect(),
+        ),
+        Rule::binary_blob => Kv3Value::Binary(
+            pair.into_inner()
+                .map(|byte| u8::from_str_radix(byte.as_str(), 16).expect("bytes are hexadecimal"))
+                .collect(),
+        ),
+        Rule::multiline_string => {
+            let text = pair.into_inner().next().map_or("", |inner| inner.as_str());
+            // The line breaks after the opening quotes and before the closing quotes are
+            // not part of the string.
+            let text = text
+                .strip_prefix("\r\n")
+                .or_else(|| text.strip_prefix('\n'))
+                .unwrap_or(text);
+            let text = text
+                .strip_suffix("\r\n")
+                .or_else(|| text.strip_suffix('\n'))
+                .unwrap_or(text);
+            Kv3Value::String(text.to_string())
+        }
+        Rule::string => Kv3Value::String(string_text(pair)),
+        Rule::null => Kv3Value::Null,
+        Rule::boolean => Kv3Value::Bool(pair.as_str() == "true"),
+        Rule::number => {
+            let text = pair.as_str();
+            if let Ok(value) = text.parse() {
+                Kv3Value::Int(value)
+            } else if let Ok(value) = text.parse() {
+                Kv3Value::UInt(value)
+            } else {
+                Kv3Value::Double(text.parse().unwrap_or_default())
+            }
+        }
+        _ => unreachable!("unexpected rule {:?}", pair.as_rule()),
+    }
+}
+
+fn write_value(text: &mut String, value: &Kv3Value) -> Result<(), KvError> {
+    match value {
+        Kv3Value::Null => text.push_str("null"),
+        Kv3Value::Bool(value) => text.push_str(&value.to_string()),
+        Kv3Value::Int(value) => text.push_str(&value.to_string()),
+        Kv3Value::UInt(value) => text.push_str(&value.to_string()),
+        // The debug format always has a decimal point or an exponent.
+        Kv3Value::Double(value) if value.is_finite() => text.push_str(&format!("{value:?}")),
+        Kv3Value::Double(value) => {
+            return Err(write_error(format!(
+                "{value} cannot be written in KeyValues3"
+            )))
+        }
+        Kv3Value::String(value) => write_string(text, value),
+        Kv3Value::Binary(bytes) => {
+            text.push_str("#[");
+            for byte in bytes {
+                text.push_str(&format!(" {byte:02X}"));
+            }
+            text.push_str(" ]");
+        }
+        Kv3Value::Array(values) => {
+            text.push_str("[\n");
+            for value in values {
+                write_value(text, value)?;
+                text.push_str(",\n");
+            }
+            text.push(']');
+        }
+        Kv3Value::Object(members) => {
+            text.push_str("{\n");
+            for member in members {
+                for comment in member.comments.iter() {
+                    if !is_comment(comment) {
+                        return Err(write_error(format!("invalid comment \"{comment}\"")));
+                    }
+                    text.push_str(comment);
+                    text.push('\n');
+                }
+                match is_identifier(&member.key) {
+                    true => text.push_str(&member.key),
+                    false => write_string(text, &member.key),
+                }
+                text.push_str(" = ");
+                write_value(text, &member.value)?;
+                text.push('\n');
+            }
+            text.push('}');
+        }
+        Kv3Value::Flagged(flag, value) => {
+            if !is_identifier(flag) {
+                return Err(write_error(format!("invalid flag \"{flag}\"")));
+            }
+            text.push_str(flag);
+            text.push(':');
+            write_value(text, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a comment has its delimiters, and nothing after its end.
+fn is_comment(comment: &str) -> bool {
+    if let Some(line) = comment.strip_prefix("//") {
+        return !line.contains('\n');
+    }
+    comment
+        .strip_prefix("/*")
+        .and_then(|block| block.strip_suffix("*/"))
+        .is_some_and(|inner| !inner.contains("*/"))
+}
+
+fn write_error(message: String) -> KvError {
+    KvError {
+        range: Range::default(),
+        additional_ranges: vec![],
+        message,
+        kind: KvErrorKind::SyntaxError,
+    }
+}
+
+fn write_string(text: &mut String, value: &str) {
+    text.push('"');
+    text.push_str(&EscapeMode::Valve.escape(value));
+    text.push('"');
+}
+
+/// Whether a key can be written without quotes.
+pub(crate) fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Serialize the members of an object, and attach the comments between them as in
+/// [`KeyValue::comments`](crate::KeyValue::comments).
+fn serialize_members(object: Pair<Rule>, line_index: &LineIndex) -> Vec<Kv3Member> {
+    let mut members: Vec<Kv3Member> = vec![];
+    let mut comments = vec![];
+    let mut prev_line = None;
+    for pair in object.into_inner() {
+        match pair.as_rule() {
+            Rule::COMMENT => {
+                let comment = pair.as_str().trim_end().to_string();
+                match members.last_mut() {
+                    Some(member) if prev_line == Some(pair.line_col().0) => {
+                        member.comments.push(comment)
+                    }
+                    _ => comments.push(comment),
+                }
+            }
+            Rule::member => {
+                prev_line = Some(pair.as_span().end_pos().line_col().0);
+                let mut key = None;
+                let mut value = None;
+                for inner in pair.into_inner() {
+                    match inner.as_rule() {
+                        Rule::COMMENT => comments.push(inner.as_str().trim_end().to_string()),
+                        Rule::key => key = Some(inner),
+                        _ => value = Some(inner),
+                    }
+                }
+                let key = key.expect("members have a key");
+                let span = key.as_span();
+                members.push(Kv3Member {
+                    key: key_text(key),
+                    key_range: line_index.range(span.start(), span.end()),
+                    value: serialize_value(value.expect("members have a value"), line_index),
+                    comments: std::mem::take(&mut comments),
+                });
+            }
+            _ => (),
+        }
+    }
+    if let Some(member) = members.last_mut() {
+        member.comments.append(&mut comments);
+    }
+
+    members
+}
+
+/// Text of a `key` rule, unquoted and unescaped.
+fn key_text(key: Pair<Rule>) -> String {
+    let inner = key.into_inner().next().expect("keys have an inner rule");
+    match inner.as_rule() {
+        Rule::string => string_text(inner),
+        _ => inner.as_str().to_string(),
+    }
+}
+
+fn string_text(string: Pair<Rule>) -> String {
+    let raw = string
+        .into_inner()
+        .next()
+        .map_or("", |inner| inner.as_str());
+    EscapeMode::Valve.unescape(raw)
+}
@@ -0,0 +1,348 @@
+use std::cmp::min;
+
+use pest::iterators::Pair;
+
+use super::{grammar::Rule, is_identifier, parse_kv3};
+use crate::{FormatterConfig, KvError, ParserConfig, QuoteStyle};
+
+/// Format a string of KeyValues3. Fails with the syntax error of the input.
+///
+/// Objects and arrays open on their own line, as written by Valve's tools. Arrays of
+/// plain values without comments are kept on one line, e.g. `[ 0.0, 1.0, 0.0 ]`.
+pub fn format_kv3(input: &str, config: FormatterConfig) -> Result<String, KvError> {
+    let document = parse_kv3(input, ParserConfig::default())?;
+    let mut emitter = Emitter::new(config);
+    emitter.collect(document);
+    emitter.emit();
+
+    Ok(emitter.buffer.join("\n"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Header,
+    Key,
+    /// Flag of a value, including its colon.
+    Flag,
+    Scalar,
+    LBrace,
+    RBrace,
+    LBracket {
+        inline: bool,
+    },
+    RBracket,
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    /// Line of the start of the token in the input.
+    line: usize,
+    /// Line of the end of the token in the input.
+    end_line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Object,
+    Array { inline: bool, len: usize },
+}
+
+#[derive(Debug, Default)]
+struct Emitter {
+    buffer: Vec<String>,
+    config: FormatterConfig,
+    tokens: Vec<Token>,
+    current_line: String,
+    /// Indentation of the current line.
+    line_indent: usize,
+    indent: usize,
+    containers: Vec<Container>,
+    /// Kind and end line of the previous token in the input.
+    prev_token: Option<(TokenKind, usize)>,
+    /// Whether the next value follows the `=` of a key.
+    after_key: bool,
+    /// Whether the next value follows a flag.
+    after_flag: bool,
+}
+
+impl Emitter {
+    fn new(config: FormatterConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    fn collect(&mut self, pair: Pair<Rule>) {
+        let kind = match pair.as_rule() {
+            Rule::document | Rule::value | Rule::flagged | Rule::member => {
+                for child in pair.into_inner() {
+                    self.collect(child);
+                }
+                return;
+            }
+            Rule::EOI => return,
+            Rule::object => {
+                let (start, end) = (pair.as_span().start_pos(), pair.as_span().end_pos());
+                self.push_token(
+                    TokenKind::LBrace,
+                    "{",
+                    start.line_col().0,
+                    start.line_col().0,
+                );
+                for child in pair.into_inner() {
+                    self.collect(child);
+                }
+                self.push_token(TokenKind::RBrace, "}", end.line_col().0, end.line_col().0);
+                return;
+            }
+            Rule::array => {
+                let (start, end) = (pair.as_span().start_pos(), pair.as_span().end_pos());
+                let inline = pair
+                    .clone()
+                    .into_inner()
+                    .all(|child| child.as_rule() == Rule::value && is_inline(child));
+                let kind = TokenKind::LBracket { inline };
+                self.push_token(kind, "[", start.line_col().0, start.line_col().0);
+                for child in pair.into_inner() {
+                    self.collect(child);
+                }
+                self.push_token(TokenKind::RBracket, "]", end.line_col().0, end.line_col().0);
+                return;
+            }
+            Rule::header => TokenKind::Header,
+            Rule::key => TokenKind::Key,
+            Rule::flag => TokenKind::Flag,
+            Rule::COMMENT => match pair
+                .clone()
+                .into_inner()
+                .next()
+                .map(|inner| inner.as_rule())
+            {
+                Some(Rule::line_comment) => TokenKind::LineComment,
+                _ => TokenKind::BlockComment,
+            },
+            _ => TokenKind::Scalar,
+        };
+        let text = match (kind, pair.as_rule()) {
+            (TokenKind::Header, _) => {
+                let fields: Vec<&str> = pair
+                    .clone()
+                    .into_inner()
+                    .map(|field| field.as_str())
+                    .collect();
+                format!("<!-- kv3 {} -->", fields.join(" "))
+            }
+            (TokenKind::Key, _) => self.key_text(pair.clone()),
+            (_, Rule::binary_blob) => {
+                let bytes: Vec<&str> = pair
+                    .clone()
+                    .into_inner()
+                    .map(|byte| byte.as_str())
+                    .collect();
+                match bytes.is_empty() {
+                    true => "#[]".to_string(),
+                    false => format!("#[ {} ]", bytes.join(" ")),
+                }
+            }
+            _ => pair.as_str().trim_end().to_string(),
+        };
+        let span = pair.as_span();
+        self.push_token(
+            kind,
+            &text,
+            span.start_pos().line_col().0,
+            span.end_pos().line_col().0,
+        );
+    }
+
+    fn push_token(&mut self, kind: TokenKind, text: &str, line: usize, end_line: usize) {
+        self.tokens.push(Token {
+            kind,
+            text: text.to_string(),
+            line,
+            end_line,
+        });
+    }
+
+    /// Text of a key, quoted according to the configured [`QuoteStyle`].
+    fn key_text(&self, key: Pair<Rule>) -> String {
+        let inner = key.into_inner().next().expect("keys have an inner rule");
+        let text = inner.as_str();
+        match (self.config.quote_style, inner.as_rule()) {
+            (QuoteStyle::Always, Rule::identifier) => format!("\"{text}\""),
+            (QuoteStyle::Minimal, Rule::string) if is_identifier(&text[1..text.len() - 1]) => {
+                text[1..text.len() - 1].to_string()
+            }
+            _ => text.to_string(),
+        }
+    }
+
+    fn emit(&mut self) {
+        let tokens = std::mem::take(&mut self.tokens);
+        for token in tokens.iter() {
+            match token.kind {
+                TokenKind::Header => {
+                    self.start_line(token);
+                    self.current_line.push_str(&token.text);
+                }
+                TokenKind::LineComment | TokenKind::BlockComment => {
+                    if self.prev_token.map(|(_, line)| line) == Some(token.line)
+                        && !self.current_line.is_empty()
+                    {
+                        self.current_line.push_str("  ");
+                    } else {
+                        self.start_line(token);
+                    }
+                    self.current_line.push_str(&token.text);
+                    if token.kind == TokenKind::LineComment {
+                        self.push_line();
+                    }
+                }
+                TokenKind::Key => {
+                    self.start_line(token);
+                    self.current_line.push_str(&token.text);
+                    self.current_line.push_str(" =");
+                    self.after_key = true;
+                }
+                TokenKind::Flag => {
+                    self.start_value(token);
+                    self.current_line.push_str(&token.text);
+                    self.after_flag = true;
+                }
+                TokenKind::Scalar => {
+                    if !self.after_flag {
+                        self.start_value(token);
+                    }
+                    self.current_line.push_str(&token.text);
+                    self.end_value();
+                }
+                TokenKind::LBracket { inline: true } => {
+                    if !self.after_flag {
+                        self.start_value(token);
+                    }
+                    self.current_line.push('[');
+                    self.containers.push(Container::Array {
+                        inline: true,
+                        len: 0,
+                    });
+                }
+                TokenKind::LBrace | TokenKind::LBracket { inline: false } => {
+                    // Objects and arrays open on a new line, even after a key or a flag.
+                    self.after_key = false;
+                    self.after_flag = false;
+                    self.start_line(token);
+                    self.current_line.push_str(&token.text);
+                    self.indent += 1;
+                    self.containers.push(match token.kind {
+                        TokenKind::LBrace => Container::Object,
+                        _ => Container::Array {
+                            inline: false,
+                            len: 0,
+                        },
+                    });
+                }
+                TokenKind::RBrace | TokenKind::RBracket => {
+                    match self.containers.pop() {
+                        Some(Container::Array { inline: true, len }) => {
+                            self.current_line.push_str(if len > 0 { " ]" } else { "]" });
+                        }
+                        _ => {
+                            self.indent -= 1;
+                            self.start_line(token);
+                            self.current_line.push_str(&token.text);
+                        }
+                    }
+                    self.end_value();
+                }
+            }
+            self.prev_token = Some((token.kind, token.end_line));
+        }
+        self.push_line();
+    }
+
+    /// Write what comes before a value: a space after a key, a separator in an inline
+    /// array, or a new line.
+    fn start_value(&mut self, token: &Token) {
+        if self.after_key && !self.current_line.is_empty() {
+            self.after_key = false;
+            self.current_line.push(' ');
+            return;
+        }
+        match self.containers.last() {
+            Some(Container::Array { inline: true, len }) => {
+                self.current_line
+                    .push_str(if *len > 0 { ", " } else { " " });
+            }
+            _ => self.start_line(token),
+        }
+    }
+
+    /// Count the value in its array, followed by a comma if the array is not inline.
+    fn end_value(&mut self) {
+        self.after_key = false;
+        self.after_flag = false;
+        if let Some(Container::Array { inline, len }) = self.containers.last_mut() {
+            *len += 1;
+            if !*inline {
+                self.current_line.push(',');
+            }
+        }
+    }
+
+    /// Start a new line for a token, keeping up to `max_empty_lines` empty lines from the
+    /// input before it. A token following a block comment on the same line stays on it.
+    fn start_line(&mut self, token: &Token) {
+        match self.prev_token {
+            Some((TokenKind::BlockComment, line))
+                if line == token.line && !self.current_line.is_empty() =>
+            {
+                self.current_line.push_str("  ");
+                return;
+            }
+            Some((_, line)) => {
+                self.push_line();
+                let empty_lines = token.line.saturating_sub(line + 1);
+                for _ in 0..min(empty_lines, self.config.max_empty_lines as usize) {
+                    self.buffer.push(String::new());
+                }
+            }
+            None => self.push_line(),
+        }
+        self.line_indent = self.indent;
+    }
+
+    fn indent_string(&self) -> String {
+        if self.config.use_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.config.indent_size as usize)
+        }
+    }
+
+    fn push_line(&mut self) {
+        if self.current_line.is_empty() {
+            return;
+        }
+        self.buffer.push(format!(
+            "{}{}",
+            self.indent_string().repeat(self.line_indent),
+            self.current_line
+        ));
+        self.current_line.clear();
+    }
+}
+
+/// Whether a value can be written in an inline array.
+fn is_inline(value: Pair<Rule>) -> bool {
+    value.into_inner().all(|inner| match inner.as_rule() {
+        Rule::flagged | Rule::value => is_inline(inner),
+        Rule::flag => true,
+        Rule::object | Rule::array | Rule::multiline_string | Rule::COMMENT => false,
+        _ => true,
+    })
+}
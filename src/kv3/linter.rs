@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use crate::{serialize_kv3_with_config, Kv3Member, Kv3Value, KvError, KvErrorKind, ParserConfig};
+
+/// Lint a string of KeyValues3.
+///
+/// Reports the keys that are repeated in an object. Unlike [`lint_keyvalue`](crate::lint_keyvalue),
+/// the KeyValues3 grammar does not recover from syntax errors: only the first syntax error
+/// of the input is reported, and duplicates are not searched for.
+pub fn lint_kv3(input: &str) -> Vec<KvError> {
+    lint_kv3_with_config(input, ParserConfig::default())
+}
+
+/// Lint a string of KeyValues3, see [`lint_kv3`], with positions in the position encoding of
+/// the configuration.
+pub fn lint_kv3_with_config(input: &str, config: ParserConfig) -> Vec<KvError> {
+    let document = match serialize_kv3_with_config(input, config) {
+        Ok(document) => document,
+        Err(err) => return vec![err],
+    };
+    let mut errors = vec![];
+    search_for_duplicates(&mut errors, &document.root);
+
+    errors
+}
+
+fn search_for_duplicates(errors: &mut Vec<KvError>, value: &Kv3Value) {
+    match value {
+        Kv3Value::Object(members) => {
+            let mut keys: HashMap<&str, Vec<&Kv3Member>> = HashMap::default();
+            for member in members {
+                keys.entry(&member.key).or_default().push(member);
+                search_for_duplicates(errors, &member.value);
+            }
+            let mut dups: Vec<_> = keys
+                .into_values()
+                .filter(|members| members.len() > 1)
+                .collect();
+            dups.sort_by_key(|members| members[0].key_range.start);
+            errors.extend(dups.into_iter().map(|members| KvError {
+                range: members[0].key_range,
+                additional_ranges: members[1..].iter().map(|member| member.key_range).collect(),
+                message: format!("Duplicate entry for key \"{}\"", members[0].key),
+                kind: KvErrorKind::DuplicateError,
+            }));
+        }
+        Kv3Value::Array(values) => {
+            for value in values {
+                search_for_duplicates(errors, value);
+            }
+        }
+        Kv3Value::Flagged(_, value) => search_for_duplicates(errors, value),
+        _ => (),
+    }
+}
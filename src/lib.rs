@@ -1,9 +1,33 @@
+mod binary;
+mod borrowed;
 mod common;
-pub(crate) mod formatter;
+mod conditional;
+mod convert;
+mod de;
+mod encoding;
+mod escape;
+mod formatter;
+mod incremental;
+mod kv3;
 mod linter;
+mod parser;
+mod printer;
+mod query;
+mod resolver;
+mod selector;
+mod ser;
 mod serializer;
+mod stream;
+mod syntax;
+mod typed;
 
-pub use {self::common::*, self::formatter::*, self::linter::*, self::serializer::*};
+pub use {
+    self::binary::*, self::borrowed::*, self::common::*, self::conditional::*, self::convert::*,
+    self::de::*, self::encoding::*, self::escape::*, self::formatter::*, self::incremental::*,
+    self::kv3::*, self::linter::*, self::parser::*, self::query::*, self::resolver::*,
+    self::selector::*, self::ser::*, self::serializer::*, self::stream::*, self::syntax::*,
+    self::typed::*,
+};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
@@ -17,7 +41,21 @@ use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_name = formatKeyvalue, skip_jsdoc)]
 pub fn format_keyvalue(input: String, config: FormatterConfig) -> Result<String, JsValue> {
-    Ok(formatter::format_keyvalue(input.as_str(), config).unwrap())
+    formatter::format_keyvalue(input.as_str(), config)
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Format the bytes of a keyvalue file, writing the output back in the encoding of the input.
+///
+/// @param {Uint8Array} input Contents of the file, in UTF-8 or in UTF-16 with a byte order mark.
+/// @param {FormatterConfig} config Config object that specifies formatter configuration.
+/// @returns {Uint8Array} Formatted output.
+/// @throws Invalid input error.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = formatKeyvalueBytes, skip_jsdoc)]
+pub fn format_keyvalue_bytes(input: Vec<u8>, config: FormatterConfig) -> Result<Vec<u8>, JsValue> {
+    formatter::format_keyvalue_bytes(&input, config)
+        .map_err(|err| JsValue::from_str(&err.to_string()))
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -27,17 +65,182 @@ extern "C" {
     pub type KvErrorArray;
 }
 
+/// Format a string of KeyValues3.
+///
+/// @param {string} input Input string for the formatter.
+/// @param {FormatterConfig} config Config object that specifies formatter configuration.
+/// @returns {string} Formatted output.
+/// @throws Invalid input error.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = formatKv3, skip_jsdoc)]
+pub fn format_kv3(input: String, config: FormatterConfig) -> Result<String, JsValue> {
+    kv3::format_kv3(input.as_str(), config).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Array<KeyValue>")]
+    pub type KeyValueArray;
+}
+
+/// Parse a string of keyvalue, recovering from syntax errors.
+///
+/// @param {string} input Input string for the parser.
+/// @param {ParserConfig | undefined} config Optional config object that specifies parser configuration.
+///@returns {Array<KeyValue>} Root entries of the input, with their ranges.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = parseDocument, skip_jsdoc)]
+pub fn parse_document(input: String, config: Option<ParserConfig>) -> KeyValueArray {
+    use js_sys::Array;
+    use wasm_bindgen::JsCast;
+
+    let result = match config {
+        Some(config) => parser::parse_document_with_config(input.as_str(), config),
+        None => parser::parse_document(input.as_str()),
+    };
+    let tmp: Array = result
+        .document
+        .roots
+        .iter()
+        .map(|kv| kv.to_js())
+        .map(JsValue::from)
+        .collect();
+    tmp.unchecked_into::<KeyValueArray>()
+}
+
+/// Select the entries of a string of keyvalue matching a selector, e.g. `**/Offsets/*[!linux]`.
+///
+/// @param {string} input Input string for the parser.
+/// @param {string} selector Selector of the entries, see the `Selector` documentation of the crate.
+/// @param {ParserConfig | undefined} config Optional config object that specifies parser configuration.
+/// @param {boolean | undefined} ignore_case Should keys be matched ignoring ASCII case, as the engine does.
+///@returns {Array<KeyValue>} Matching entries, in document order, with their ranges.
+/// @throws Invalid selector or invalid input error.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = selectKeyvalue, skip_jsdoc)]
+pub fn select_keyvalue(
+    input: String,
+    selector: String,
+    config: Option<ParserConfig>,
+    ignore_case: Option<bool>,
+) -> Result<KeyValueArray, JsValue> {
+    use js_sys::Array;
+    use wasm_bindgen::JsCast;
+
+    let selector =
+        Selector::parse(selector.as_str()).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let document = match config {
+        Some(config) => serializer::serialize_document_with_config(input.as_str(), config),
+        None => serializer::serialize_document(input.as_str()),
+    }
+    .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let matches = if ignore_case.unwrap_or_default() {
+        selector.select_ignore_case(&document.roots)
+    } else {
+        selector.select(&document.roots)
+    };
+    let tmp: Array = matches
+        .into_iter()
+        .map(|kv| kv.to_js())
+        .map(JsValue::from)
+        .collect();
+    Ok(tmp.unchecked_into::<KeyValueArray>())
+}
+
 /// Lint a string of keyvalue.
 ///
 /// @param {string} input Input string for the linter.
+/// @param {ParserConfig | undefined} config Optional config object that specifies parser configuration.
 ///@returns {Array<KvError>} Array of errors that the linter encountered.
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_name = lintKeyvalue, skip_jsdoc)]
-pub fn lint_keyvalue(input: String) -> KvErrorArray {
+pub fn lint_keyvalue(input: String, config: Option<ParserConfig>) -> KvErrorArray {
+    use js_sys::Array;
+    use wasm_bindgen::JsCast;
+
+    let errors = match config {
+        Some(config) => linter::lint_keyvalue_with_config(input.as_str(), config),
+        None => linter::lint_keyvalue(input.as_str()),
+    };
+    let tmp: Array = errors
+        .into_iter()
+        .map(|e| e.to_js())
+        .map(JsValue::from)
+        .collect();
+    tmp.unchecked_into::<KvErrorArray>()
+}
+
+/// Lint the bytes of a keyvalue file, decoded according to its byte order mark.
+///
+/// @param {Uint8Array} input Contents of the file, in UTF-8 or in UTF-16 with a byte order mark.
+/// @param {ParserConfig | undefined} config Optional config object that specifies parser configuration.
+///@returns {Array<KvError>} Array of errors that the linter encountered.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = lintKeyvalueBytes, skip_jsdoc)]
+pub fn lint_keyvalue_bytes(input: Vec<u8>, config: Option<ParserConfig>) -> KvErrorArray {
+    use js_sys::Array;
+    use wasm_bindgen::JsCast;
+
+    let tmp: Array = linter::lint_keyvalue_bytes(&input, config.unwrap_or_default())
+        .into_iter()
+        .map(|e| e.to_js())
+        .map(JsValue::from)
+        .collect();
+    tmp.unchecked_into::<KvErrorArray>()
+}
+
+/// Lint a string of keyvalue and the files referenced by its `#base` and `#include` directives.
+///
+/// @param {string} input Input string for the linter.
+/// @param {string} path Path of the input, used to resolve relative directives.
+/// @param {MemoryResolver} resolver Files that can be referenced by the directives.
+/// @param {ParserConfig | undefined} config Optional config object that specifies parser configuration.
+///@returns {Array<KvError>} Array of errors that the linter encountered.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = lintKeyvalueWithResolver, skip_jsdoc)]
+pub fn lint_keyvalue_with_resolver(
+    input: String,
+    path: String,
+    resolver: &MemoryResolver,
+    config: Option<ParserConfig>,
+) -> KvErrorArray {
+    use js_sys::Array;
+    use wasm_bindgen::JsCast;
+
+    let errors = match config {
+        Some(config) => linter::lint_keyvalue_with_resolver_with_config(
+            input.as_str(),
+            path.as_str(),
+            resolver,
+            config,
+        ),
+        None => linter::lint_keyvalue_with_resolver(input.as_str(), path.as_str(), resolver),
+    };
+    let tmp: Array = errors
+        .into_iter()
+        .map(|e| e.to_js())
+        .map(JsValue::from)
+        .collect();
+    tmp.unchecked_into::<KvErrorArray>()
+}
+
+/// Lint a string of KeyValues3. Only the first syntax error of the input is reported.
+///
+/// @param {string} input Input string for the linter.
+/// @param {ParserConfig | undefined} config Optional config object, of which only the position encoding applies.
+///@returns {Array<KvError>} Array of errors that the linter encountered.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = lintKv3, skip_jsdoc)]
+pub fn lint_kv3(input: String, config: Option<ParserConfig>) -> KvErrorArray {
     use js_sys::Array;
     use wasm_bindgen::JsCast;
 
-    let tmp: Array = linter::lint_keyvalue(input.as_str())
+    let errors = match config {
+        Some(config) => kv3::lint_kv3_with_config(input.as_str(), config),
+        None => kv3::lint_kv3(input.as_str()),
+    };
+    let tmp: Array = errors
         .into_iter()
         .map(|e| e.to_js())
         .map(JsValue::from)
@@ -1,6 +1,4 @@
-use std::collections::HashMap;
-
-use pest::error::LineColLocation;
+use std::{collections::HashMap, fmt};
 
 #[cfg(target_arch = "wasm32")]
 use js_sys::Array;
@@ -8,7 +6,14 @@ use wasm_bindgen::prelude::wasm_bindgen;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::JsValue;
 
-use crate::{KeyValue, Position, Range, Value};
+use crate::{
+    decode_text_with_config, parse_document_with_config,
+    parser::{parse_tree, Parse},
+    resolver::resolve_directives,
+    serializer::Serializer,
+    BorrowedKeyValue, BorrowedValue, FileResolver, KeyValue, ParseResult, ParserConfig, Range,
+    Value,
+};
 
 #[wasm_bindgen]
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,6 +21,10 @@ pub enum KvErrorKind {
     #[default]
     SyntaxError,
     DuplicateError,
+    MissingFileError,
+    IncludeCycleError,
+    EncodingError,
+    ConversionWarning,
 }
 
 /// Representation of a KeyValue linter error
@@ -35,8 +44,24 @@ pub struct KvError {
     pub kind: KvErrorKind,
 }
 
+impl fmt::Display for KvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.range.start.line + 1,
+            self.range.start.character + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for KvError {}
+
 #[test]
 fn test_kv_error() {
+    use crate::Position;
+
     let mut kv_error = KvError::default();
     assert_eq!(kv_error.range.start.line, 0);
     assert_eq!(kv_error.range.start.character, 0);
@@ -130,98 +155,176 @@ impl KvErrorJs {
 }
 
 pub fn lint_keyvalue(input: &str) -> Vec<KvError> {
-    let mut errors = vec![];
-    let serialized = super::serialize_keyvalue(input);
-    match serialized {
-        Err(err) => {
-            let range = match err.line_col {
-                LineColLocation::Pos(pos) => Range {
-                    start: Position {
-                        line: pos.0 as u32,
-                        character: pos.1 as u32,
-                    },
-                    end: Position {
-                        line: pos.0 as u32,
-                        character: pos.1 as u32,
-                    },
-                },
-                LineColLocation::Span(start, end) => Range {
-                    start: Position {
-                        line: start.0 as u32,
-                        character: start.1 as u32,
-                    },
-                    end: Position {
-                        line: end.0 as u32,
-                        character: end.1 as u32,
-                    },
-                },
-            };
-            errors.push(KvError {
-                range,
-                additional_ranges: vec![],
-                message: err.variant.message().to_string(),
-                kind: KvErrorKind::SyntaxError,
-            });
-        }
-        Ok(kv) => {
-            let mut dups = vec![];
-            search_for_duplicates(&mut dups, &[kv]);
-            for dup in dups {
-                errors.push(KvError {
-                    range: dup.original_declaration,
-                    additional_ranges: dup.duplicate_declarations,
-                    message: format!("Duplicate entry for key \"{}\"", dup.key),
-                    kind: KvErrorKind::DuplicateError,
-                });
-            }
+    lint_keyvalue_with_config(input, ParserConfig::default())
+}
+
+/// Lint the bytes of a keyvalue file, decoded according to its byte order mark.
+///
+/// Text that cannot be decoded is reported as a single [`KvErrorKind::EncodingError`].
+pub fn lint_keyvalue_bytes(input: &[u8], config: ParserConfig) -> Vec<KvError> {
+    match decode_text_with_config(input, config) {
+        Ok((text, _)) => lint_keyvalue_with_config(&text, config),
+        Err(err) => vec![err],
+    }
+}
+
+pub fn lint_keyvalue_with_config(input: &str, config: ParserConfig) -> Vec<KvError> {
+    // The duplicate check runs on everything that could be parsed, even if the input
+    // has syntax errors. Entries borrow their keys from the input to spare allocations.
+    let Parse {
+        tree,
+        mut errors,
+        line_index,
+    } = parse_tree(input, config);
+    let roots = Serializer::new(input, line_index, config).serialize_roots(&tree.root);
+    errors.extend(duplicate_errors(&roots));
+
+    errors
+}
+
+/// Lint a parsed document, such as one updated by [`reparse_document`](crate::reparse_document).
+/// Returns its syntax errors and its duplicate keys, as [`lint_keyvalue_with_config`].
+pub fn lint_parse_result(result: &ParseResult) -> Vec<KvError> {
+    let mut errors = result.errors.clone();
+    errors.extend(duplicate_errors(&result.document.roots));
+
+    errors
+}
+
+fn duplicate_errors<E: Entry>(roots: &[E]) -> Vec<KvError> {
+    let mut dups = vec![];
+    // Repeated roots are how multi-root files such as VMFs list their entries,
+    // so only the content of each root is checked.
+    for kv in roots.iter() {
+        if let Some(section) = kv.section() {
+            search_for_duplicates(&mut dups, section);
         }
     }
+    dups.into_iter()
+        .map(|dup| KvError {
+            range: dup.original_declaration,
+            additional_ranges: dup.duplicate_declarations,
+            message: format!("Duplicate entry for key \"{}\"", dup.key),
+            kind: KvErrorKind::DuplicateError,
+        })
+        .collect()
+}
+
+/// Lint a string of keyvalue, and the files referenced by its `#base` and `#include` directives.
+///
+/// Errors in the referenced files, such as missing files or include cycles, are reported on
+/// the range of the directive of the input that references them.
+///
+/// Directives are resolved even when the input has syntax errors.
+pub fn lint_keyvalue_with_resolver<R: FileResolver + ?Sized>(
+    input: &str,
+    path: &str,
+    resolver: &R,
+) -> Vec<KvError> {
+    lint_keyvalue_with_resolver_with_config(input, path, resolver, ParserConfig::default())
+}
+
+pub fn lint_keyvalue_with_resolver_with_config<R: FileResolver + ?Sized>(
+    input: &str,
+    path: &str,
+    resolver: &R,
+    config: ParserConfig,
+) -> Vec<KvError> {
+    let mut result = parse_document_with_config(input, config);
+    let mut errors = lint_parse_result(&result);
+    errors.extend(resolve_directives(
+        &mut result.document,
+        path,
+        resolver,
+        config,
+    ));
 
     errors
 }
 
-struct Duplicate {
-    key: String,
+struct Duplicate<'a> {
+    key: &'a str,
     original_declaration: Range,
     duplicate_declarations: Vec<Range>,
 }
 
-impl Duplicate {
-    fn new(keyvalue: &KeyValue) -> Self {
+impl<'a> Duplicate<'a> {
+    fn new<E: Entry>(keyvalue: &'a E) -> Self {
         Self {
-            key: keyvalue.key.clone(),
-            original_declaration: keyvalue.key_range,
+            key: keyvalue.key(),
+            original_declaration: keyvalue.key_range(),
             duplicate_declarations: vec![],
         }
     }
 }
 
-fn search_for_duplicates(dups: &mut Vec<Duplicate>, keyvalues: &[KeyValue]) {
-    let mut keys: HashMap<String, Duplicate> = HashMap::default();
-    for kv in keyvalues.iter() {
-        match &kv.value {
-            Value::String(_) => {
-                if let Some(dup) = keys.get_mut(&kv.key) {
-                    dup.duplicate_declarations.push(kv.key_range);
-                } else {
-                    keys.insert(kv.key.clone(), Duplicate::new(kv));
-                }
-            }
-            Value::Section(section_val) => {
-                if let Some(dup) = keys.get_mut(&kv.key) {
-                    dup.duplicate_declarations.push(kv.key_range);
-                } else {
-                    keys.insert(kv.key.clone(), Duplicate::new(kv));
-                }
-                search_for_duplicates(dups, section_val)
-            }
+/// Entry checked for duplicates, owned or borrowed.
+trait Entry: Sized {
+    fn key(&self) -> &str;
+    fn conditional(&self) -> Option<&str>;
+    fn key_range(&self) -> Range;
+    fn section(&self) -> Option<&[Self]>;
+}
+
+impl Entry for KeyValue {
+    fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn conditional(&self) -> Option<&str> {
+        self.conditional.as_deref()
+    }
+
+    fn key_range(&self) -> Range {
+        self.key_range
+    }
+
+    fn section(&self) -> Option<&[Self]> {
+        match &self.value {
+            Value::Section(section) => Some(section),
+            _ => None,
+        }
+    }
+}
+
+impl Entry for BorrowedKeyValue<'_> {
+    fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn conditional(&self) -> Option<&str> {
+        self.conditional
+    }
+
+    fn key_range(&self) -> Range {
+        self.key_range
+    }
+
+    fn section(&self) -> Option<&[Self]> {
+        match &self.value {
+            BorrowedValue::Section(section) => Some(section),
+            BorrowedValue::String(_) => None,
         }
     }
-    dups.extend(keys.into_iter().filter_map(|(_, dup)| {
-        if !dup.duplicate_declarations.is_empty() {
-            Some(dup)
+}
+
+/// Entries with the same key but different conditionals, e.g. `[$WIN32]` and `[$OSX]`,
+/// target different platforms and are not considered duplicates.
+fn search_for_duplicates<'a, E: Entry>(dups: &mut Vec<Duplicate<'a>>, keyvalues: &'a [E]) {
+    let mut keys: HashMap<(&str, Option<&str>), Duplicate> = HashMap::default();
+    for kv in keyvalues.iter() {
+        let id = (kv.key(), kv.conditional());
+        if let Some(dup) = keys.get_mut(&id) {
+            dup.duplicate_declarations.push(kv.key_range());
         } else {
-            None
+            keys.insert(id, Duplicate::new(kv));
+        }
+        if let Some(section_val) = kv.section() {
+            search_for_duplicates(dups, section_val)
         }
-    }));
+    }
+    dups.extend(
+        keys.into_values()
+            .filter(|dup| !dup.duplicate_declarations.is_empty()),
+    );
 }
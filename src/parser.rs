@@ -0,0 +1,375 @@
+use crate::{
+    serializer::serialize_tree, Document, KvError, KvErrorKind, LineIndex, ParserConfig,
+    SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree,
+};
+
+use self::lexer::tokenize;
+pub(crate) use self::lexer::{lex_token, SyntaxError};
+
+mod lexer;
+
+/// Result of an error-recovering parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseResult {
+    /// Lossless syntax tree of the input. Tokens that could not be parsed are wrapped in
+    /// [`SyntaxKind::Error`] nodes.
+    pub tree: SyntaxTree,
+
+    /// Everything that could be parsed. Entries around a syntax error are kept: a key
+    /// without a value gets an empty value, and an unclosed section ends with the input.
+    pub document: Document,
+
+    /// Syntax errors of the input, in order of appearance.
+    pub errors: Vec<KvError>,
+}
+
+/// Parse a string of keyvalue, recovering from syntax errors.
+///
+/// Unlike [`serialize_document`](crate::serialize_document), parsing never fails: every
+/// syntax error is reported and the entries around it are still parsed.
+pub fn parse_document(input: &str) -> ParseResult {
+    parse_document_with_config(input, ParserConfig::default())
+}
+
+pub fn parse_document_with_config(input: &str, config: ParserConfig) -> ParseResult {
+    let Parse {
+        tree,
+        errors,
+        line_index,
+    } = parse_tree(input, config);
+    let document = serialize_tree(&tree, line_index, config);
+
+    ParseResult {
+        tree,
+        document,
+        errors,
+    }
+}
+
+/// Syntax tree of a string of keyvalue, with its syntax errors and the index of its lines.
+pub(crate) struct Parse {
+    pub(crate) tree: SyntaxTree,
+    pub(crate) errors: Vec<KvError>,
+    pub(crate) line_index: LineIndex,
+}
+
+/// Parse the syntax tree of a string of keyvalue. The positions of the lines are indexed
+/// while lexing.
+pub(crate) fn parse_tree(input: &str, config: ParserConfig) -> Parse {
+    let (tokens, lex_errors, line_index) =
+        tokenize(input, config.escape_mode, config.position_encoding);
+    let mut parser = Parser {
+        input,
+        tokens,
+        pos: 0,
+        stack: vec![],
+        errors: lex_errors,
+        unclosed: false,
+    };
+    let root = parser.parse_document();
+
+    let mut errors = parser.errors;
+    errors.sort_by_key(|error| error.start);
+    let errors = errors
+        .into_iter()
+        .map(|error| KvError {
+            range: line_index.range(error.start, error.end),
+            additional_ranges: vec![],
+            message: error.message,
+            kind: KvErrorKind::SyntaxError,
+        })
+        .collect();
+    let tree = SyntaxTree {
+        source: input.to_string(),
+        root,
+    };
+
+    Parse {
+        tree,
+        errors,
+        line_index,
+    }
+}
+
+/// Parse the tokens between the braces of a section of `input`, for an incremental reparse.
+///
+/// Returns the children of the section between its braces, with the syntax errors of the
+/// tokens. Returns `None` if the tokens do not stand on their own, i.e. if they close the
+/// section early or leave a section open.
+pub(crate) fn parse_section_content(
+    input: &str,
+    tokens: Vec<SyntaxToken>,
+    lex_errors: Vec<SyntaxError>,
+) -> Option<(Vec<SyntaxElement>, Vec<SyntaxError>)> {
+    let mut parser = Parser {
+        input,
+        tokens,
+        pos: 0,
+        stack: vec![],
+        errors: lex_errors,
+        unclosed: false,
+    };
+    parser.stack.push(SyntaxNode {
+        kind: SyntaxKind::Section,
+        start: 0,
+        end: 0,
+        children: vec![],
+    });
+    while let Some(token) = parser.peek() {
+        if token.kind == SyntaxKind::RBrace {
+            return None;
+        }
+        parser.parse_item();
+    }
+    parser.eat_trivia();
+    if parser.unclosed {
+        return None;
+    }
+    let mut errors = parser.errors;
+    errors.sort_by_key(|error| error.start);
+    let node = parser.stack.pop().expect("the section node is open");
+
+    Some((node.children, errors))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<SyntaxToken>,
+    pos: usize,
+    /// Nodes being built, the innermost last.
+    stack: Vec<SyntaxNode>,
+    errors: Vec<SyntaxError>,
+    /// Whether a section is left open at the end of the tokens.
+    unclosed: bool,
+}
+
+impl Parser<'_> {
+    fn parse_document(&mut self) -> SyntaxNode {
+        self.start_node(SyntaxKind::Document);
+        while let Some(token) = self.peek() {
+            match token.kind {
+                SyntaxKind::RBrace => {
+                    self.error(token, "unexpected '}'");
+                    self.start_node(SyntaxKind::Error);
+                    self.bump();
+                    self.finish_node();
+                }
+                SyntaxKind::UnquotedString if self.at_directive() => self.parse_directive(),
+                _ => self.parse_item(),
+            }
+        }
+        self.eat_trivia();
+
+        let mut root = self.stack.pop().expect("the document node is open");
+        root.start = 0;
+        root.end = self.input.len();
+
+        root
+    }
+
+    /// Parse an entry of a document or a section. Tokens that cannot start an entry are
+    /// wrapped in an error node.
+    fn parse_item(&mut self) {
+        let Some(token) = self.peek() else {
+            return;
+        };
+        match token.kind {
+            kind if kind.is_string() => self.parse_keyvalue(),
+            SyntaxKind::LBrace => {
+                self.error(token, "expected a key");
+                // Parse the section anyway to keep the braces balanced.
+                self.start_node(SyntaxKind::Error);
+                self.parse_section();
+                self.finish_node();
+            }
+            _ => {
+                self.error(token, "expected a key");
+                self.start_node(SyntaxKind::Error);
+                self.bump();
+                self.finish_node();
+            }
+        }
+    }
+
+    fn parse_keyvalue(&mut self) {
+        self.start_node(SyntaxKind::KeyValue);
+        let key = self.bump().expect("the key was peeked");
+        match self.peek().map(|token| token.kind) {
+            Some(kind) if kind.is_string() && !self.at_next_entry(key) => {
+                self.bump();
+                if let Some(SyntaxKind::Conditional) = self.peek().map(|token| token.kind) {
+                    self.bump();
+                }
+            }
+            Some(SyntaxKind::Conditional) => {
+                self.bump();
+                match self.peek().map(|token| token.kind) {
+                    Some(SyntaxKind::LBrace) => self.parse_section(),
+                    _ => self.error(key, "expected '{' after the conditional"),
+                }
+            }
+            Some(SyntaxKind::LBrace) => self.parse_section(),
+            _ => self.error(key, "expected a value or a section"),
+        }
+        self.finish_node();
+    }
+
+    /// Whether the string following `key` looks like the key of the next entry rather than
+    /// the value of `key`, i.e. `key` is missing its value.
+    ///
+    /// This is the case when the string is on another line, is followed by its own value on
+    /// the same line, and the run of strings it starts cannot be split into pairs otherwise.
+    fn at_next_entry(&self, key: SyntaxToken) -> bool {
+        let mut tokens = self.tokens[self.pos..]
+            .iter()
+            .filter(|token| !token.kind.is_trivia());
+        let (Some(next), Some(after)) = (tokens.next(), tokens.next()) else {
+            return false;
+        };
+        if !self.input[key.end..next.start].contains('\n')
+            || !after.kind.is_string()
+            || self.input[next.end..after.start].contains('\n')
+        {
+            return false;
+        }
+        let mut strings = 3;
+        let mut opens_section = false;
+        for token in tokens {
+            match token.kind {
+                kind if kind.is_string() => strings += 1,
+                SyntaxKind::Conditional => (),
+                kind => {
+                    opens_section = kind == SyntaxKind::LBrace;
+                    break;
+                }
+            }
+        }
+        // Sections are opened by a lone key, so the run should be even otherwise.
+        (strings % 2 == 1) != opens_section
+    }
+
+    /// Parse a section, starting at its opening brace.
+    fn parse_section(&mut self) {
+        self.start_node(SyntaxKind::Section);
+        let l_brace = self.bump().expect("the brace was peeked");
+        loop {
+            match self.peek() {
+                None => {
+                    self.error(l_brace, "expected '}' to close the section");
+                    self.unclosed = true;
+                    break;
+                }
+                Some(token) if token.kind == SyntaxKind::RBrace => {
+                    self.bump();
+                    break;
+                }
+                Some(_) => self.parse_item(),
+            }
+        }
+        self.finish_node();
+    }
+
+    /// Whether the next token is a `#base` or `#include` followed by a path on the same line.
+    fn at_directive(&self) -> bool {
+        let Some(index) = self.tokens[self.pos..]
+            .iter()
+            .position(|token| !token.kind.is_trivia())
+        else {
+            return false;
+        };
+        let index = self.pos + index;
+        let kind = self.tokens[index];
+        let text = self.input[kind.start..kind.end].to_lowercase();
+        if text != "#base" && text != "#include" {
+            return false;
+        }
+        match self.tokens[index + 1..] {
+            [separator, path, ..] => {
+                separator.kind == SyntaxKind::Whitespace
+                    && self.input[separator.start..separator.end]
+                        .bytes()
+                        .all(|b| b == b' ' || b == b'\t')
+                    && path.kind.is_string()
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_directive(&mut self) {
+        self.start_node(SyntaxKind::Directive);
+        self.bump();
+        self.bump();
+        self.finish_node();
+    }
+
+    /// Returns the next significant token, without consuming it.
+    fn peek(&self) -> Option<SyntaxToken> {
+        self.tokens[self.pos..]
+            .iter()
+            .find(|token| !token.kind.is_trivia())
+            .copied()
+    }
+
+    /// Add the next significant token to the current node, with the trivia before it.
+    fn bump(&mut self) -> Option<SyntaxToken> {
+        self.eat_trivia();
+        let token = *self.tokens.get(self.pos)?;
+        self.pos += 1;
+        self.push(SyntaxElement::Token(token));
+
+        Some(token)
+    }
+
+    /// Add the whitespace and comments at the current position to the current node.
+    fn eat_trivia(&mut self) {
+        while let Some(token) = self.tokens.get(self.pos) {
+            if !token.kind.is_trivia() {
+                break;
+            }
+            self.push(SyntaxElement::Token(*token));
+            self.pos += 1;
+        }
+    }
+
+    /// Open a node. The trivia before it belongs to the parent node.
+    fn start_node(&mut self, kind: SyntaxKind) {
+        if !self.stack.is_empty() {
+            self.eat_trivia();
+        }
+        self.stack.push(SyntaxNode {
+            kind,
+            start: 0,
+            end: 0,
+            children: vec![],
+        });
+    }
+
+    fn finish_node(&mut self) {
+        let mut node = self.stack.pop().expect("a node is open");
+        node.start = node.children.first().map_or(0, |child| match child {
+            SyntaxElement::Node(node) => node.start,
+            SyntaxElement::Token(token) => token.start,
+        });
+        node.end = node.children.last().map_or(0, |child| match child {
+            SyntaxElement::Node(node) => node.end,
+            SyntaxElement::Token(token) => token.end,
+        });
+        self.push(SyntaxElement::Node(node));
+    }
+
+    fn push(&mut self, element: SyntaxElement) {
+        self.stack
+            .last_mut()
+            .expect("a node is open")
+            .children
+            .push(element);
+    }
+
+    fn error(&mut self, token: SyntaxToken, message: &str) {
+        self.errors.push(SyntaxError {
+            start: token.start,
+            end: token.end,
+            message: message.to_string(),
+        });
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{EscapeMode, LineIndex, PositionEncoding, SyntaxKind, SyntaxToken};
+
+/// Error encountered while lexing or parsing, with its byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SyntaxError {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) message: String,
+}
+
+/// Split an input into tokens, and index the lines of the tokens to compute positions.
+///
+/// Lexing never fails: unterminated strings, comments and conditionals are reported as
+/// errors and produce a token anyway.
+pub(crate) fn tokenize(
+    input: &str,
+    escape_mode: EscapeMode,
+    encoding: PositionEncoding,
+) -> (Vec<SyntaxToken>, Vec<SyntaxError>, LineIndex) {
+    let mut lexer = Lexer::new(input, 0, escape_mode);
+    let mut tokens = vec![];
+    let mut line_index = LineIndex::empty(encoding);
+    while let Some(token) = lexer.next_token() {
+        if !matches!(token.kind, SyntaxKind::LBrace | SyntaxKind::RBrace) {
+            line_index.push_text(token.start, &input[token.start..token.end]);
+        }
+        tokens.push(token);
+    }
+
+    (tokens, lexer.errors, line_index)
+}
+
+/// A token lexed from a prefix of the input, see [`lex_token`].
+pub(crate) struct LexedToken {
+    pub(crate) token: SyntaxToken,
+    pub(crate) errors: Vec<SyntaxError>,
+    /// Whether the lexer looked past the end of the input, so that the token could be
+    /// different if the input was longer.
+    pub(crate) reached_end: bool,
+}
+
+/// Lex the token starting at `pos`, for inputs that are read piece by piece.
+pub(crate) fn lex_token(input: &str, pos: usize, escape_mode: EscapeMode) -> Option<LexedToken> {
+    let mut lexer = Lexer::new(input, pos, escape_mode);
+    let token = lexer.next_token()?;
+
+    Some(LexedToken {
+        token,
+        errors: lexer.errors,
+        reached_end: lexer.reached_end,
+    })
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    escape_mode: EscapeMode,
+    errors: Vec<SyntaxError>,
+    /// Whether a byte past the end of the input was needed.
+    reached_end: bool,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str, pos: usize, escape_mode: EscapeMode) -> Self {
+        Self {
+            input,
+            bytes: input.as_bytes(),
+            pos,
+            escape_mode,
+            errors: vec![],
+            reached_end: false,
+        }
+    }
+
+    fn next_token(&mut self) -> Option<SyntaxToken> {
+        let start = self.pos;
+        let kind = match *self.bytes.get(start)? {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                self.eat_while(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'));
+                SyntaxKind::Whitespace
+            }
+            b'{' => {
+                self.pos += 1;
+                SyntaxKind::LBrace
+            }
+            b'}' => {
+                self.pos += 1;
+                SyntaxKind::RBrace
+            }
+            b'"' => self.lex_quoted_string(),
+            b'[' => {
+                self.lex_conditional();
+                SyntaxKind::Conditional
+            }
+            _ if self.at("//") => {
+                self.eat_while(|b| b != b'\n');
+                SyntaxKind::LineComment
+            }
+            _ if self.at("/*") => {
+                self.lex_block_comment();
+                SyntaxKind::BlockComment
+            }
+            _ => {
+                self.lex_unquoted_string();
+                SyntaxKind::UnquotedString
+            }
+        };
+
+        Some(SyntaxToken {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn at(&mut self, prefix: &str) -> bool {
+        let rest = &self.bytes[self.pos..];
+        if rest.len() < prefix.len() && prefix.as_bytes().starts_with(rest) {
+            self.reached_end = true;
+        }
+        rest.starts_with(prefix.as_bytes())
+    }
+
+    /// Returns the byte at `offset`, or notes that the end of the input was reached.
+    fn byte(&mut self, offset: usize) -> Option<u8> {
+        let byte = self.bytes.get(offset).copied();
+        self.reached_end |= byte.is_none();
+        byte
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        while self.byte(self.pos).is_some_and(&predicate) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&mut self, start: usize, end: usize, message: &str) {
+        self.errors.push(SyntaxError {
+            start,
+            end,
+            message: message.to_string(),
+        });
+    }
+
+    /// Strings may span several lines. An unterminated string is cut at the end of its
+    /// first line, so that it does not swallow the rest of the input.
+    ///
+    /// A multiline string whose closing quote is directly followed by more text, as in
+    /// `"value` then `"key"` on the next line, is the opening quote of the next string:
+    /// it is considered unterminated as well.
+    fn lex_quoted_string(&mut self) -> SyntaxKind {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.byte(self.pos) {
+                None => return self.unterminated_string(start),
+                Some(b'"') => {
+                    self.pos += 1;
+                    let multiline = self.bytes[start..self.pos].contains(&b'\n');
+                    if multiline && !self.at_delimiter() {
+                        return self.unterminated_string(start);
+                    }
+                    return SyntaxKind::QuotedString;
+                }
+                Some(b'\\') => self.lex_escape(),
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn unterminated_string(&mut self, start: usize) -> SyntaxKind {
+        let end = self.line_end(start);
+        // Drop the escape errors found past the cut.
+        self.errors.retain(|error| error.start < end);
+        self.pos = end;
+        self.error(start, end, "unterminated string");
+
+        SyntaxKind::UnterminatedString
+    }
+
+    /// Whether the next character ends the current token.
+    fn at_delimiter(&mut self) -> bool {
+        match self.byte(self.pos) {
+            None | Some(b' ' | b'\t' | b'\r' | b'\n' | b'{' | b'}' | b'[') => true,
+            _ => self.at("//") || self.at("/*"),
+        }
+    }
+
+    fn lex_escape(&mut self) {
+        let start = self.pos;
+        let next = self.input[start + 1..].chars().next();
+        // A `\u` escape needs 4 more bytes.
+        self.reached_end |= next.is_none() || (next == Some('u') && self.bytes.len() < start + 6);
+        match (self.escape_mode, next) {
+            (EscapeMode::Disabled, _) | (_, None) => self.pos += 1,
+            (EscapeMode::Valve, Some(c)) => self.pos += 1 + c.len_utf8(),
+            (EscapeMode::Json, Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => {
+                self.pos += 2
+            }
+            (EscapeMode::Json, Some('u'))
+                if self.bytes[start + 2..]
+                    .iter()
+                    .take(4)
+                    .filter(|b| b.is_ascii_hexdigit())
+                    .count()
+                    == 4 =>
+            {
+                self.pos += 6
+            }
+            (EscapeMode::Json, Some(c)) => {
+                self.pos += 1;
+                let end = if c == '\n' {
+                    start + 1
+                } else {
+                    start + 1 + c.len_utf8()
+                };
+                self.error(start, end, "invalid escape sequence");
+            }
+        }
+    }
+
+    /// Returns the offset of the end of the line containing `offset`, excluding the line break.
+    fn line_end(&mut self, offset: usize) -> usize {
+        let end = match self.input[offset..].find('\n') {
+            Some(i) => offset + i,
+            None => {
+                self.reached_end = true;
+                self.input.len()
+            }
+        };
+        if end > offset && self.bytes[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        }
+    }
+
+    fn lex_conditional(&mut self) {
+        let start = self.pos;
+        self.eat_while(|b| b != b']' && b != b'\n');
+        if self.byte(self.pos) == Some(b']') {
+            self.pos += 1;
+        } else {
+            self.pos = self.line_end(start);
+            self.error(start, self.pos, "unterminated conditional");
+        }
+    }
+
+    fn lex_block_comment(&mut self) {
+        let start = self.pos;
+        match self.input[start + 2..].find("*/") {
+            Some(i) => self.pos = start + 2 + i + 2,
+            None => {
+                self.reached_end = true;
+                self.pos = self.input.len();
+                self.error(start, self.pos, "unterminated block comment");
+            }
+        }
+    }
+
+    fn lex_unquoted_string(&mut self) {
+        while let Some(b) = self.byte(self.pos) {
+            if matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'"' | b'{' | b'}')
+                || self.at("//")
+                || self.at("/*")
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+}
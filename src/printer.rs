@@ -0,0 +1,66 @@
+use std::fmt;
+
+use crate::{
+    formatter::TokenWriter, ser::unwritable, FormatterConfig, KeyValue, SerializeError, Value,
+};
+
+impl KeyValue {
+    /// Write the entry and its children as keyvalue text, laid out according to `config`
+    /// as [`format_keyvalue`](crate::format_keyvalue) would. No empty lines are written
+    /// between entries.
+    ///
+    /// Keys and values are written quoted or not as their `key_quoted` and `value_quoted`
+    /// flags say, unless the [`QuoteStyle`](crate::QuoteStyle) says otherwise or they cannot
+    /// be written without quotes. The comments of an entry are written on their own lines
+    /// before its key, where they are read back as comments of the same entry.
+    ///
+    /// Reading the output back with the same escape mode gives the same entry, apart from
+    /// its ranges and spans and from the values of binary keyvalues, which are read back as
+    /// strings.
+    ///
+    /// Fails if a key or a value contains a quote while escapes are disabled.
+    pub fn to_string_with(&self, config: &FormatterConfig) -> Result<String, SerializeError> {
+        let mut writer = TokenWriter::new(config.clone());
+        write_keyvalue(&mut writer, self).map_err(unwritable)?;
+
+        Ok(writer.finish())
+    }
+}
+
+/// Write the entry with the default [`FormatterConfig`].
+impl fmt::Display for KeyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self
+            .to_string_with(&FormatterConfig::default())
+            .map_err(|_| fmt::Error)?;
+        f.write_str(&text)
+    }
+}
+
+fn write_keyvalue(writer: &mut TokenWriter, kv: &KeyValue) -> Result<(), String> {
+    for comment in kv.comments.iter() {
+        writer.comment(comment);
+    }
+    writer.key(&kv.key, kv.key_quoted)?;
+    match &kv.value {
+        Value::Section(section) => {
+            if let Some(conditional) = &kv.conditional {
+                writer.conditional(conditional);
+            }
+            writer.open_section();
+            for kv in section {
+                write_keyvalue(writer, kv)?;
+            }
+            writer.close_section();
+        }
+        value => {
+            let text = value.to_text().expect("only sections have no text");
+            writer.value(&text, kv.value_quoted)?;
+            if let Some(conditional) = &kv.conditional {
+                writer.conditional(conditional);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use crate::{KeyValue, Value};
+
+/// Lookups of the entries of a section by key.
+///
+/// Sections may hold several entries with the same key: [`Value::get`] returns the first
+/// one, as the engine's `KeyValues::FindKey` does, and [`Value::get_all`] all of them. The
+/// `_ignore_case` variants compare keys ignoring ASCII case, which is how the engine
+/// matches keys. Strings have no entries.
+impl Value {
+    /// Entries of the section, empty for strings.
+    pub fn entries(&self) -> &[KeyValue] {
+        match self {
+            Value::Section(section) => section,
+            _ => &[],
+        }
+    }
+
+    /// Entries of the section, empty for strings.
+    pub fn entries_mut(&mut self) -> &mut [KeyValue] {
+        match self {
+            Value::Section(section) => section,
+            _ => &mut [],
+        }
+    }
+
+    /// Keys of the entries of the section, in order, with duplicates.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries().iter().map(|kv| kv.key.as_str())
+    }
+
+    /// Text of a string value, `None` for sections and binary values.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) | Value::WString(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&KeyValue> {
+        self.entries().iter().find(|kv| kv.key == key)
+    }
+
+    pub fn get_ignore_case(&self, key: &str) -> Option<&KeyValue> {
+        self.entries()
+            .iter()
+            .find(|kv| kv.key.eq_ignore_ascii_case(key))
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a KeyValue> {
+        self.entries().iter().filter(move |kv| kv.key == key)
+    }
+
+    pub fn get_all_ignore_case<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a KeyValue> {
+        self.entries()
+            .iter()
+            .filter(move |kv| kv.key.eq_ignore_ascii_case(key))
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut KeyValue> {
+        self.entries_mut().iter_mut().find(|kv| kv.key == key)
+    }
+
+    pub fn get_mut_ignore_case(&mut self, key: &str) -> Option<&mut KeyValue> {
+        self.entries_mut()
+            .iter_mut()
+            .find(|kv| kv.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Follow a path of keys separated by `/`, e.g. `csgo/Offsets/GiveNamedItem`, taking
+    /// the first entry with each key, as `KeyValues::FindKey` does. Keys containing a `/`
+    /// cannot be reached by a path, look them up with [`Value::get`] instead.
+    pub fn get_path(&self, path: &str) -> Option<&KeyValue> {
+        let (first, rest) = split_path(path);
+        let kv = self.get(first)?;
+        match rest {
+            Some(rest) => kv.value.get_path(rest),
+            None => Some(kv),
+        }
+    }
+
+    pub fn get_path_ignore_case(&self, path: &str) -> Option<&KeyValue> {
+        let (first, rest) = split_path(path);
+        let kv = self.get_ignore_case(first)?;
+        match rest {
+            Some(rest) => kv.value.get_path_ignore_case(rest),
+            None => Some(kv),
+        }
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut KeyValue> {
+        let (first, rest) = split_path(path);
+        let kv = self.get_mut(first)?;
+        match rest {
+            Some(rest) => kv.value.get_path_mut(rest),
+            None => Some(kv),
+        }
+    }
+
+    pub fn get_path_mut_ignore_case(&mut self, path: &str) -> Option<&mut KeyValue> {
+        let (first, rest) = split_path(path);
+        let kv = self.get_mut_ignore_case(first)?;
+        match rest {
+            Some(rest) => kv.value.get_path_mut_ignore_case(rest),
+            None => Some(kv),
+        }
+    }
+
+    /// Entries of the section and of all of its subsections, depth first, in order.
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants {
+            stack: vec![self.entries().iter()],
+        }
+    }
+}
+
+/// Lookups of the entries of the section of an entry, see [`Value::get`].
+impl KeyValue {
+    pub fn entries(&self) -> &[KeyValue] {
+        self.value.entries()
+    }
+
+    pub fn entries_mut(&mut self) -> &mut [KeyValue] {
+        self.value.entries_mut()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.value.keys()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        self.value.as_str()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&KeyValue> {
+        self.value.get(key)
+    }
+
+    pub fn get_ignore_case(&self, key: &str) -> Option<&KeyValue> {
+        self.value.get_ignore_case(key)
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a KeyValue> {
+        self.value.get_all(key)
+    }
+
+    pub fn get_all_ignore_case<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a KeyValue> {
+        self.value.get_all_ignore_case(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut KeyValue> {
+        self.value.get_mut(key)
+    }
+
+    pub fn get_mut_ignore_case(&mut self, key: &str) -> Option<&mut KeyValue> {
+        self.value.get_mut_ignore_case(key)
+    }
+
+    pub fn get_path(&self, path: &str) -> Option<&KeyValue> {
+        self.value.get_path(path)
+    }
+
+    pub fn get_path_ignore_case(&self, path: &str) -> Option<&KeyValue> {
+        self.value.get_path_ignore_case(path)
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut KeyValue> {
+        self.value.get_path_mut(path)
+    }
+
+    pub fn get_path_mut_ignore_case(&mut self, path: &str) -> Option<&mut KeyValue> {
+        self.value.get_path_mut_ignore_case(path)
+    }
+
+    pub fn descendants(&self) -> Descendants<'_> {
+        self.value.descendants()
+    }
+}
+
+/// Iterator over the entries of a section and of its subsections, see
+/// [`Value::descendants`].
+pub struct Descendants<'a> {
+    stack: Vec<std::slice::Iter<'a, KeyValue>>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a KeyValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entries = self.stack.last_mut()?;
+            let Some(kv) = entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if let Value::Section(section) = &kv.value {
+                self.stack.push(section.iter());
+            }
+            return Some(kv);
+        }
+    }
+}
+
+/// First key of a path, and the rest of the path if there is more than one key.
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('/') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (path, None),
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    serialize_document_with_config, Directive, DirectiveKind, Document, KeyValue, KvError,
+    KvErrorKind, ParserConfig, Range, Value,
+};
+
+/// Source of the files referenced by `#base` and `#include` directives.
+pub trait FileResolver {
+    /// Load the contents of the file at `path`, or `None` if it does not exist.
+    ///
+    /// Paths are relative, use `/` as a separator and are already normalized.
+    fn load(&self, path: &str) -> Option<String>;
+}
+
+/// Resolve files relative to a root directory on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSystemResolver {
+    root: PathBuf,
+}
+
+impl FileSystemResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl FileResolver for FileSystemResolver {
+    fn load(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(self.root.join(path)).ok()
+    }
+}
+
+/// Resolve files from an in-memory map of paths to contents.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemoryResolver {
+    files: HashMap<String, String>,
+}
+
+#[wasm_bindgen]
+impl MemoryResolver {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file to the resolver, replacing any previous file with the same path.
+    pub fn insert(&mut self, path: &str, contents: &str) {
+        self.files
+            .insert(normalize_path(path), contents.to_string());
+    }
+}
+
+impl FileResolver for MemoryResolver {
+    fn load(&self, path: &str) -> Option<String> {
+        self.files.get(path).cloned()
+    }
+}
+
+/// Document with its directives resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedDocument {
+    /// Document with the keys of the referenced files merged into its roots.
+    pub document: Document,
+
+    /// Errors encountered while resolving the directives.
+    /// The range of each error is the range of the directive of the input
+    /// that caused it, even if the error happened in a nested file.
+    pub errors: Vec<KvError>,
+}
+
+/// Parse a document and resolve its `#base` and `#include` directives.
+///
+/// Directives are resolved relative to the directory of `path`, following Valve's rules:
+/// - roots of `#include`d files are appended after the roots of the document,
+/// - roots of `#base` files are merged recursively into the first root, without overriding
+///   existing keys.
+pub fn resolve_document<R: FileResolver + ?Sized>(
+    input: &str,
+    path: &str,
+    resolver: &R,
+) -> Result<ResolvedDocument, KvError> {
+    resolve_document_with_config(input, path, resolver, ParserConfig::default())
+}
+
+/// Parse a document and resolve its directives, see [`resolve_document`]. The referenced
+/// files are parsed with the same configuration.
+pub fn resolve_document_with_config<R: FileResolver + ?Sized>(
+    input: &str,
+    path: &str,
+    resolver: &R,
+    config: ParserConfig,
+) -> Result<ResolvedDocument, KvError> {
+    let mut document = serialize_document_with_config(input, config)?;
+    let errors = resolve_directives(&mut document, path, resolver, config);
+
+    Ok(ResolvedDocument { document, errors })
+}
+
+/// Merge the files referenced by the directives of a document into its roots, and return
+/// the errors encountered. The document may come from an input with syntax errors.
+pub(crate) fn resolve_directives<R: FileResolver + ?Sized>(
+    document: &mut Document,
+    path: &str,
+    resolver: &R,
+    config: ParserConfig,
+) -> Vec<KvError> {
+    let path = normalize_path(path);
+    let mut resolution = Resolution {
+        resolver,
+        config,
+        stack: vec![path.clone()],
+    };
+
+    resolution.merge_directives(&path, &document.directives, &mut document.roots)
+}
+
+struct Resolution<'a, R: FileResolver + ?Sized> {
+    resolver: &'a R,
+    config: ParserConfig,
+    /// Paths of the files currently being resolved, used to detect cycles.
+    stack: Vec<String>,
+}
+
+impl<R: FileResolver + ?Sized> Resolution<'_, R> {
+    fn merge_directives(
+        &mut self,
+        path: &str,
+        directives: &[Directive],
+        roots: &mut Vec<KeyValue>,
+    ) -> Vec<KvError> {
+        let mut errors = vec![];
+        let mut bases = vec![];
+        for directive in directives {
+            let (kvs, directive_errors) = self.resolve_file(path, directive);
+            errors.extend(directive_errors.into_iter().map(|error| KvError {
+                range: directive.path_range,
+                ..error
+            }));
+            match directive.kind {
+                DirectiveKind::Include => roots.extend(kvs),
+                DirectiveKind::Base => bases.extend(kvs),
+            }
+        }
+        // Base keys are merged once all the includes have been appended.
+        if let Some(root) = roots.first_mut() {
+            for base in bases {
+                merge_keys(root, base);
+            }
+        }
+
+        errors
+    }
+
+    fn resolve_file(
+        &mut self,
+        parent: &str,
+        directive: &Directive,
+    ) -> (Vec<KeyValue>, Vec<KvError>) {
+        let path = join_path(parent, &directive.path);
+        if self.stack.contains(&path) {
+            let cycle = self
+                .stack
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|path| format!("\"{}\"", path))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return (
+                vec![],
+                vec![error(
+                    format!("Include cycle: {}", cycle),
+                    KvErrorKind::IncludeCycleError,
+                )],
+            );
+        }
+        let Some(input) = self.resolver.load(&path) else {
+            return (
+                vec![],
+                vec![error(
+                    format!("File \"{}\" not found", path),
+                    KvErrorKind::MissingFileError,
+                )],
+            );
+        };
+        let mut document = match serialize_document_with_config(&input, self.config) {
+            Ok(document) => document,
+            Err(err) => {
+                return (
+                    vec![],
+                    vec![error(
+                        format!("Syntax error in \"{}\": {}", path, err.message),
+                        KvErrorKind::SyntaxError,
+                    )],
+                )
+            }
+        };
+        self.stack.push(path.clone());
+        let errors = self.merge_directives(&path, &document.directives, &mut document.roots);
+        self.stack.pop();
+
+        (document.roots, errors)
+    }
+}
+
+fn error(message: String, kind: KvErrorKind) -> KvError {
+    KvError {
+        range: Range::default(),
+        additional_ranges: vec![],
+        message,
+        kind,
+    }
+}
+
+/// Recursively merge the keys of a base into a section, keeping the existing keys. Keys are
+/// matched ignoring ASCII case, as the engine does.
+fn merge_keys(kv: &mut KeyValue, base: KeyValue) {
+    let (Value::Section(section), Value::Section(base_section)) = (&mut kv.value, base.value)
+    else {
+        return;
+    };
+    for base_kv in base_section {
+        if let Some(existing) = section
+            .iter_mut()
+            .find(|kv| kv.key.eq_ignore_ascii_case(&base_kv.key))
+        {
+            merge_keys(existing, base_kv);
+        } else {
+            section.push(base_kv);
+        }
+    }
+}
+
+/// Resolve `relative` against the directory of the file at `parent`.
+fn join_path(parent: &str, relative: &str) -> String {
+    let directory = match parent.rsplit_once('/') {
+        Some((directory, _)) => directory,
+        None => "",
+    };
+    normalize_path(&format!("{}/{}", directory, relative))
+}
+
+/// Normalize separators and remove `.` and `..` segments from a path.
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    segments.join("/")
+}
@@ -0,0 +1,495 @@
+use std::{collections::HashSet, fmt, iter::Peekable, ptr, str::CharIndices};
+
+use crate::{KeyValue, Value};
+
+/// Error raised when a selector cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    /// Selector that failed to parse.
+    pub selector: String,
+
+    /// Byte offset of the error in the selector.
+    pub offset: usize,
+
+    /// Error message of the error.
+    pub message: String,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid selector `{}` at offset {}: {}",
+            self.selector, self.offset, self.message
+        )
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+/// A query over the entries of a keyvalue tree.
+///
+/// A selector is a path of steps separated by `/`, read from the entries it is applied to:
+///
+/// ```text
+/// selector   = step ("/" step)*
+/// step       = "**" | key filter*
+/// key        = bare key | quoted key
+/// filter     = "[" index "]" | "[" "!"? operand (("=" | "!=") key)? "]"
+/// operand    = "." | selector
+/// ```
+///
+/// - A key matches the entries with that key. Bare keys can hold the wildcards `*`, any
+///   text, and `?`, any character. Quoted keys, e.g. `"Weapon Drop"`, are matched
+///   literally and may hold any character, with `\"` and `\\` escapes.
+/// - `**` matches any number of nested sections, including none: `**/#format` matches
+///   the `#format` entries at any depth.
+/// - `[n]` keeps the `n`th of the entries matched in a same section, counting from `0`,
+///   or from the end for negative indexes: `Keys/Signature[1]` is the second
+///   `Signature` of `Keys`. Only a whole integer is an index: `*[1v1]` is a predicate on
+///   a `1v1` key.
+/// - `[operand]` keeps the entries with at least one entry matching the operand, a
+///   selector applied to their own entries, and `[operand="text"]` those where the value
+///   of one of them matches the text, e.g. `*[windows="400"]`. `.` stands for the value
+///   of the entry itself. `!` negates the filter: `Offsets/*[!linux]` are the offsets
+///   without a `linux` value.
+///
+/// Filters are applied in order, so `*[linux][0]` is the first entry with a `linux`
+/// value, while `*[0][linux]` is the first entry, if it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Descend,
+    Key(Pattern, Vec<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Index(i64),
+    Predicate {
+        negated: bool,
+        operand: Operand,
+        comparison: Option<Comparison>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    Value,
+    Selector(Selector),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparison {
+    equal: bool,
+    pattern: Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    text: String,
+    wildcards: bool,
+}
+
+/// Select the entries matching a selector in a tree, see [`Selector`].
+pub fn select<'a>(
+    roots: &'a [KeyValue],
+    selector: &str,
+) -> Result<Vec<&'a KeyValue>, SelectorError> {
+    Ok(Selector::parse(selector)?.select(roots))
+}
+
+/// Select the entries matching a selector in a tree, ignoring the ASCII case of keys, see
+/// [`Selector`].
+pub fn select_ignore_case<'a>(
+    roots: &'a [KeyValue],
+    selector: &str,
+) -> Result<Vec<&'a KeyValue>, SelectorError> {
+    Ok(Selector::parse(selector)?.select_ignore_case(roots))
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        SelectorParser::new(selector).parse()
+    }
+
+    /// Entries of the tree matching the selector, in document order. Values are compared
+    /// exactly.
+    pub fn select<'a>(&self, roots: &'a [KeyValue]) -> Vec<&'a KeyValue> {
+        self.select_with(roots, false)
+    }
+
+    /// Entries of the tree matching the selector, comparing keys ignoring ASCII case as
+    /// the engine does. Values are still compared exactly.
+    pub fn select_ignore_case<'a>(&self, roots: &'a [KeyValue]) -> Vec<&'a KeyValue> {
+        self.select_with(roots, true)
+    }
+
+    fn select_with<'a>(&self, roots: &'a [KeyValue], ignore_case: bool) -> Vec<&'a KeyValue> {
+        let matches: HashSet<*const KeyValue> = self
+            .matches(roots, ignore_case)
+            .into_iter()
+            .map(ptr::from_ref)
+            .collect();
+        // Matches are collected section by section, walk the tree to sort them.
+        roots
+            .iter()
+            .flat_map(|kv| std::iter::once(kv).chain(kv.descendants()))
+            .filter(|kv| matches.contains(&ptr::from_ref(*kv)))
+            .collect()
+    }
+
+    /// Entries matching the selector, grouped by the section that holds them.
+    fn matches<'a>(&self, entries: &'a [KeyValue], ignore_case: bool) -> Vec<&'a KeyValue> {
+        let mut sections = vec![entries];
+        let mut matches = vec![];
+        for step in &self.steps {
+            match step {
+                Step::Descend => {
+                    sections = nested_sections(&sections);
+                    matches = sections.iter().flat_map(|section| section.iter()).collect();
+                }
+                Step::Key(pattern, filters) => {
+                    matches.clear();
+                    for section in &sections {
+                        let mut group: Vec<_> = section
+                            .iter()
+                            .filter(|kv| pattern.matches(&kv.key, ignore_case))
+                            .collect();
+                        for filter in filters {
+                            group = filter.apply(group, ignore_case);
+                        }
+                        matches.extend(group);
+                    }
+                    sections = matches.iter().map(|kv| kv.entries()).collect();
+                }
+            }
+        }
+
+        matches
+    }
+}
+
+impl Filter {
+    fn apply<'a>(&self, mut group: Vec<&'a KeyValue>, ignore_case: bool) -> Vec<&'a KeyValue> {
+        match self {
+            Filter::Index(index) => {
+                let index = if *index < 0 {
+                    group.len() as i64 + index
+                } else {
+                    *index
+                };
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| group.get(index).copied())
+                    .into_iter()
+                    .collect()
+            }
+            Filter::Predicate {
+                negated,
+                operand,
+                comparison,
+            } => {
+                group.retain(|kv| {
+                    let found = match operand {
+                        Operand::Value if comparison.is_none() => kv.value.to_text().is_some(),
+                        Operand::Value => compare(&kv.value, comparison.as_ref()),
+                        Operand::Selector(selector) => selector
+                            .matches(kv.entries(), ignore_case)
+                            .iter()
+                            .any(|kv| compare(&kv.value, comparison.as_ref())),
+                    };
+                    found != *negated
+                });
+                group
+            }
+        }
+    }
+}
+
+/// Whether a value passes a comparison, if there is one. Sections never match a text.
+fn compare(value: &Value, comparison: Option<&Comparison>) -> bool {
+    match (comparison, value.to_text()) {
+        (Some(comparison), Some(text)) => {
+            comparison.pattern.matches(&text, false) == comparison.equal
+        }
+        (Some(_), None) => false,
+        (None, _) => true,
+    }
+}
+
+/// The given sections and all the sections nested in them, without duplicates.
+fn nested_sections<'a>(sections: &[&'a [KeyValue]]) -> Vec<&'a [KeyValue]> {
+    fn visit<'a>(
+        section: &'a [KeyValue],
+        seen: &mut HashSet<*const KeyValue>,
+        result: &mut Vec<&'a [KeyValue]>,
+    ) {
+        // Empty sections have nothing to match, and no address to tell them apart.
+        if section.is_empty() || !seen.insert(section.as_ptr()) {
+            return;
+        }
+        result.push(section);
+        for kv in section {
+            visit(kv.entries(), seen, result);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut result = vec![];
+    for section in sections {
+        visit(section, &mut seen, &mut result);
+    }
+
+    result
+}
+
+impl Pattern {
+    fn matches(&self, text: &str, ignore_case: bool) -> bool {
+        let eq = |a: char, b: char| a == b || (ignore_case && a.eq_ignore_ascii_case(&b));
+        if !self.wildcards {
+            return self.text.chars().count() == text.chars().count()
+                && self.text.chars().zip(text.chars()).all(|(a, b)| eq(a, b));
+        }
+        let pattern: Vec<char> = self.text.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        // Greedy matching, going back to the last `*` on a mismatch.
+        let (mut p, mut t) = (0, 0);
+        let mut star = None;
+        while t < text.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    star = Some((p, t));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || eq(c, text[t]) => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match star {
+                    Some((star_p, star_t)) => {
+                        p = star_p + 1;
+                        t = star_t + 1;
+                        star = Some((star_p, star_t + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+}
+
+/// Recursive descent parser for selectors, see [`Selector`].
+struct SelectorParser<'a> {
+    selector: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> SelectorParser<'a> {
+    fn new(selector: &'a str) -> Self {
+        Self {
+            selector,
+            chars: selector.char_indices().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Selector, SelectorError> {
+        let selector = self.parse_selector()?;
+        match self.chars.peek() {
+            None => Ok(selector),
+            Some(&(_, c)) => Err(self.error(format!("unexpected character '{}'", c))),
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, SelectorError> {
+        let mut steps = vec![self.parse_step()?];
+        while self.eat('/') {
+            steps.push(self.parse_step()?);
+        }
+
+        Ok(Selector { steps })
+    }
+
+    fn parse_step(&mut self) -> Result<Step, SelectorError> {
+        let Some(pattern) = self.parse_key()? else {
+            return Err(self.error("expected a key".to_string()));
+        };
+        if pattern.wildcards && pattern.text == "**" {
+            if self.peek_is('[') {
+                return Err(self.error("'**' cannot be filtered".to_string()));
+            }
+            return Ok(Step::Descend);
+        }
+        let mut filters = vec![];
+        while self.eat('[') {
+            filters.push(self.parse_filter()?);
+            self.skip_whitespace();
+            if !self.eat(']') {
+                return Err(self.error("expected ']'".to_string()));
+            }
+        }
+
+        Ok(Step::Key(pattern, filters))
+    }
+
+    fn parse_filter(&mut self) -> Result<Filter, SelectorError> {
+        self.skip_whitespace();
+        if self.at_index() {
+            return self.parse_index();
+        }
+        let negated = self.eat('!');
+        self.skip_whitespace();
+        let operand = if self.peek_is('.') && self.is_bare_dot() {
+            self.chars.next();
+            Operand::Value
+        } else {
+            Operand::Selector(self.parse_selector()?)
+        };
+        self.skip_whitespace();
+        let equal = if self.eat('=') {
+            true
+        } else if self.eat('!') {
+            if !self.eat('=') {
+                return Err(self.error("expected '='".to_string()));
+            }
+            false
+        } else {
+            return Ok(Filter::Predicate {
+                negated,
+                operand,
+                comparison: None,
+            });
+        };
+        self.skip_whitespace();
+        let Some(pattern) = self.parse_key()? else {
+            return Err(self.error("expected a value".to_string()));
+        };
+
+        Ok(Filter::Predicate {
+            negated,
+            operand,
+            comparison: Some(Comparison { equal, pattern }),
+        })
+    }
+
+    /// Whether the filter is an integer up to its `]`, rather than a predicate on a key
+    /// starting like a number, such as `1v1`.
+    fn at_index(&self) -> bool {
+        let content: String = self
+            .chars
+            .clone()
+            .map(|(_, c)| c)
+            .take_while(|c| *c != ']')
+            .collect();
+        let content = content.trim_end();
+        let digits = content.strip_prefix('-').unwrap_or(content);
+        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+    }
+
+    fn parse_index(&mut self) -> Result<Filter, SelectorError> {
+        let start = self.offset();
+        self.eat('-');
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_digit()) {
+            self.chars.next();
+        }
+        let text = &self.selector[start..self.offset()];
+        text.parse()
+            .map(Filter::Index)
+            .map_err(|_| self.error_at(start, format!("invalid index '{}'", text)))
+    }
+
+    /// Parse a quoted or bare key, or `None` if there is none.
+    fn parse_key(&mut self) -> Result<Option<Pattern>, SelectorError> {
+        if self.eat('"') {
+            let mut text = String::new();
+            loop {
+                match self.chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => match self.chars.next() {
+                        Some((_, c)) => text.push(c),
+                        None => return Err(self.error("unterminated string".to_string())),
+                    },
+                    Some((_, c)) => text.push(c),
+                    None => return Err(self.error("unterminated string".to_string())),
+                }
+            }
+            return Ok(Some(Pattern {
+                text,
+                wildcards: false,
+            }));
+        }
+        let start = self.offset();
+        while matches!(self.chars.peek(), Some((_, c)) if !is_delimiter(*c)) {
+            self.chars.next();
+        }
+        let text = &self.selector[start..self.offset()];
+        if text.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Pattern {
+            text: text.to_string(),
+            wildcards: text.contains(['*', '?']),
+        }))
+    }
+
+    /// Whether the next `.` stands on its own for the value of an entry, rather than
+    /// starting a key such as `.git`.
+    fn is_bare_dot(&self) -> bool {
+        let mut chars = self.chars.clone();
+        chars.next();
+        match chars.peek() {
+            Some((_, c)) => is_delimiter(*c),
+            None => true,
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek_is(expected) {
+            self.chars.next();
+            return true;
+        }
+
+        false
+    }
+
+    fn peek_is(&mut self, expected: char) -> bool {
+        matches!(self.chars.peek(), Some((_, c)) if *c == expected)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn offset(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.selector.len(), |(offset, _)| *offset)
+    }
+
+    fn error(&mut self, message: String) -> SelectorError {
+        let offset = self.offset();
+        self.error_at(offset, message)
+    }
+
+    fn error_at(&self, offset: usize, message: String) -> SelectorError {
+        SelectorError {
+            selector: self.selector.to_string(),
+            offset,
+            message,
+        }
+    }
+}
+
+/// Characters that end a bare key.
+fn is_delimiter(c: char) -> bool {
+    matches!(c, '/' | '[' | ']' | '"' | '=' | '!') || c.is_whitespace()
+}
@@ -0,0 +1,729 @@
+use std::{fmt, io};
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{formatter::TokenWriter, FormatterConfig};
+
+/// Error raised when a Rust value cannot be serialized as keyvalue text.
+#[derive(Debug)]
+pub enum SerializeError {
+    /// The output could not be written.
+    Io(io::Error),
+
+    /// The value cannot be represented as keyvalue, e.g. a sequence at the root.
+    Message(String),
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::Io(err) => write!(f, "{err}"),
+            SerializeError::Message(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SerializeError::Io(err) => Some(err),
+            SerializeError::Message(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SerializeError {
+    fn from(err: io::Error) -> Self {
+        SerializeError::Io(err)
+    }
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        SerializeError::Message(message.to_string())
+    }
+}
+
+/// How sequences are written, see [`SerializerConfig`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceStyle {
+    /// Repeat the key of the sequence for each of its elements, e.g. `"include" "a.cfg"`
+    /// then `"include" "b.cfg"`. Empty sequences and sequences of sequences cannot be
+    /// written this way.
+    #[default]
+    RepeatedKeys,
+
+    /// Write the sequence as a section whose keys are the indices of the elements, counted
+    /// from the given index, e.g. `"include" { "1" "a.cfg" "2" "b.cfg" }`.
+    /// [`from_str_with_config`](crate::from_str_with_config) reads it back with the same
+    /// style in its configuration.
+    Indexed(u32),
+}
+
+/// Configuration options for [`to_string_with_config`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerializerConfig {
+    /// Layout of the output, as for [`format_keyvalue`](crate::format_keyvalue).
+    pub formatter: FormatterConfig,
+
+    /// How sequences map to keys.
+    pub sequence_style: SequenceStyle,
+}
+
+/// Serialize a Rust value as a string of keyvalue.
+///
+/// The value must be a map or a struct, whose entries are the root entries of the output.
+/// This mirrors [`from_str`](crate::from_str):
+///
+/// - Strings, numbers, characters and unit enum variants are written as strings, and
+///   booleans as `1` or `0`.
+/// - Maps and structs are written as sections. Map keys must be strings, numbers,
+///   characters or unit enum variants.
+/// - Sequences are written according to the [`SequenceStyle`] of the configuration.
+/// - Other enum variants are written as a section holding a single entry whose key is the
+///   variant.
+/// - `None` values are skipped, along with their key. Sequences cannot hold `None`.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    to_string_with_config(value, &SerializerConfig::default())
+}
+
+pub fn to_string_with_config<T: Serialize + ?Sized>(
+    value: &T,
+    config: &SerializerConfig,
+) -> Result<String, SerializeError> {
+    let mut writer = TokenWriter::new(config.formatter.clone());
+    value.serialize(RootSerializer {
+        writer: &mut writer,
+        style: config.sequence_style,
+    })?;
+
+    Ok(writer.finish())
+}
+
+/// Serialize a Rust value as keyvalue text into an [`io::Write`], see [`to_string`].
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), SerializeError> {
+    to_writer_with_config(writer, value, &SerializerConfig::default())
+}
+
+pub fn to_writer_with_config<W: io::Write, T: Serialize + ?Sized>(
+    mut writer: W,
+    value: &T,
+    config: &SerializerConfig,
+) -> Result<(), SerializeError> {
+    let output = to_string_with_config(value, config)?;
+    writer.write_all(output.as_bytes())?;
+
+    Ok(())
+}
+
+fn unsupported(what: &str) -> SerializeError {
+    SerializeError::Message(format!("{what} cannot be serialized as keyvalue"))
+}
+
+pub(crate) fn unwritable(text: String) -> SerializeError {
+    SerializeError::Message(format!(
+        "\"{text}\" contains a quote, which cannot be written without escape sequences"
+    ))
+}
+
+/// The value being serialized, whose entries are the root entries.
+struct RootSerializer<'w> {
+    writer: &'w mut TokenWriter,
+    style: SequenceStyle,
+}
+
+macro_rules! unsupported_at_root {
+    ($($method:ident($($arg:ty),*),)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(SerializeError::Message(
+                    "expected a map or a struct at the root".to_string(),
+                ))
+            }
+        )*
+    };
+}
+
+impl<'w> ser::Serializer for RootSerializer<'w> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<(), SerializeError>;
+    type SerializeTuple = Impossible<(), SerializeError>;
+    type SerializeTupleStruct = Impossible<(), SerializeError>;
+    type SerializeTupleVariant = Impossible<(), SerializeError>;
+    type SerializeMap = EntriesSerializer<'w>;
+    type SerializeStruct = EntriesSerializer<'w>;
+    type SerializeStructVariant = EntriesSerializer<'w>;
+
+    unsupported_at_root! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    /// The variant is the single root entry.
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(EntrySerializer {
+            writer: self.writer,
+            style: self.style,
+            key: variant,
+            repeated: false,
+            element: false,
+        })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        Err(unsupported("a sequence at the root"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        Err(unsupported("a tuple at the root"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        Err(unsupported("a tuple at the root"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        Err(unsupported("a tuple at the root"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        Ok(EntriesSerializer::new(self.writer, self.style, 0))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        Ok(EntriesSerializer::new(self.writer, self.style, 0))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        self.writer.key(variant, true).map_err(unwritable)?;
+        self.writer.open_section();
+        Ok(EntriesSerializer::new(self.writer, self.style, 1))
+    }
+}
+
+/// An entry with the given key, whose value is being serialized.
+struct EntrySerializer<'w, 'k> {
+    writer: &'w mut TokenWriter,
+    style: SequenceStyle,
+    key: &'k str,
+    /// Whether the entry is an element of a sequence written as repeated keys.
+    repeated: bool,
+    /// Whether the entry is an element of a sequence, which cannot be skipped.
+    element: bool,
+}
+
+impl<'w> EntrySerializer<'w, '_> {
+    fn string(self, text: &str) -> Result<(), SerializeError> {
+        self.writer.key(self.key, true).map_err(unwritable)?;
+        self.writer.value(text, true).map_err(unwritable)
+    }
+
+    /// Open a section for the value, closed by the returned serializer.
+    fn section(self) -> Result<EntriesSerializer<'w>, SerializeError> {
+        self.writer.key(self.key, true).map_err(unwritable)?;
+        self.writer.open_section();
+        Ok(EntriesSerializer::new(self.writer, self.style, 1))
+    }
+
+    fn sequence(self) -> Result<SequenceSerializer<'w>, SerializeError> {
+        match self.style {
+            SequenceStyle::RepeatedKeys if self.repeated => {
+                Err(unsupported("a sequence of sequences with repeated keys"))
+            }
+            SequenceStyle::RepeatedKeys => Ok(SequenceSerializer {
+                writer: self.writer,
+                style: self.style,
+                key: Some(self.key.to_string()),
+                index: 0,
+                close: 0,
+            }),
+            SequenceStyle::Indexed(start) => {
+                self.writer.key(self.key, true).map_err(unwritable)?;
+                self.writer.open_section();
+                Ok(SequenceSerializer {
+                    writer: self.writer,
+                    style: self.style,
+                    key: None,
+                    index: start,
+                    close: 1,
+                })
+            }
+        }
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<(), SerializeError> {
+                self.string(&value.to_string())
+            }
+        )*
+    };
+}
+
+impl<'w> ser::Serializer for EntrySerializer<'w, '_> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = SequenceSerializer<'w>;
+    type SerializeTuple = SequenceSerializer<'w>;
+    type SerializeTupleStruct = SequenceSerializer<'w>;
+    type SerializeTupleVariant = SequenceSerializer<'w>;
+    type SerializeMap = EntriesSerializer<'w>;
+    type SerializeStruct = EntriesSerializer<'w>;
+    type SerializeStructVariant = EntriesSerializer<'w>;
+
+    serialize_display! {
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<(), SerializeError> {
+        self.string(if value { "1" } else { "0" })
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<(), SerializeError> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        if self.element {
+            return Err(unsupported("None in a sequence"));
+        }
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        self.string("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        self.string("")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        let entries = self.section()?;
+        value.serialize(EntrySerializer {
+            writer: &mut *entries.writer,
+            style: entries.style,
+            key: variant,
+            repeated: false,
+            element: false,
+        })?;
+        ser::SerializeMap::end(entries)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        self.sequence()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        self.sequence()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        self.sequence()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        let entries = self.section()?;
+        let mut sequence = EntrySerializer {
+            writer: entries.writer,
+            style: entries.style,
+            key: variant,
+            repeated: false,
+            element: false,
+        }
+        .sequence()?;
+        sequence.close += 1;
+
+        Ok(sequence)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        self.section()
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        self.section()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        let mut entries = self.section()?;
+        entries.writer.key(variant, true).map_err(unwritable)?;
+        entries.writer.open_section();
+        entries.close += 1;
+
+        Ok(entries)
+    }
+}
+
+/// Entries of a map or a struct, followed by the closing braces of the sections opened
+/// for them.
+struct EntriesSerializer<'w> {
+    writer: &'w mut TokenWriter,
+    style: SequenceStyle,
+    /// Key of the entry whose value is serialized next.
+    key: Option<String>,
+    close: usize,
+}
+
+impl<'w> EntriesSerializer<'w> {
+    fn new(writer: &'w mut TokenWriter, style: SequenceStyle, close: usize) -> Self {
+        Self {
+            writer,
+            style,
+            key: None,
+            close,
+        }
+    }
+
+    fn entry<'a>(&'a mut self, key: &'a str) -> EntrySerializer<'a, 'a> {
+        EntrySerializer {
+            writer: self.writer,
+            style: self.style,
+            key,
+            repeated: false,
+            element: false,
+        }
+    }
+
+    fn finish(self) -> Result<(), SerializeError> {
+        for _ in 0..self.close {
+            self.writer.close_section();
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for EntriesSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self.key.take().expect("a key was serialized");
+        value.serialize(self.entry(&key))
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for EntriesSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self.entry(key))
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for EntriesSerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self.entry(key))
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+/// Elements of a sequence, written with a repeated key or with their indices as keys.
+struct SequenceSerializer<'w> {
+    writer: &'w mut TokenWriter,
+    style: SequenceStyle,
+    /// Key repeated for each element, `None` to use the indices.
+    key: Option<String>,
+    /// Index of the next element.
+    index: u32,
+    close: usize,
+}
+
+impl SequenceSerializer<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let (key, repeated) = match &self.key {
+            Some(key) => (key.clone(), true),
+            None => (self.index.to_string(), false),
+        };
+        self.index += 1;
+        value.serialize(EntrySerializer {
+            writer: self.writer,
+            style: self.style,
+            key: &key,
+            repeated,
+            element: true,
+        })
+    }
+
+    fn finish(self) -> Result<(), SerializeError> {
+        if self.key.is_some() && self.index == 0 {
+            return Err(unsupported("an empty sequence with repeated keys"));
+        }
+        for _ in 0..self.close {
+            self.writer.close_section();
+        }
+        Ok(())
+    }
+}
+
+macro_rules! serialize_sequence {
+    ($($trait:ident::$method:ident,)*) => {
+        $(
+            impl ser::$trait for SequenceSerializer<'_> {
+                type Ok = ();
+                type Error = SerializeError;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+                    self.element(value)
+                }
+
+                fn end(self) -> Result<(), SerializeError> {
+                    self.finish()
+                }
+            }
+        )*
+    };
+}
+
+serialize_sequence! {
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field,
+}
+
+/// Serializes the key of a map entry to a string.
+struct KeySerializer;
+
+macro_rules! key_display {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<String, SerializeError> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! key_unsupported {
+    ($($method:ident($($arg:ty),*) -> $ok:ty,)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, SerializeError> {
+                Err(unsupported("a map key that is not a string"))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<String, SerializeError>;
+    type SerializeTuple = Impossible<String, SerializeError>;
+    type SerializeTupleStruct = Impossible<String, SerializeError>;
+    type SerializeTupleVariant = Impossible<String, SerializeError>;
+    type SerializeMap = Impossible<String, SerializeError>;
+    type SerializeStruct = Impossible<String, SerializeError>;
+    type SerializeStructVariant = Impossible<String, SerializeError>;
+
+    key_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    key_unsupported! {
+        serialize_bytes(&[u8]) -> String,
+        serialize_none() -> String,
+        serialize_unit() -> String,
+        serialize_unit_struct(&'static str) -> String,
+        serialize_seq(Option<usize>) -> Self::SerializeSeq,
+        serialize_tuple(usize) -> Self::SerializeTuple,
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct,
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant,
+        serialize_map(Option<usize>) -> Self::SerializeMap,
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct,
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant,
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerializeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerializeError> {
+        Err(unsupported("a map key that is not a string"))
+    }
+}
@@ -1,72 +1,470 @@
-use pest::{iterators::Pair, Parser};
-use pest_derive::Parser;
+use std::borrow::Cow;
 
-use crate::{collectors::RangeCollector, Range};
+#[cfg(target_arch = "wasm32")]
+use js_sys::Array;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsValue;
 
-#[derive(Parser)]
-#[grammar = "valve-kv.pest"]
-pub struct KeyValueParser;
+use crate::{
+    parser::{parse_tree, Parse},
+    BorrowedKeyValue, BorrowedValue, EscapeMode, KvError, KvErrorKind, LineIndex, PositionEncoding,
+    Range, Span, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyValue {
     pub key: String,
     pub value: Value,
     pub key_range: Range,
+    pub key_span: Span,
+    /// Range of the value string, or of the section from its `{` to its `}`.
+    /// Empty, at the end of the key, if the value is missing.
+    pub value_range: Range,
+    pub value_span: Span,
+    /// Range of the whole entry, from its key to its value or section, including
+    /// its conditional.
+    pub range: Range,
+    pub span: Span,
+    /// Range of the opening brace of the section. `None` for string values.
+    pub l_brace_range: Option<Range>,
+    /// Range of the closing brace of the section. `None` for string values, or if
+    /// the section is not closed.
+    pub r_brace_range: Option<Range>,
+    /// Whether the key was surrounded by quotes in the source.
+    pub key_quoted: bool,
+    /// Whether the value was surrounded by quotes in the source.
+    /// Always `false` for sections.
+    pub value_quoted: bool,
+    /// Platform condition following the value, or preceding the section,
+    /// without its brackets, e.g. `$WIN32` for `[$WIN32]`.
+    pub conditional: Option<String>,
+    /// Comments of the entry, with their delimiters: the comments since the previous
+    /// entry, those inside of the entry, and one following it on its last line.
+    /// Comments at the end of a section go to its last entry.
+    pub comments: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl KeyValue {
+    /// Create an entry that does not come from a text file. Its ranges and spans are empty.
+    pub fn new(key: impl Into<String>, value: Value) -> Self {
+        Self {
+            key: key.into(),
+            value,
+            key_range: Range::default(),
+            key_span: Span::default(),
+            value_range: Range::default(),
+            value_span: Span::default(),
+            range: Range::default(),
+            span: Span::default(),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: false,
+            value_quoted: false,
+            conditional: None,
+            comments: vec![],
+        }
+    }
+}
+
+/// Value of an entry.
+///
+/// Text files only contain strings and sections, the other variants come from
+/// [binary keyvalues](crate::read_binary).
+#[derive(Debug, Clone)]
 pub enum Value {
     String(String),
     Section(Vec<KeyValue>),
+    Int32(i32),
+    Float32(f32),
+    /// Pointer, only meaningful in the process that wrote it.
+    Pointer(u32),
+    WString(String),
+    /// Red, green, blue and alpha components.
+    Color([u8; 4]),
+    UInt64(u64),
 }
 
+impl Value {
+    /// Text of a value as it is written in a text file, e.g. `255 0 0 255` for a color.
+    /// `None` for sections.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Value::String(value) | Value::WString(value) => Some(value.clone()),
+            Value::Section(_) => None,
+            Value::Int32(value) => Some(value.to_string()),
+            Value::Float32(value) => Some(value.to_string()),
+            Value::Pointer(value) => Some(value.to_string()),
+            Value::Color([r, g, b, a]) => Some(format!("{r} {g} {b} {a}")),
+            Value::UInt64(value) => Some(value.to_string()),
+        }
+    }
+}
+
+// Floats are compared by their bits so that values round-trip exactly and `Eq` holds.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(a), Value::String(b)) | (Value::WString(a), Value::WString(b)) => a == b,
+            (Value::Section(a), Value::Section(b)) => a == b,
+            (Value::Int32(a), Value::Int32(b)) => a == b,
+            (Value::Float32(a), Value::Float32(b)) => a.to_bits() == b.to_bits(),
+            (Value::Pointer(a), Value::Pointer(b)) => a == b,
+            (Value::Color(a), Value::Color(b)) => a == b,
+            (Value::UInt64(a), Value::UInt64(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+/// A `#base` or `#include` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub kind: DirectiveKind,
+    /// Path of the referenced file, as written in the source.
+    pub path: String,
+    pub path_range: Range,
+    pub path_span: Span,
+    /// Range of the whole directive, from its `#` to the end of its path.
+    pub range: Range,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectiveKind {
+    /// `#base`: keys of the referenced file are merged in, without overriding existing keys.
+    Base,
+    /// `#include`: keys of the referenced file are appended.
+    Include,
+}
+
+/// A keyvalue file, with its directives and all of its root entries.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
-struct Serializer {
-    range_collector: RangeCollector,
-}
-
-pub fn serialize_keyvalue(input: &str) -> Result<KeyValue, Box<pest::error::Error<Rule>>> {
-    let mut serializer = Serializer::default();
-    serializer.range_collector.collect_linebreaks(input);
-
-    let pairs = KeyValueParser::parse(Rule::start, input)?;
-    let mut kv = KeyValue {
-        key: String::new(),
-        value: Value::String(String::new()),
-        key_range: Range::default(),
-    };
-
-    for pair in pairs {
-        if let Rule::start = pair.as_rule() {
-            for pair in pair.into_inner() {
-                if let Rule::keyvalue = pair.as_rule() {
-                    kv = serializer.serialize_kv(pair);
-                }
-            }
+pub struct Document {
+    pub directives: Vec<Directive>,
+    pub roots: Vec<KeyValue>,
+}
+
+/// Configuration options for the parser
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParserConfig {
+    /// Should the escape sequences of quoted strings be decoded.
+    /// When `false`, keys and values hold the raw text between the quotes.
+    pub unescape: bool,
+
+    /// Escape sequences recognized in quoted strings
+    #[wasm_bindgen(js_name = escapeMode)]
+    pub escape_mode: EscapeMode,
+
+    /// Unit of the character offsets of the positions in ranges and errors
+    #[wasm_bindgen(js_name = positionEncoding)]
+    pub position_encoding: PositionEncoding,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            unescape: true,
+            escape_mode: EscapeMode::Json,
+            position_encoding: PositionEncoding::Utf16,
         }
     }
+}
 
-    Ok(kv)
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl ParserConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        unescape: bool,
+        escape_mode: EscapeMode,
+        position_encoding: PositionEncoding,
+    ) -> Self {
+        Self {
+            unescape,
+            escape_mode,
+            position_encoding,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl KeyValue {
+    pub(crate) fn to_js(&self) -> KeyValueJs {
+        let (value, children) = match &self.value {
+            Value::Section(section) => (
+                None,
+                section.iter().map(|kv| JsValue::from(kv.to_js())).collect(),
+            ),
+            value => (value.to_text(), Array::new()),
+        };
+        KeyValueJs {
+            key: self.key.clone(),
+            value,
+            children,
+            key_range: self.key_range,
+            key_span: self.key_span,
+            value_range: self.value_range,
+            value_span: self.value_span,
+            range: self.range,
+            span: self.span,
+            l_brace_range: self.l_brace_range,
+            r_brace_range: self.r_brace_range,
+            key_quoted: self.key_quoted,
+            value_quoted: self.value_quoted,
+            conditional: self.conditional.clone(),
+        }
+    }
+}
+
+/// Representation of a KeyValue entry
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = KeyValue, getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyValueJs {
+    #[wasm_bindgen(readonly)]
+    pub key: String,
+
+    /// Value of the entry, `undefined` for sections
+    #[wasm_bindgen(readonly)]
+    pub value: Option<String>,
+
+    /// Entries of the section, empty for string values
+    #[wasm_bindgen(readonly)]
+    pub children: Array,
+
+    #[wasm_bindgen(js_name = keyRange, readonly)]
+    pub key_range: Range,
+
+    #[wasm_bindgen(js_name = keySpan, readonly)]
+    pub key_span: Span,
+
+    /// Range of the value string, or of the section from its `{` to its `}`
+    #[wasm_bindgen(js_name = valueRange, readonly)]
+    pub value_range: Range,
+
+    #[wasm_bindgen(js_name = valueSpan, readonly)]
+    pub value_span: Span,
+
+    /// Range of the whole entry
+    #[wasm_bindgen(readonly)]
+    pub range: Range,
+
+    #[wasm_bindgen(readonly)]
+    pub span: Span,
+
+    #[wasm_bindgen(js_name = lBraceRange, readonly)]
+    pub l_brace_range: Option<Range>,
+
+    #[wasm_bindgen(js_name = rBraceRange, readonly)]
+    pub r_brace_range: Option<Range>,
+
+    #[wasm_bindgen(js_name = keyQuoted, readonly)]
+    pub key_quoted: bool,
+
+    #[wasm_bindgen(js_name = valueQuoted, readonly)]
+    pub value_quoted: bool,
+
+    #[wasm_bindgen(readonly)]
+    pub conditional: Option<String>,
 }
 
-impl Serializer {
-    fn serialize_kv(&mut self, pair: Pair<Rule>) -> KeyValue {
-        let mut kv = KeyValue {
-            key: String::new(),
-            value: Value::String(String::new()),
+/// Serialize a string containing exactly one root keyvalue.
+pub fn serialize_keyvalue(input: &str) -> Result<KeyValue, KvError> {
+    serialize_keyvalue_with_config(input, ParserConfig::default())
+}
+
+pub fn serialize_keyvalue_with_config(
+    input: &str,
+    config: ParserConfig,
+) -> Result<KeyValue, KvError> {
+    let document = serialize_document_with_config(input, config)?;
+    let mut roots = document.roots.into_iter();
+    match (roots.next(), roots.next()) {
+        (Some(kv), None) => Ok(kv),
+        (None, _) => {
+            let end = LineIndex::new(input, config.position_encoding).position(input.len());
+            Err(syntax_error(Range::new(end, end), "expected a keyvalue"))
+        }
+        (Some(_), Some(extra)) => Err(syntax_error(
+            extra.key_range,
+            "expected a single root keyvalue",
+        )),
+    }
+}
+
+/// Serialize a string containing any number of root keyvalues, such as a VMF file.
+///
+/// Fails with the first syntax error of the input. Use [`parse_document`](crate::parse_document)
+/// to get every syntax error along with a partial document.
+pub fn serialize_document(input: &str) -> Result<Document, KvError> {
+    serialize_document_with_config(input, ParserConfig::default())
+}
+
+pub fn serialize_document_with_config(
+    input: &str,
+    config: ParserConfig,
+) -> Result<Document, KvError> {
+    let Parse {
+        tree,
+        errors,
+        line_index,
+    } = parse_tree(input, config);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(serialize_tree(&tree, line_index, config)),
+    }
+}
+
+fn syntax_error(range: Range, message: &str) -> KvError {
+    KvError {
+        range,
+        additional_ranges: vec![],
+        message: message.to_string(),
+        kind: KvErrorKind::SyntaxError,
+    }
+}
+
+/// Build the document of a syntax tree, skipping its error nodes.
+pub(crate) fn serialize_tree(
+    tree: &SyntaxTree,
+    line_index: LineIndex,
+    config: ParserConfig,
+) -> Document {
+    let serializer = Serializer::new(&tree.source, line_index, config);
+    let roots = serializer
+        .serialize_roots(&tree.root)
+        .into_iter()
+        .map(BorrowedKeyValue::into_owned)
+        .collect();
+
+    Document {
+        directives: serializer.serialize_directives(&tree.root),
+        roots,
+    }
+}
+
+/// Builds [`BorrowedKeyValue`]s from the nodes of a syntax tree of `source`.
+pub(crate) struct Serializer<'a> {
+    source: &'a str,
+    line_index: LineIndex,
+    config: ParserConfig,
+}
+
+impl<'a> Serializer<'a> {
+    /// `line_index` is the index of `source`, built by the lexer.
+    pub(crate) fn new(source: &'a str, line_index: LineIndex, config: ParserConfig) -> Self {
+        Self {
+            source,
+            line_index,
+            config,
+        }
+    }
+
+    /// Directives of a [`SyntaxKind::Document`] node.
+    pub(crate) fn serialize_directives(&self, root: &SyntaxNode) -> Vec<Directive> {
+        root.child_nodes()
+            .filter(|node| node.kind == SyntaxKind::Directive)
+            .map(|node| self.serialize_directive(node))
+            .collect()
+    }
+
+    /// Entries of a [`SyntaxKind::Document`] or [`SyntaxKind::Section`] node.
+    pub(crate) fn serialize_roots(&self, root: &SyntaxNode) -> Vec<BorrowedKeyValue<'a>> {
+        let (roots, _) = self.serialize_entries(root);
+        roots
+    }
+
+    fn serialize_directive(&self, node: &SyntaxNode) -> Directive {
+        let mut tokens = node.child_tokens().filter(|token| !token.kind.is_trivia());
+        let kind = match tokens
+            .next()
+            .map(|token| self.text(token).to_lowercase())
+            .as_deref()
+        {
+            Some("#base") => DirectiveKind::Base,
+            _ => DirectiveKind::Include,
+        };
+        let path = tokens.next().expect("directives always have a path");
+        // Paths are never escaped, backslashes are directory separators.
+        let (text, _) = self.serialize_string(path, false);
+
+        Directive {
+            kind,
+            path: text.into_owned(),
+            path_range: self.range(path),
+            path_span: span(path),
+            range: self.line_index.range(node.start, node.end),
+            span: Span::new(node.start, node.end),
+        }
+    }
+
+    fn serialize_kv(&self, node: &SyntaxNode) -> BorrowedKeyValue<'a> {
+        let mut kv = BorrowedKeyValue {
+            key: Cow::Borrowed(""),
+            value: BorrowedValue::String(Cow::Borrowed("")),
             key_range: Range::default(),
+            key_span: Span::default(),
+            value_range: Range::default(),
+            value_span: Span::default(),
+            range: self.line_index.range(node.start, node.end),
+            span: Span::new(node.start, node.end),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: false,
+            value_quoted: false,
+            conditional: None,
+            comments: vec![],
         };
-        for pair in pair.into_inner() {
-            match pair.as_rule() {
-                Rule::key => {
-                    kv.key = pair.as_str().to_string().replace('"', "");
-                    kv.key_range = self.range_collector.span_to_range(pair.as_span());
+        let mut has_key = false;
+        for child in node.children.iter() {
+            match child {
+                SyntaxElement::Token(token) if token.kind.is_string() => {
+                    let (text, quoted) = self.serialize_string(token, self.config.unescape);
+                    if !has_key {
+                        kv.key = text;
+                        kv.key_quoted = quoted;
+                        kv.key_range = self.range(token);
+                        kv.key_span = span(token);
+                        // Empty value at the end of the key, until a value is found.
+                        kv.value_range = Range::new(kv.key_range.end, kv.key_range.end);
+                        kv.value_span = Span::new(token.end, token.end);
+                        has_key = true;
+                    } else {
+                        kv.value = BorrowedValue::String(text);
+                        kv.value_quoted = quoted;
+                        kv.value_range = self.range(token);
+                        kv.value_span = span(token);
+                    }
+                }
+                SyntaxElement::Token(token) if token.kind == SyntaxKind::Conditional => {
+                    let text = self.text(token);
+                    let text = text.strip_prefix('[').unwrap_or(text);
+                    let text = text.strip_suffix(']').unwrap_or(text);
+                    kv.conditional = Some(text);
                 }
-                Rule::value => {
-                    kv.value = Value::String(pair.as_str().to_string().replace('"', ""));
+                SyntaxElement::Token(token) if token.kind.is_trivia() => {
+                    if let Some(comment) = self.comment(token) {
+                        kv.comments.push(comment);
+                    }
                 }
-                Rule::section => {
-                    kv.value = Value::Section(self.serialize_section(pair));
+                SyntaxElement::Node(node) if node.kind == SyntaxKind::Section => {
+                    let (section, comments) = self.serialize_entries(node);
+                    kv.value = BorrowedValue::Section(section);
+                    kv.comments.extend(comments);
+                    kv.value_range = self.line_index.range(node.start, node.end);
+                    kv.value_span = Span::new(node.start, node.end);
+                    for token in node.child_tokens() {
+                        match token.kind {
+                            SyntaxKind::LBrace => kv.l_brace_range = Some(self.range(token)),
+                            SyntaxKind::RBrace => kv.r_brace_range = Some(self.range(token)),
+                            _ => (),
+                        }
+                    }
                 }
                 _ => (),
             }
@@ -75,14 +473,119 @@ impl Serializer {
         kv
     }
 
-    fn serialize_section(&mut self, pair: Pair<Rule>) -> Vec<KeyValue> {
-        let mut kvs = Vec::new();
-        for pair in pair.into_inner() {
-            if let Rule::keyvalue = pair.as_rule() {
-                kvs.push(self.serialize_kv(pair));
+    /// Serialize the entry at `index` in the children of a section or of the document, with
+    /// the comments [`Serializer::serialize_entries`] attaches to it.
+    pub(crate) fn serialize_entry(&self, node: &SyntaxNode, index: usize) -> BorrowedKeyValue<'a> {
+        let is_entry = |child: &SyntaxElement| matches!(child, SyntaxElement::Node(node) if node.kind == SyntaxKind::KeyValue);
+        let children = &node.children;
+        // Comments are attached to the entries around them.
+        let start = children[..index]
+            .iter()
+            .rposition(is_entry)
+            .map_or(0, |i| i + 1);
+        let end = children[index + 1..]
+            .iter()
+            .position(is_entry)
+            .map_or(children.len(), |i| index + 1 + i);
+        let (mut kvs, mut comments) = self.attach_comments(&children[start..end], start == 0);
+        let mut kv = kvs.pop().expect("the entry is in the children");
+        // The comments after the last entry are attached to it.
+        if end == children.len() {
+            kv.comments.append(&mut comments);
+        }
+
+        kv
+    }
+
+    /// Serialize the entries of a section or of the document, and attach the comments
+    /// between them. Returns the comments of a node without entries separately.
+    fn serialize_entries(&self, node: &SyntaxNode) -> (Vec<BorrowedKeyValue<'a>>, Vec<&'a str>) {
+        let (mut kvs, mut comments) = self.attach_comments(&node.children, true);
+        match kvs.last_mut() {
+            Some(kv) => {
+                kv.comments.append(&mut comments);
+                (kvs, comments)
             }
+            None => (kvs, comments),
+        }
+    }
+
+    /// Serialize the entries of `children`, and attach the comments before each entry and
+    /// after it on its line. Returns the comments after the last line break separately.
+    /// `new_line` is false when `children` follows an entry, whose comments are skipped.
+    fn attach_comments(
+        &self,
+        children: &[SyntaxElement],
+        mut new_line: bool,
+    ) -> (Vec<BorrowedKeyValue<'a>>, Vec<&'a str>) {
+        let mut kvs: Vec<BorrowedKeyValue> = vec![];
+        let mut comments = vec![];
+        for child in children.iter() {
+            match child {
+                SyntaxElement::Token(token) => match self.comment(token) {
+                    Some(comment) if !new_line => {
+                        if let Some(kv) = kvs.last_mut() {
+                            kv.comments.push(comment);
+                        }
+                    }
+                    Some(comment) => comments.push(comment),
+                    None => new_line |= self.text(token).contains('\n'),
+                },
+                SyntaxElement::Node(node) if node.kind == SyntaxKind::KeyValue => {
+                    let mut kv = self.serialize_kv(node);
+                    kv.comments.splice(0..0, comments.drain(..));
+                    kvs.push(kv);
+                    new_line = false;
+                }
+                SyntaxElement::Node(_) => new_line = true,
+            }
+        }
+
+        (kvs, comments)
+    }
+
+    /// Text of a comment token, without the line break of a line comment.
+    fn comment(&self, token: &SyntaxToken) -> Option<&'a str> {
+        match token.kind {
+            SyntaxKind::LineComment => Some(self.text(token).trim_end_matches('\r')),
+            SyntaxKind::BlockComment => Some(self.text(token)),
+            _ => None,
         }
+    }
+
+    /// Returns the text of a string token and whether it was quoted. The text is only
+    /// copied if it has escape sequences to decode.
+    fn serialize_string(&self, token: &SyntaxToken, unescape: bool) -> (Cow<'a, str>, bool) {
+        let text = self.text(token);
+        let Some(inner) = unquote(text, token.kind) else {
+            return (Cow::Borrowed(text), false);
+        };
+        let escaped = unescape && self.config.escape_mode != EscapeMode::Disabled;
+        if escaped && inner.contains('\\') {
+            (Cow::Owned(self.config.escape_mode.unescape(inner)), true)
+        } else {
+            (Cow::Borrowed(inner), true)
+        }
+    }
 
-        kvs
+    fn text(&self, token: &SyntaxToken) -> &'a str {
+        &self.source[token.start..token.end]
     }
+
+    fn range(&self, token: &SyntaxToken) -> Range {
+        self.line_index.range(token.start, token.end)
+    }
+}
+
+/// Text between the quotes of a quoted string token. `None` for unquoted strings.
+pub(crate) fn unquote(text: &str, kind: SyntaxKind) -> Option<&str> {
+    match kind {
+        SyntaxKind::QuotedString => Some(&text[1..text.len() - 1]),
+        SyntaxKind::UnterminatedString => Some(&text[1..]),
+        _ => None,
+    }
+}
+
+fn span(token: &SyntaxToken) -> Span {
+    Span::new(token.start, token.end)
 }
@@ -0,0 +1,567 @@
+use std::{collections::VecDeque, fmt, io::Read};
+
+use crate::{
+    parser::lex_token, serializer::unquote, DirectiveKind, KeyValue, KvError, KvErrorKind,
+    ParserConfig, Position, Range, SyntaxKind, Value,
+};
+
+/// Number of bytes read from the input at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Event of an [`EventReader`], in the order of the input.
+///
+/// Comments found inside of an entry, e.g. between a key and its value, come before the
+/// event of the entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A `#base` or `#include` directive at the root of the input.
+    Directive {
+        kind: DirectiveKind,
+        path: String,
+        path_range: Range,
+    },
+
+    /// The key of a section and its opening brace. Followed by the events of its entries
+    /// and by an [`Event::EndSection`].
+    StartSection {
+        key: String,
+        key_range: Range,
+        /// Platform condition preceding the section, without its brackets.
+        conditional: Option<String>,
+        l_brace_range: Range,
+    },
+
+    /// A key and its string value.
+    KeyValue {
+        key: String,
+        value: String,
+        key_range: Range,
+        value_range: Range,
+        /// Platform condition following the value, without its brackets.
+        conditional: Option<String>,
+    },
+
+    /// The closing brace of the innermost open section.
+    EndSection { r_brace_range: Range },
+
+    /// A comment, with its delimiters.
+    Comment { text: String, range: Range },
+}
+
+/// Error raised by an [`EventReader`].
+#[derive(Debug)]
+pub enum StreamError {
+    /// The input could not be read.
+    Io(std::io::Error),
+
+    /// The input is not valid keyvalue, or not valid UTF-8.
+    Syntax(KvError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(err) => write!(f, "{err}"),
+            StreamError::Syntax(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Io(err) => Some(err),
+            StreamError::Syntax(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for StreamError {
+    fn from(err: std::io::Error) -> Self {
+        StreamError::Io(err)
+    }
+}
+
+impl From<KvError> for StreamError {
+    fn from(err: KvError) -> Self {
+        StreamError::Syntax(err)
+    }
+}
+
+/// Pull parser reading the [`Event`]s of a keyvalue input one at a time.
+///
+/// Only the token being read is kept in memory, so that huge files such as `items_game.txt`
+/// can be scanned, and the sections of interest extracted with [`EventReader::read_section`].
+///
+/// Reading stops at the first syntax error. Unlike [`parse_document`](crate::parse_document),
+/// a key without a value is not detected from the layout of the following lines: the next
+/// string is taken as its value.
+pub struct EventReader<'a> {
+    reader: Box<dyn Read + 'a>,
+    config: ParserConfig,
+    /// Text read from the input and not consumed yet, from `pos`.
+    buffer: String,
+    pos: usize,
+    /// Bytes of an incomplete UTF-8 character at the end of the text read.
+    pending: Vec<u8>,
+    /// Whether the input was read to its end.
+    eof: bool,
+    /// Position of `pos` in the input.
+    position: Position,
+    /// Tokens lexed in advance.
+    lookahead: VecDeque<Token>,
+    events: VecDeque<Event>,
+    /// Ranges of the opening braces of the open sections.
+    sections: Vec<Range>,
+    done: bool,
+}
+
+/// Read the events of a string of keyvalue.
+pub fn read_events(input: &str) -> EventReader<'_> {
+    read_events_with_config(input, ParserConfig::default())
+}
+
+pub fn read_events_with_config(input: &str, config: ParserConfig) -> EventReader<'_> {
+    EventReader::new(input.as_bytes(), config)
+}
+
+/// Entries of a section, its comments if it has no entries, and its closing brace.
+type Entries = (Vec<KeyValue>, Vec<String>, Option<Range>);
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: SyntaxKind,
+    text: String,
+    range: Range,
+}
+
+impl<'a> EventReader<'a> {
+    /// Read the events of a UTF-8 encoded input, such as a file. A leading byte order mark
+    /// is skipped.
+    pub fn new(reader: impl Read + 'a, config: ParserConfig) -> Self {
+        Self {
+            reader: Box::new(reader),
+            config,
+            buffer: String::new(),
+            pos: 0,
+            pending: vec![],
+            eof: false,
+            position: Position::default(),
+            lookahead: VecDeque::new(),
+            events: VecDeque::new(),
+            sections: vec![],
+            done: false,
+        }
+    }
+
+    /// Read the entries of the section opened by the last [`Event::StartSection`], up to
+    /// its closing brace, or the remaining root entries outside of a section. Comments are
+    /// attached to the entries as in [`KeyValue::comments`].
+    ///
+    /// The entries have ranges but no spans, and the range of an entry does not include the
+    /// conditional of its value.
+    pub fn read_section(&mut self) -> Result<Vec<KeyValue>, StreamError> {
+        let (kvs, _, _) = self.read_entries()?;
+
+        Ok(kvs)
+    }
+
+    /// Skip the entries of the section opened by the last [`Event::StartSection`], up to
+    /// its closing brace.
+    pub fn skip_section(&mut self) -> Result<(), StreamError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next_event()? {
+                Some(Event::StartSection { .. }) => depth += 1,
+                Some(Event::EndSection { .. }) => depth -= 1,
+                Some(_) => (),
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the entries up to the end of the current section, the comments of the
+    /// section if it has no entries, and the range of its closing brace.
+    fn read_entries(&mut self) -> Result<Entries, StreamError> {
+        let mut kvs: Vec<KeyValue> = vec![];
+        let mut comments = vec![];
+        // Line of the end of the previous entry.
+        let mut prev_line = None;
+        while let Some(event) = self.next_event()? {
+            match event {
+                Event::Comment { text, range } => match kvs.last_mut() {
+                    Some(kv) if prev_line == Some(range.start.line) => kv.comments.push(text),
+                    _ => comments.push(text),
+                },
+                Event::KeyValue {
+                    key,
+                    value,
+                    key_range,
+                    value_range,
+                    conditional,
+                } => {
+                    let mut kv = KeyValue::new(key, Value::String(value));
+                    kv.key_range = key_range;
+                    kv.value_range = value_range;
+                    kv.range = Range::new(key_range.start, value_range.end);
+                    kv.conditional = conditional;
+                    kv.comments = std::mem::take(&mut comments);
+                    prev_line = Some(value_range.end.line);
+                    kvs.push(kv);
+                }
+                Event::StartSection {
+                    key,
+                    key_range,
+                    conditional,
+                    l_brace_range,
+                } => {
+                    let (section, section_comments, r_brace_range) = self.read_entries()?;
+                    let end = r_brace_range.unwrap_or(l_brace_range).end;
+                    let mut kv = KeyValue::new(key, Value::Section(section));
+                    kv.key_range = key_range;
+                    kv.value_range = Range::new(l_brace_range.start, end);
+                    kv.range = Range::new(key_range.start, end);
+                    kv.l_brace_range = Some(l_brace_range);
+                    kv.r_brace_range = r_brace_range;
+                    kv.conditional = conditional;
+                    kv.comments = std::mem::take(&mut comments);
+                    kv.comments.extend(section_comments);
+                    prev_line = Some(end.line);
+                    kvs.push(kv);
+                }
+                Event::EndSection { r_brace_range } => {
+                    return Ok(attach_comments(kvs, comments, Some(r_brace_range)));
+                }
+                Event::Directive { .. } => (),
+            }
+        }
+
+        Ok(attach_comments(kvs, comments, None))
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, StreamError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            if let Err(err) = self.parse_item() {
+                self.done = true;
+                self.events.clear();
+                return Err(err);
+            }
+        }
+    }
+
+    /// Parse the next entry, directive or closing brace, and queue its events.
+    fn parse_item(&mut self) -> Result<(), StreamError> {
+        let Some(token) = self.next_significant()? else {
+            if let Some(l_brace_range) = self.sections.last() {
+                return Err(syntax_error(
+                    *l_brace_range,
+                    "expected '}' to close the section",
+                ));
+            }
+            self.done = true;
+            return Ok(());
+        };
+        match token.kind {
+            SyntaxKind::RBrace => match self.sections.pop() {
+                Some(_) => self.events.push_back(Event::EndSection {
+                    r_brace_range: token.range,
+                }),
+                None => return Err(syntax_error(token.range, "unexpected '}'")),
+            },
+            SyntaxKind::UnquotedString if self.at_directive(&token)? => {
+                let path = self.next_significant()?.expect("the path was peeked");
+                let kind = match token.text.to_lowercase().as_str() {
+                    "#base" => DirectiveKind::Base,
+                    _ => DirectiveKind::Include,
+                };
+                // Paths are never escaped, backslashes are directory separators.
+                self.events.push_back(Event::Directive {
+                    kind,
+                    path: self.string_text(&path, false),
+                    path_range: path.range,
+                });
+            }
+            kind if kind.is_string() => self.parse_keyvalue(token)?,
+            _ => return Err(syntax_error(token.range, "expected a key")),
+        }
+
+        Ok(())
+    }
+
+    fn parse_keyvalue(&mut self, key: Token) -> Result<(), StreamError> {
+        let key_text = self.string_text(&key, self.config.unescape);
+        let next = self.next_significant()?;
+        match next.as_ref().map(|token| token.kind) {
+            Some(kind) if kind.is_string() => {
+                let value = next.expect("the value was matched");
+                let conditional = match self.peek_significant()? {
+                    Some(SyntaxKind::Conditional) => self.next_significant()?.map(conditional_text),
+                    _ => None,
+                };
+                self.events.push_back(Event::KeyValue {
+                    key: key_text,
+                    value: self.string_text(&value, self.config.unescape),
+                    key_range: key.range,
+                    value_range: value.range,
+                    conditional,
+                });
+            }
+            Some(SyntaxKind::Conditional) => {
+                let conditional = next.map(conditional_text);
+                match self.next_significant()? {
+                    Some(l_brace) if l_brace.kind == SyntaxKind::LBrace => {
+                        self.start_section(key_text, key.range, conditional, l_brace.range)
+                    }
+                    _ => {
+                        return Err(syntax_error(
+                            key.range,
+                            "expected '{' after the conditional",
+                        ))
+                    }
+                }
+            }
+            Some(SyntaxKind::LBrace) => {
+                let l_brace = next.expect("the brace was matched");
+                self.start_section(key_text, key.range, None, l_brace.range);
+            }
+            _ => return Err(syntax_error(key.range, "expected a value or a section")),
+        }
+
+        Ok(())
+    }
+
+    fn start_section(
+        &mut self,
+        key: String,
+        key_range: Range,
+        conditional: Option<String>,
+        l_brace_range: Range,
+    ) {
+        self.sections.push(l_brace_range);
+        self.events.push_back(Event::StartSection {
+            key,
+            key_range,
+            conditional,
+            l_brace_range,
+        });
+    }
+
+    /// Whether `token` is a `#base` or `#include` at the root, followed by a path on the
+    /// same line.
+    fn at_directive(&mut self, token: &Token) -> Result<bool, StreamError> {
+        let text = token.text.to_lowercase();
+        if !self.sections.is_empty() || (text != "#base" && text != "#include") {
+            return Ok(false);
+        }
+        let separated = self.peek_raw(0)?.is_some_and(|separator| {
+            separator.kind == SyntaxKind::Whitespace
+                && separator.text.bytes().all(|b| b == b' ' || b == b'\t')
+        });
+
+        Ok(separated && self.peek_raw(1)?.is_some_and(|path| path.kind.is_string()))
+    }
+
+    /// Returns the next significant token, and queues the comments before it.
+    fn next_significant(&mut self) -> Result<Option<Token>, StreamError> {
+        while let Some(token) = self.next_raw()? {
+            match token.kind {
+                SyntaxKind::Whitespace => (),
+                SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+                    self.events.push_back(Event::Comment {
+                        text: token.text.trim_end_matches('\r').to_string(),
+                        range: token.range,
+                    });
+                }
+                _ => return Ok(Some(token)),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the kind of the next significant token, without consuming it.
+    fn peek_significant(&mut self) -> Result<Option<SyntaxKind>, StreamError> {
+        let mut index = 0;
+        while let Some(token) = self.peek_raw(index)? {
+            if !token.kind.is_trivia() {
+                return Ok(Some(token.kind));
+            }
+            index += 1;
+        }
+
+        Ok(None)
+    }
+
+    fn next_raw(&mut self) -> Result<Option<Token>, StreamError> {
+        match self.lookahead.pop_front() {
+            Some(token) => Ok(Some(token)),
+            None => self.lex(),
+        }
+    }
+
+    fn peek_raw(&mut self, index: usize) -> Result<Option<&Token>, StreamError> {
+        while self.lookahead.len() <= index {
+            match self.lex()? {
+                Some(token) => self.lookahead.push_back(token),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(self.lookahead.get(index))
+    }
+
+    /// Lex the next token of the input, reading more of it until the token is complete.
+    fn lex(&mut self) -> Result<Option<Token>, StreamError> {
+        loop {
+            if self.pos < self.buffer.len() {
+                let lexed = lex_token(&self.buffer, self.pos, self.config.escape_mode)
+                    .expect("the buffer is not empty");
+                if !lexed.reached_end || self.eof {
+                    let (start, end) = (lexed.token.start, lexed.token.end);
+                    if let Some(error) = lexed.errors.first() {
+                        let error_start =
+                            advance(self.position, &self.buffer[start..error.start], self.config);
+                        let error_end = advance(
+                            error_start,
+                            &self.buffer[error.start..error.end],
+                            self.config,
+                        );
+                        return Err(syntax_error(
+                            Range::new(error_start, error_end),
+                            &error.message,
+                        ));
+                    }
+                    let text = self.buffer[start..end].to_string();
+                    let end_position = advance(self.position, &text, self.config);
+                    let token = Token {
+                        kind: lexed.token.kind,
+                        text,
+                        range: Range::new(self.position, end_position),
+                    };
+                    self.pos = end;
+                    self.position = end_position;
+                    return Ok(Some(token));
+                }
+            } else if self.eof {
+                return Ok(None);
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Drop the consumed text and read more of the input. The amount read grows with the
+    /// text left, so that long tokens are lexed in linear time.
+    fn fill(&mut self) -> Result<(), StreamError> {
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        let mut bytes = std::mem::take(&mut self.pending);
+        let start = bytes.len();
+        bytes.resize(start + CHUNK_SIZE.max(self.buffer.len()), 0);
+        let read = loop {
+            match self.reader.read(&mut bytes[start..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err.into()),
+            }
+        };
+        bytes.truncate(start + read);
+        self.eof = read == 0;
+        let at_start = self.position == Position::default() && self.buffer.is_empty();
+        if at_start && bytes.starts_with(b"\xEF\xBB\xBF") {
+            bytes.drain(..3);
+        }
+        match std::str::from_utf8(&bytes) {
+            Ok(text) => self.buffer.push_str(text),
+            Err(err) if err.error_len().is_none() && !self.eof => {
+                let valid = err.valid_up_to();
+                let text = std::str::from_utf8(&bytes[..valid]).expect("the prefix is valid");
+                self.buffer.push_str(text);
+                self.pending = bytes[valid..].to_vec();
+            }
+            Err(err) => {
+                let text =
+                    std::str::from_utf8(&bytes[..err.valid_up_to()]).expect("the prefix is valid");
+                let position = advance(
+                    advance(self.position, &self.buffer, self.config),
+                    text,
+                    self.config,
+                );
+                return Err(KvError {
+                    range: Range::new(position, position),
+                    additional_ranges: vec![],
+                    message: "invalid UTF-8".to_string(),
+                    kind: KvErrorKind::EncodingError,
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn string_text(&self, token: &Token, unescape: bool) -> String {
+        match unquote(&token.text, token.kind) {
+            Some(inner) if unescape => self.config.escape_mode.unescape(inner),
+            Some(inner) => inner.to_string(),
+            None => token.text.clone(),
+        }
+    }
+}
+
+impl Iterator for EventReader<'_> {
+    type Item = Result<Event, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+/// Attach the comments at the end of a section to its last entry.
+fn attach_comments(
+    mut kvs: Vec<KeyValue>,
+    mut comments: Vec<String>,
+    r_brace_range: Option<Range>,
+) -> Entries {
+    if let Some(kv) = kvs.last_mut() {
+        kv.comments.append(&mut comments);
+    }
+
+    (kvs, comments, r_brace_range)
+}
+
+fn conditional_text(token: Token) -> String {
+    let text = token.text.strip_prefix('[').unwrap_or(&token.text);
+    let text = text.strip_suffix(']').unwrap_or(text);
+    text.to_string()
+}
+
+/// Position after `text`, starting at `position`.
+fn advance(mut position: Position, text: &str, config: ParserConfig) -> Position {
+    for c in text.chars() {
+        if c == '\n' {
+            position.line += 1;
+            position.character = 0;
+        } else {
+            position.character += config.position_encoding.len(c) as u32;
+        }
+    }
+
+    position
+}
+
+fn syntax_error(range: Range, message: &str) -> StreamError {
+    StreamError::Syntax(KvError {
+        range,
+        additional_ranges: vec![],
+        message: message.to_string(),
+        kind: KvErrorKind::SyntaxError,
+    })
+}
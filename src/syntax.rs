@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// Kind of a token or a node of the [`SyntaxTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// Run of spaces, tabs and line breaks.
+    Whitespace,
+    LineComment,
+    BlockComment,
+    QuotedString,
+    /// Quoted string missing its closing quote, cut at the end of its first line.
+    UnterminatedString,
+    UnquotedString,
+    /// Platform condition, including its brackets, e.g. `[$WIN32]`.
+    Conditional,
+    LBrace,
+    RBrace,
+
+    /// Root node, containing every directive and root entry.
+    Document,
+    /// `#base` or `#include` followed by a path.
+    Directive,
+    /// Key followed by a value or a section, and an optional conditional.
+    KeyValue,
+    /// Braces and the entries between them.
+    Section,
+    /// Tokens that could not be parsed, e.g. a stray `}`.
+    Error,
+}
+
+impl SyntaxKind {
+    /// Whether tokens of this kind are whitespace or comments.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::LineComment | SyntaxKind::BlockComment
+        )
+    }
+
+    /// Whether tokens of this kind are keys, values or paths.
+    pub fn is_string(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::QuotedString | SyntaxKind::UnterminatedString | SyntaxKind::UnquotedString
+        )
+    }
+}
+
+/// Leaf of the [`SyntaxTree`], with its byte offsets in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub kind: SyntaxKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Inner node of the [`SyntaxTree`]. Its byte offsets span all of its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub start: usize,
+    pub end: usize,
+    pub children: Vec<SyntaxElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    /// Direct child nodes of this node.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Direct child tokens of this node.
+    pub fn child_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    /// Every token of this node and its descendants, in source order.
+    pub fn descendant_tokens(&self) -> DescendantTokens<'_> {
+        DescendantTokens {
+            stack: vec![self.children.iter()],
+        }
+    }
+}
+
+/// Iterator over the tokens of a node, see [`SyntaxNode::descendant_tokens`].
+pub struct DescendantTokens<'a> {
+    stack: Vec<std::slice::Iter<'a, SyntaxElement>>,
+}
+
+impl<'a> Iterator for DescendantTokens<'a> {
+    type Item = &'a SyntaxToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(SyntaxElement::Token(token)) => return Some(token),
+                Some(SyntaxElement::Node(node)) => self.stack.push(node.children.iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Lossless concrete syntax tree of a keyvalue file.
+///
+/// Every byte of the source belongs to exactly one token, including whitespace, comments
+/// and invalid input, so the tree can be written back byte for byte with `to_string`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree {
+    /// Text the tree was parsed from.
+    pub source: String,
+
+    /// [`SyntaxKind::Document`] node.
+    pub root: SyntaxNode,
+}
+
+impl SyntaxTree {
+    /// Returns the source text of a token.
+    pub fn text(&self, token: &SyntaxToken) -> &str {
+        &self.source[token.start..token.end]
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.root.descendant_tokens() {
+            f.write_str(self.text(token))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+
+use crate::{KeyValue, Range, Value};
+
+/// Error raised when a value cannot be read as a number, a vector or a color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueError {
+    /// Range of the value. Empty for errors of a [`Value`] read on its own, which does
+    /// not know where it comes from.
+    pub range: Range,
+
+    /// Error message of the error.
+    pub message: String,
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.range.start.line + 1,
+            self.range.start.character + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+/// Typed reads of values, following the rules of the engine's `KeyValues` getters.
+///
+/// Like `atoi` and `atof`, numbers are read from the start of the text, after any
+/// whitespace, and the text following them is ignored: `"1.5"` reads as the integer `1`.
+/// Where the engine would silently fall back to zero because the text does not start with
+/// a number, an error is returned instead.
+impl Value {
+    /// Read an integer, as `KeyValues::GetInt`. Floats are truncated.
+    pub fn as_i64(&self) -> Result<i64, ValueError> {
+        match self {
+            Value::String(text) | Value::WString(text) => read_i64(text),
+            Value::Int32(value) => Ok(i64::from(*value)),
+            Value::Float32(value) => Ok(*value as i64),
+            Value::Pointer(value) => Ok(i64::from(*value)),
+            Value::UInt64(value) => i64::try_from(*value)
+                .map_err(|_| value_error(format!("{value} does not fit in an integer"))),
+            Value::Section(_) | Value::Color(_) => Err(unexpected(self, "an integer")),
+        }
+    }
+
+    /// Read a float, as `KeyValues::GetFloat`.
+    pub fn as_f32(&self) -> Result<f32, ValueError> {
+        match self {
+            Value::String(text) | Value::WString(text) => {
+                let (value, _) = read_f32(text).ok_or_else(|| expected_number(text))?;
+                Ok(value)
+            }
+            Value::Int32(value) => Ok(*value as f32),
+            Value::Float32(value) => Ok(*value),
+            Value::Pointer(value) => Ok(*value as f32),
+            Value::UInt64(value) => Ok(*value as f32),
+            Value::Section(_) | Value::Color(_) => Err(unexpected(self, "a float")),
+        }
+    }
+
+    /// Read a boolean, as `KeyValues::GetBool`: any integer other than `0` is `true`.
+    ///
+    /// `true` and `false` are not booleans to the engine, which reads both as `0`, so they
+    /// are rejected.
+    pub fn as_bool(&self) -> Result<bool, ValueError> {
+        self.as_i64().map(|value| value != 0)
+    }
+
+    /// Read a vector of 3 floats separated by whitespace, e.g. `0 1 0`, optionally
+    /// surrounded by brackets or braces, e.g. `[0 1 0]`.
+    pub fn as_vector3(&self) -> Result<[f32; 3], ValueError> {
+        self.read_triple("a vector")
+    }
+
+    /// Read a color, as `KeyValues::GetColor`: 3 or 4 components from `0` to `255`
+    /// separated by whitespace, e.g. `255 128 0 255`, optionally surrounded by brackets or
+    /// braces, e.g. `{255 0 0}`. The alpha defaults to `255`. Fractional components are
+    /// truncated.
+    pub fn as_color(&self) -> Result<[u8; 4], ValueError> {
+        let text = match self {
+            Value::Color(color) => return Ok(*color),
+            Value::String(text) | Value::WString(text) => text,
+            _ => return Err(unexpected(self, "a color")),
+        };
+        let components = read_components(text, "a color")?;
+        if !matches!(components.len(), 3 | 4) {
+            return Err(value_error(format!(
+                "expected 3 or 4 components for a color, found {}",
+                components.len()
+            )));
+        }
+        let mut color = [255; 4];
+        for (channel, component) in color.iter_mut().zip(components) {
+            if !(0.0..256.0).contains(&component) {
+                return Err(value_error(format!(
+                    "color component {component} is not between 0 and 255"
+                )));
+            }
+            *channel = component as u8;
+        }
+
+        Ok(color)
+    }
+
+    /// Read an angle as its pitch, yaw and roll in degrees, written like a vector.
+    pub fn as_qangle(&self) -> Result<[f32; 3], ValueError> {
+        self.read_triple("an angle")
+    }
+
+    fn read_triple(&self, expected: &str) -> Result<[f32; 3], ValueError> {
+        let (Value::String(text) | Value::WString(text)) = self else {
+            return Err(unexpected(self, expected));
+        };
+        let components = read_components(text, expected)?;
+        match components[..] {
+            [x, y, z] => Ok([x, y, z]),
+            _ => Err(value_error(format!(
+                "expected 3 components for {expected}, found {}",
+                components.len()
+            ))),
+        }
+    }
+}
+
+/// Typed reads of the value of an entry, see [`Value::as_i64`]. Errors carry the range of
+/// the value.
+impl KeyValue {
+    pub fn as_i64(&self) -> Result<i64, ValueError> {
+        self.value.as_i64().map_err(|error| self.locate(error))
+    }
+
+    pub fn as_f32(&self) -> Result<f32, ValueError> {
+        self.value.as_f32().map_err(|error| self.locate(error))
+    }
+
+    pub fn as_bool(&self) -> Result<bool, ValueError> {
+        self.value.as_bool().map_err(|error| self.locate(error))
+    }
+
+    pub fn as_vector3(&self) -> Result<[f32; 3], ValueError> {
+        self.value.as_vector3().map_err(|error| self.locate(error))
+    }
+
+    pub fn as_color(&self) -> Result<[u8; 4], ValueError> {
+        self.value.as_color().map_err(|error| self.locate(error))
+    }
+
+    pub fn as_qangle(&self) -> Result<[f32; 3], ValueError> {
+        self.value.as_qangle().map_err(|error| self.locate(error))
+    }
+
+    fn locate(&self, error: ValueError) -> ValueError {
+        ValueError {
+            range: self.value_range,
+            ..error
+        }
+    }
+}
+
+fn value_error(message: String) -> ValueError {
+    ValueError {
+        range: Range::default(),
+        message,
+    }
+}
+
+fn unexpected(value: &Value, expected: &str) -> ValueError {
+    let found = match value {
+        Value::String(_) | Value::WString(_) => "a string",
+        Value::Section(_) => "a section",
+        Value::Int32(_) | Value::UInt64(_) => "an integer",
+        Value::Float32(_) => "a float",
+        Value::Pointer(_) => "a pointer",
+        Value::Color(_) => "a color",
+    };
+    value_error(format!("expected {expected}, found {found}"))
+}
+
+fn expected_number(text: &str) -> ValueError {
+    value_error(format!("expected a number, found \"{text}\""))
+}
+
+/// Read an integer at the start of `text`, as `atoi`.
+fn read_i64(text: &str) -> Result<i64, ValueError> {
+    let trimmed = text.trim_start();
+    let sign_len = usize::from(trimmed.starts_with(['+', '-']));
+    let digits = trimmed[sign_len..]
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .count();
+    if digits == 0 {
+        return Err(expected_number(text));
+    }
+    let number = &trimmed[..sign_len + digits];
+    number
+        .parse()
+        .map_err(|_| value_error(format!("{number} does not fit in an integer")))
+}
+
+/// Read a float at the start of `text`, as `atof`. Returns the float and the length of the
+/// text that was read, or `None` if `text` does not start with a number.
+fn read_f32(text: &str) -> Option<(f32, usize)> {
+    let start = text.len() - text.trim_start().len();
+    let bytes = text.as_bytes();
+    let digits_from = |pos: usize| {
+        bytes[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut pos = start;
+    if matches!(bytes.get(pos), Some(b'+' | b'-')) {
+        pos += 1;
+    }
+    let integer = digits_from(pos);
+    pos += integer;
+    let mut fraction = 0;
+    if bytes.get(pos) == Some(&b'.') {
+        fraction = digits_from(pos + 1);
+        if integer + fraction > 0 {
+            pos += 1 + fraction;
+        }
+    }
+    if integer + fraction == 0 {
+        return None;
+    }
+    // The exponent is only read if it has digits.
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(pos + 1), Some(b'+' | b'-')));
+        let exponent = digits_from(pos + 1 + sign);
+        if exponent > 0 {
+            pos += 1 + sign + exponent;
+        }
+    }
+    let value = text[start..pos].parse().ok()?;
+
+    Some((value, pos))
+}
+
+/// Read the whitespace separated floats of a vector or a color, as `sscanf` with `%f`
+/// conversions.
+fn read_components(text: &str, expected: &str) -> Result<Vec<f32>, ValueError> {
+    let trimmed = text.trim();
+    let inner = [('[', ']'), ('{', '}')]
+        .iter()
+        .find_map(|(open, close)| trimmed.strip_prefix(*open)?.strip_suffix(*close))
+        .unwrap_or(trimmed);
+    let mut components = vec![];
+    let mut rest = inner;
+    while !rest.trim_start().is_empty() {
+        let Some((component, len)) = read_f32(rest) else {
+            return Err(value_error(format!(
+                "expected {expected}, found \"{text}\""
+            )));
+        };
+        components.push(component);
+        rest = &rest[len..];
+        // Components are separated by whitespace.
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return Err(value_error(format!(
+                "expected {expected}, found \"{text}\""
+            )));
+        }
+    }
+
+    Ok(components)
+}
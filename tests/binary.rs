@@ -0,0 +1,109 @@
+use valve_kv_tools::{read_binary, write_binary, KeyValue, Value};
+
+/// `shortcuts.vdf` with a single non-Steam game.
+const SHORTCUTS: &[u8] = b"\x00shortcuts\x00\
+\x000\x00\
+\x02appid\x00\x39\x30\x00\x80\
+\x01AppName\x00Game\x00\
+\x01Exe\x00\"C:\\Game.exe\"\x00\
+\x02IsHidden\x00\x00\x00\x00\x00\
+\x07LastPlayTime\x00\x01\x00\x00\x00\x00\x00\x00\x00\
+\x00tags\x00\x010\x00favorite\x00\x08\
+\x08\
+\x08\
+\x08";
+
+#[test]
+fn binary_read_shortcuts() {
+    let roots = read_binary(SHORTCUTS).unwrap();
+    assert_eq!(roots.len(), 1);
+    assert_eq!(roots[0].key, "shortcuts");
+    let Value::Section(shortcuts) = &roots[0].value else {
+        panic!("expected a section");
+    };
+    let Value::Section(game) = &shortcuts[0].value else {
+        panic!("expected a section");
+    };
+    let values: Vec<(&str, &Value)> = game.iter().map(|kv| (kv.key.as_str(), &kv.value)).collect();
+    assert_eq!(
+        values[..5],
+        [
+            ("appid", &Value::Int32(-2147471303)),
+            ("AppName", &Value::String("Game".to_string())),
+            ("Exe", &Value::String("\"C:\\Game.exe\"".to_string())),
+            ("IsHidden", &Value::Int32(0)),
+            ("LastPlayTime", &Value::UInt64(1)),
+        ]
+    );
+    assert_eq!(
+        game[5].value,
+        Value::Section(vec![KeyValue::new(
+            "0",
+            Value::String("favorite".to_string())
+        )])
+    );
+}
+
+#[test]
+fn binary_round_trip() {
+    let roots = read_binary(SHORTCUTS).unwrap();
+    assert_eq!(write_binary(&roots).unwrap(), SHORTCUTS);
+}
+
+#[test]
+fn binary_typed_values() {
+    let roots = vec![KeyValue::new(
+        "root",
+        Value::Section(vec![
+            KeyValue::new("float", Value::Float32(0.5)),
+            KeyValue::new("pointer", Value::Pointer(0xDEADBEEF)),
+            KeyValue::new("wstring", Value::WString("Größe 😀".to_string())),
+            KeyValue::new("color", Value::Color([255, 128, 0, 255])),
+            KeyValue::new("nan", Value::Float32(f32::NAN)),
+        ]),
+    )];
+    let bytes = write_binary(&roots).unwrap();
+    assert_eq!(&bytes[..17], b"\x00root\x00\x03float\x00\x00\x00\x00\x3F");
+    assert_eq!(read_binary(&bytes).unwrap(), roots);
+}
+
+#[test]
+fn binary_end_markers() {
+    // Alternate end markers, and no final marker after the root entries.
+    let input = b"\x00root\x00\x01key\x00value\x00\x0B";
+    let roots = read_binary(input).unwrap();
+    assert_eq!(
+        roots,
+        [KeyValue::new(
+            "root",
+            Value::Section(vec![KeyValue::new(
+                "key",
+                Value::String("value".to_string())
+            )])
+        )]
+    );
+}
+
+#[test]
+fn binary_errors() {
+    let error = read_binary(b"\x01key\x00val").unwrap_err();
+    assert_eq!(
+        (error.offset, error.message.as_str()),
+        (5, "unterminated string")
+    );
+
+    let error = read_binary(b"\x00root\x00\x09key\x00").unwrap_err();
+    assert_eq!(error.to_string(), "offset 6: unknown node type 9");
+
+    let error = read_binary(b"\x00root\x00\x02int\x00\x01\x00").unwrap_err();
+    assert_eq!(error.message, "unexpected end of input");
+
+    let error = read_binary(b"\x00root\x00").unwrap_err();
+    assert_eq!(error.message, "expected the end of the section");
+
+    let error = read_binary(b"\x08\x08").unwrap_err();
+    assert_eq!(error.offset, 1);
+
+    let error = write_binary(&[KeyValue::new("a\0b", Value::Int32(1))]).unwrap_err();
+    assert_eq!(error.offset, 2);
+}
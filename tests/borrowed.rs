@@ -0,0 +1,58 @@
+use std::borrow::Cow;
+
+use valve_kv_tools::{
+    serialize_document, serialize_document_borrowed, serialize_document_borrowed_with_config,
+    BorrowedValue, EscapeMode, ParserConfig,
+};
+
+const INPUT: &str = r#"#base "base.txt"
+// Weapons
+"weapons"
+{
+    "pistol" "9mm" [$WIN32] // default
+    "quote" "say \"hi\""
+    "empty" {}
+}
+"#;
+
+#[test]
+fn borrowed_into_owned() {
+    let borrowed = serialize_document_borrowed(INPUT).unwrap();
+    assert_eq!(borrowed.into_owned(), serialize_document(INPUT).unwrap());
+}
+
+#[test]
+fn borrowed_only_copies_escaped_strings() {
+    let document = serialize_document_borrowed(INPUT).unwrap();
+    let root = &document.roots[0];
+    assert!(matches!(root.key, Cow::Borrowed("weapons")));
+    assert_eq!(root.comments, ["// Weapons"]);
+    let BorrowedValue::Section(section) = &root.value else {
+        panic!("expected a section");
+    };
+    assert!(matches!(section[0].key, Cow::Borrowed("pistol")));
+    assert_eq!(section[0].conditional, Some("$WIN32"));
+    assert!(matches!(
+        section[0].value,
+        BorrowedValue::String(Cow::Borrowed("9mm"))
+    ));
+    assert_eq!(
+        section[1].value,
+        BorrowedValue::String(Cow::Owned("say \"hi\"".to_string()))
+    );
+    assert!(matches!(
+        section[1].value,
+        BorrowedValue::String(Cow::Owned(_))
+    ));
+
+    let config = ParserConfig {
+        escape_mode: EscapeMode::Disabled,
+        ..ParserConfig::default()
+    };
+    let input = r#""path" "C:\Games""#;
+    let document = serialize_document_borrowed_with_config(input, config).unwrap();
+    assert!(matches!(
+        document.roots[0].value,
+        BorrowedValue::String(Cow::Borrowed(r"C:\Games"))
+    ));
+}
@@ -0,0 +1,96 @@
+use valve_kv_tools::{
+    evaluate_conditional, evaluate_conditionals, evaluate_conditionals_roots, serialize_document,
+    serialize_keyvalue, Value,
+};
+
+#[test]
+fn conditional_symbol() {
+    assert!(evaluate_conditional("$WIN32", &["WIN32"]).unwrap());
+    assert!(!evaluate_conditional("$WIN32", &["LINUX"]).unwrap());
+}
+
+#[test]
+fn conditional_case_insensitive() {
+    assert!(evaluate_conditional("$win32", &["$WIN32"]).unwrap());
+}
+
+#[test]
+fn conditional_not() {
+    assert!(evaluate_conditional("!$X360", &["WIN32"]).unwrap());
+    assert!(!evaluate_conditional("!$X360", &["X360"]).unwrap());
+}
+
+#[test]
+fn conditional_and_or() {
+    let symbols = ["WIN32", "DECK"];
+    assert!(evaluate_conditional("$WIN32 && $DECK", &symbols).unwrap());
+    assert!(!evaluate_conditional("$WIN32 && !$DECK", &symbols).unwrap());
+    assert!(evaluate_conditional("$OSX || $DECK", &symbols).unwrap());
+    assert!(evaluate_conditional("$OSX && $LINUX || $WIN32", &symbols).unwrap());
+    assert!(!evaluate_conditional("$OSX && ($LINUX || $WIN32)", &symbols).unwrap());
+}
+
+#[test]
+fn conditional_invalid() {
+    assert!(evaluate_conditional("WIN32", &["WIN32"]).is_err());
+    assert!(evaluate_conditional("$WIN32 &", &["WIN32"]).is_err());
+    assert!(evaluate_conditional("($WIN32", &["WIN32"]).is_err());
+    assert!(evaluate_conditional("$", &["WIN32"]).is_err());
+    assert!(evaluate_conditional("", &["WIN32"]).is_err());
+}
+
+#[test]
+fn conditionals_prune_tree() {
+    let input = r#""key"
+{
+    "font" "Arial" [$WIN32]
+    "font" "Helvetica" [$OSX]
+    "tall" "12"
+    "console" [$X360]
+    {
+        "font" "Verdana"
+    }
+}"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    let kv = evaluate_conditionals(&kv, &["OSX"]).unwrap().unwrap();
+    let Value::Section(section) = kv.value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section.len(), 2);
+    assert_eq!(section[0].key, "font");
+    assert_eq!(section[0].value, Value::String("Helvetica".to_string()));
+    assert_eq!(section[0].conditional, None);
+    assert_eq!(section[1].key, "tall");
+}
+
+#[test]
+fn conditionals_prune_root() {
+    let input = r#""key" [$X360] { "font" "Arial" }"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(evaluate_conditionals(&kv, &["WIN32"]).unwrap(), None);
+}
+
+#[test]
+fn conditionals_prune_roots() {
+    let input = r#""win" [$WIN32] { "font" "Arial" }
+"osx" [$OSX] { "font" "Helvetica" [!$OSX] }
+"any" { "tall" "12" }"#;
+    let roots = serialize_document(input).unwrap().roots;
+    let roots = evaluate_conditionals_roots(&roots, &["OSX"]).unwrap();
+    let keys: Vec<&str> = roots.iter().map(|kv| kv.key.as_str()).collect();
+    assert_eq!(keys, ["osx", "any"]);
+    assert_eq!(roots[0].value, Value::Section(vec![]));
+    let roots = serialize_document(r#""a" { } "b" [WIN32] { }"#)
+        .unwrap()
+        .roots;
+    let err = evaluate_conditionals_roots(&roots, &["WIN32"]).unwrap_err();
+    assert_eq!(err.expression, "WIN32");
+}
+
+#[test]
+fn conditionals_invalid_expression() {
+    let input = r#""key" { "font" "Arial" [WIN32] }"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    let err = evaluate_conditionals(&kv, &["WIN32"]).unwrap_err();
+    assert_eq!(err.expression, "WIN32");
+}
@@ -0,0 +1,189 @@
+use valve_kv_tools::{
+    kv1_to_kv3, kv3_to_kv1, serialize_document, serialize_kv3, ConversionConfig, DuplicateKeys,
+    FormatterConfig, Kv3Value, KvErrorKind, Value,
+};
+
+const KV1: &str = r#"// Material of the wall
+"LightmappedGeneric"
+{
+    "$basetexture" "concrete/wall" // diffuse
+    "$surfaceprop" "concrete"
+    "$envmaptint" "0.5"
+    "$selfillum" "1"
+    "$translucent" "false"
+    "proxy" { "name" "a" }
+    "proxy" { "name" "b" }
+}
+"#;
+
+#[test]
+fn convert_kv1_to_kv3() {
+    let roots = serialize_document(KV1).unwrap().roots;
+    let config = ConversionConfig {
+        infer_types: true,
+        ..Default::default()
+    };
+    let conversion = kv1_to_kv3(&roots, config);
+    assert_eq!(conversion.diagnostics, []);
+    assert_eq!(
+        conversion
+            .output
+            .to_text(FormatterConfig::default())
+            .unwrap(),
+        r#"<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+{
+    // Material of the wall
+    LightmappedGeneric =
+    {
+        // diffuse
+        "$basetexture" = "concrete/wall"
+        "$surfaceprop" = "concrete"
+        "$envmaptint" = 0.5
+        "$selfillum" = 1
+        "$translucent" = false
+        proxy =
+        [
+            {
+                name = "a"
+            },
+            {
+                name = "b"
+            },
+        ]
+    }
+}"#
+    );
+}
+
+#[test]
+fn convert_round_trip() {
+    let roots = serialize_document(KV1).unwrap().roots;
+    let kv3 = kv1_to_kv3(&roots, ConversionConfig::default()).output;
+    let kv3 = serialize_kv3(&kv3.to_text(FormatterConfig::default()).unwrap()).unwrap();
+    let conversion = kv3_to_kv1(&kv3);
+    assert_eq!(conversion.diagnostics, []);
+    let strip = |kvs: &[valve_kv_tools::KeyValue]| -> Vec<(String, Option<String>, Vec<String>)> {
+        kvs.iter()
+            .map(|kv| (kv.key.clone(), kv.value.to_text(), kv.comments.clone()))
+            .collect()
+    };
+    assert_eq!(strip(&conversion.output), strip(&roots));
+    let (Value::Section(converted), Value::Section(original)) =
+        (&conversion.output[0].value, &roots[0].value)
+    else {
+        panic!("expected sections");
+    };
+    assert_eq!(strip(converted), strip(original));
+}
+
+#[test]
+fn convert_duplicate_keys() {
+    let roots = serialize_document(r#""root" { "a" "1" "a" "2" [$WIN32] "a" "3" }"#)
+        .unwrap()
+        .roots;
+    let values = |config: ConversionConfig| {
+        let conversion = kv1_to_kv3(&roots, config);
+        let Kv3Value::Object(root) = conversion.output.root else {
+            panic!("expected an object");
+        };
+        let Kv3Value::Object(members) = &root[0].value else {
+            panic!("expected an object");
+        };
+        (members[0].value.clone(), conversion.diagnostics)
+    };
+    let string = |text: &str| Kv3Value::String(text.to_string());
+
+    let (value, diagnostics) = values(ConversionConfig::default());
+    assert_eq!(
+        value,
+        Kv3Value::Array(vec![string("1"), string("2"), string("3")])
+    );
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, KvErrorKind::ConversionWarning);
+    assert_eq!(
+        diagnostics[0].message,
+        "the conditional [$WIN32] of \"a\" is dropped"
+    );
+
+    let (value, diagnostics) = values(ConversionConfig {
+        duplicate_keys: DuplicateKeys::First,
+        ..Default::default()
+    });
+    assert_eq!(value, string("1"));
+    assert_eq!(diagnostics.len(), 3);
+
+    let (value, diagnostics) = values(ConversionConfig {
+        duplicate_keys: DuplicateKeys::Last,
+        ..Default::default()
+    });
+    assert_eq!(value, string("3"));
+    assert_eq!(diagnostics[1].range.start.character, 9);
+    assert_eq!(diagnostics[2].range.start.character, 17);
+}
+
+#[test]
+fn convert_lossy_kv3() {
+    let input = r#"<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+{
+    model = resource:"models/a.vmdl"
+    empty = []
+    nothing = null
+    blob = #[ 01 FF ]
+    matrix = [ [ 1, 0 ], [ 0, 1 ] ]
+    enabled = true
+}"#;
+    let conversion = kv3_to_kv1(&serialize_kv3(input).unwrap());
+    let values: Vec<(&str, Option<String>)> = conversion
+        .output
+        .iter()
+        .map(|kv| (kv.key.as_str(), kv.value.to_text()))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            ("model", Some("models/a.vmdl".to_string())),
+            ("nothing", Some(String::new())),
+            ("blob", Some("01FF".to_string())),
+            ("matrix", Some("1 0".to_string())),
+            ("matrix", Some("0 1".to_string())),
+            ("enabled", Some("1".to_string())),
+        ]
+    );
+    let messages: Vec<&str> = conversion
+        .diagnostics
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "the flag resource of \"model\" is dropped",
+            "the empty array \"empty\" is dropped",
+            "null \"nothing\" is converted to an empty string",
+            "binary blob \"blob\" is converted to a hexadecimal string",
+            "nested array \"matrix\" is converted to a string",
+            "nested array \"matrix\" is converted to a string",
+        ]
+    );
+}
+
+#[test]
+fn convert_non_finite_numbers() {
+    let roots = serialize_document(r#""root" { "a" "inf" "b" "NaN" "c" "-inf" "d" "1.5" }"#)
+        .unwrap()
+        .roots;
+    let config = ConversionConfig {
+        infer_types: true,
+        ..Default::default()
+    };
+    let conversion = kv1_to_kv3(&roots, config);
+    assert_eq!(conversion.diagnostics, []);
+    let text = conversion
+        .output
+        .to_text(FormatterConfig::default())
+        .unwrap();
+    assert!(text.contains(r#"a = "inf""#));
+    assert!(text.contains(r#"b = "NaN""#));
+    assert!(text.contains(r#"c = "-inf""#));
+    assert!(text.contains("d = 1.5"));
+}
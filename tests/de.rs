@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use valve_kv_tools::{from_str, Position, Range};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Gamedata<'a> {
+    #[serde(rename = "Games", borrow)]
+    games: BTreeMap<&'a str, Game>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Game {
+    #[serde(rename = "Offsets", default)]
+    offsets: BTreeMap<String, Offset>,
+    #[serde(rename = "Keys", default)]
+    keys: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Offset {
+    windows: i32,
+    linux: Option<i32>,
+}
+
+#[test]
+fn de_gamedata() {
+    let input = r#""Games"
+{
+    "csgo"
+    {
+        "Offsets"
+        {
+            "GiveNamedItem"
+            {
+                "windows" "400"
+                "linux" " 401 "
+            }
+            "Weapon_Drop" { "windows" "263" }
+        }
+        "Keys" { "Signature" "\"quoted\"" }
+    }
+}"#;
+    let gamedata: Gamedata = from_str(input).unwrap();
+    let csgo = &gamedata.games["csgo"];
+    assert_eq!(
+        csgo.offsets["GiveNamedItem"],
+        Offset {
+            windows: 400,
+            linux: Some(401),
+        }
+    );
+    assert_eq!(
+        csgo.offsets["Weapon_Drop"],
+        Offset {
+            windows: 263,
+            linux: None,
+        }
+    );
+    assert_eq!(csgo.keys["Signature"], "\"quoted\"");
+}
+
+#[test]
+fn de_duplicate_keys() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        #[serde(rename = "Databases")]
+        databases: Databases,
+    }
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Databases {
+        driver_default: String,
+        #[serde(default)]
+        include: Vec<String>,
+        #[serde(default)]
+        exclude: Vec<String>,
+    }
+
+    let input = r#""Databases"
+{
+    "driver_default" "mysql"
+    "include" "a.cfg"
+    "include" "b.cfg"
+    "exclude" "c.cfg"
+}"#;
+    let config: Config = from_str(input).unwrap();
+    assert_eq!(
+        config.databases,
+        Databases {
+            driver_default: "mysql".to_string(),
+            include: vec!["a.cfg".to_string(), "b.cfg".to_string()],
+            exclude: vec!["c.cfg".to_string()],
+        }
+    );
+
+    let error = from_str::<Config>(
+        r#""Databases"
+{
+    "driver_default" "mysql"
+    "driver_default" "sqlite"
+}"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(3, 4), Position::new(3, 20))
+    );
+    assert_eq!(
+        error.message,
+        "duplicate key \"driver_default\", expected a single value"
+    );
+}
+
+#[test]
+fn de_enums() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Mode {
+        #[serde(rename = "fast")]
+        Fast,
+        #[serde(rename = "slow")]
+        Slow,
+    }
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Circle { radius: f32 },
+        Square(u8),
+    }
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Settings {
+        mode: Mode,
+        shapes: Vec<Shape>,
+    }
+
+    let input = r#""mode" "slow"
+"shapes" { "Circle" { "radius" "1.5" } }
+"shapes" { "Square" "4" }"#;
+    let settings: Settings = from_str(input).unwrap();
+    assert_eq!(
+        settings,
+        Settings {
+            mode: Mode::Slow,
+            shapes: vec![Shape::Circle { radius: 1.5 }, Shape::Square(4)],
+        }
+    );
+
+    let error = from_str::<Settings>(r#""mode" "medium" "shapes" { "Square" "4" }"#).unwrap_err();
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(0, 7), Position::new(0, 15))
+    );
+}
+
+#[test]
+fn de_untyped() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    enum Untyped {
+        String(String),
+        Section(BTreeMap<String, Untyped>),
+        Duplicates(Vec<Untyped>),
+    }
+
+    let value: BTreeMap<String, Untyped> = from_str(r#""a" "1" "b" { "c" "2" "c" "3" }"#).unwrap();
+    assert_eq!(value["a"], Untyped::String("1".to_string()));
+    assert_eq!(
+        value["b"],
+        Untyped::Section(BTreeMap::from([(
+            "c".to_string(),
+            Untyped::Duplicates(vec![
+                Untyped::String("2".to_string()),
+                Untyped::String("3".to_string())
+            ])
+        )]))
+    );
+}
+
+#[test]
+fn de_errors_carry_ranges() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    #[serde(deny_unknown_fields)]
+    struct Player {
+        health: u8,
+        alive: bool,
+    }
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Root {
+        player: Player,
+    }
+
+    let error = from_str::<Root>(
+        r#""player"
+{
+    "health" "300"
+    "alive" "1"
+}"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(2, 13), Position::new(2, 18))
+    );
+    assert_eq!(
+        error.to_string(),
+        "3:14: invalid value: string \"300\", expected u8"
+    );
+
+    let error =
+        from_str::<Root>(r#""player" { "health" "1" "alive" "true" "armor" "1" }"#).unwrap_err();
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(0, 39), Position::new(0, 46))
+    );
+
+    let error = from_str::<Root>(r#""player" { "health" "1" }"#).unwrap_err();
+    assert_eq!(error.message, "missing field `alive`");
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(0, 9), Position::new(0, 25))
+    );
+
+    let error = from_str::<Root>(r#""player" {"#).unwrap_err();
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(0, 9), Position::new(0, 10))
+    );
+}
@@ -0,0 +1,78 @@
+use valve_kv_tools::{
+    format_keyvalue, format_keyvalue_bytes, lint_keyvalue_bytes, FormatterConfig, KvErrorKind,
+    ParserConfig, Position, PositionEncoding, TextEncoding,
+};
+
+const INPUT: &str = r#""lang"
+{
+"Language" "russian"
+"Tokens" { "Greeting" "Привет" }
+}"#;
+
+#[test]
+fn encoding_format_utf16le() {
+    let input = TextEncoding::Utf16Le.encode(INPUT);
+    assert_eq!(&input[..2], [0xFF, 0xFE]);
+    let output = format_keyvalue_bytes(&input, FormatterConfig::default()).unwrap();
+    let expected = format_keyvalue(INPUT, FormatterConfig::default()).unwrap();
+    assert_eq!(output, TextEncoding::Utf16Le.encode(&expected));
+}
+
+#[test]
+fn encoding_format_keeps_encoding() {
+    for encoding in [
+        TextEncoding::Utf8,
+        TextEncoding::Utf8Bom,
+        TextEncoding::Utf16Le,
+        TextEncoding::Utf16Be,
+    ] {
+        let output =
+            format_keyvalue_bytes(&encoding.encode(INPUT), FormatterConfig::default()).unwrap();
+        assert_eq!(TextEncoding::detect(&output), encoding);
+    }
+}
+
+#[test]
+fn encoding_lint_with_bom() {
+    let input = TextEncoding::Utf8Bom.encode(r#""key" "value""#);
+    assert_eq!(lint_keyvalue_bytes(&input, ParserConfig::default()), []);
+
+    let input = TextEncoding::Utf16Be.encode("\"Привет\" \"мир\" }");
+    let errors = lint_keyvalue_bytes(&input, ParserConfig::default());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].range.start, Position::new(0, 15));
+}
+
+#[test]
+fn encoding_invalid_input() {
+    let errors = lint_keyvalue_bytes(b"\"key\"\n\"\xFF\"", ParserConfig::default());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, KvErrorKind::EncodingError);
+    assert_eq!(errors[0].range.start, Position::new(1, 1));
+
+    // Lone high surrogate.
+    let errors = lint_keyvalue_bytes(&[0xFF, 0xFE, b'k', 0, 0x00, 0xD8], ParserConfig::default());
+    assert_eq!(errors[0].kind, KvErrorKind::EncodingError);
+    assert_eq!(errors[0].range.start, Position::new(0, 1));
+
+    let error = format_keyvalue_bytes(&[0xFF, 0xFE, b'k'], FormatterConfig::default()).unwrap_err();
+    assert_eq!(error.message, "odd number of bytes in UTF-16 text");
+}
+
+#[test]
+fn encoding_invalid_input_position_encoding() {
+    let input = ["\"Größe 😀".as_bytes(), b"\xFF\""].concat();
+    for (position_encoding, character) in [
+        (PositionEncoding::Utf8, 13),
+        (PositionEncoding::Utf16, 9),
+        (PositionEncoding::Scalars, 8),
+    ] {
+        let config = ParserConfig {
+            position_encoding,
+            ..Default::default()
+        };
+        let errors = lint_keyvalue_bytes(&input, config);
+        assert_eq!(errors[0].kind, KvErrorKind::EncodingError);
+        assert_eq!(errors[0].range.start, Position::new(0, character));
+    }
+}
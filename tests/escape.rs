@@ -0,0 +1,31 @@
+use valve_kv_tools::{escape_string, unescape_string};
+
+#[test]
+fn unescape_simple() {
+    assert_eq!(unescape_string(r#"say \"hi\""#), r#"say "hi""#);
+    assert_eq!(unescape_string(r#"a\\b\/c"#), r#"a\b/c"#);
+    assert_eq!(unescape_string(r#"line\nnext\ttab\r"#), "line\nnext\ttab\r");
+    assert_eq!(unescape_string(r#"\b\f"#), "\u{8}\u{c}");
+}
+
+#[test]
+fn unescape_unicode() {
+    assert_eq!(unescape_string(r#"A\u00e9"#), "A\u{e9}");
+    assert_eq!(unescape_string(r#"\ud83d\ude00"#), "\u{1f600}");
+    assert_eq!(unescape_string(r#"\ud83d"#), "\u{fffd}");
+}
+
+#[test]
+fn unescape_invalid() {
+    assert_eq!(unescape_string(r#"\q"#), r#"\q"#);
+    assert_eq!(unescape_string(r#"\u12"#), r#"\u12"#);
+    assert_eq!(unescape_string(r#"end\"#), r#"end\"#);
+}
+
+#[test]
+fn escape_round_trip() {
+    let text = "say \"hi\"\n\tC:\\path\u{1}";
+    let escaped = escape_string(text);
+    assert_eq!(escaped, r#"say \"hi\"\n\tC:\\path\u0001"#);
+    assert_eq!(unescape_string(&escaped), text);
+}
@@ -1,4 +1,4 @@
-use valve_kv_tools::{format_keyvalue, FormatterConfig};
+use valve_kv_tools::{format_keyvalue, EscapeMode, FormatterConfig, QuoteStyle};
 
 #[test]
 fn formatter_key_value() {
@@ -202,3 +202,225 @@ fn formatter_multiple_comments() {
     let output = format_keyvalue(input, FormatterConfig::default()).unwrap();
     assert_eq!(input, output);
 }
+
+#[test]
+fn formatter_unquoted_preserve() {
+    let input = r#"LightmappedGeneric
+{
+    $basetexture    "foo/bar"
+    "$surfaceprop"    metal
+}"#;
+    let output = format_keyvalue(input, FormatterConfig::default()).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn formatter_unquoted_always() {
+    let input = r#"LightmappedGeneric
+{
+    $basetexture    "foo/bar"
+    $path    materials\foo
+}"#;
+    let output = r#""LightmappedGeneric"
+{
+    "$basetexture"    "foo/bar"
+    "$path"    "materials\\foo"
+}"#;
+    let config = FormatterConfig {
+        quote_style: QuoteStyle::Always,
+        ..Default::default()
+    };
+    assert_eq!(output, format_keyvalue(input, config).unwrap());
+}
+
+#[test]
+fn formatter_unquoted_minimal() {
+    let input = r#""LightmappedGeneric"
+{
+    "$basetexture"    "foo/bar"
+    "$name"    "two words"
+    "$empty"    ""
+    "$brace"    "{"
+}"#;
+    let output = r#"LightmappedGeneric
+{
+    $basetexture    foo/bar
+    $name    "two words"
+    $empty    ""
+    $brace    "{"
+}"#;
+    let config = FormatterConfig {
+        quote_style: QuoteStyle::Minimal,
+        ..Default::default()
+    };
+    assert_eq!(output, format_keyvalue(input, config).unwrap());
+}
+
+#[test]
+fn formatter_unquoted_minimal_keeps_quotes() {
+    let input = r##""#default"
+{
+    "#format"    "{1:s}"
+    "platform"    "[$WIN32]"
+}"##;
+    let output = r##""#default"
+{
+    #format    "{1:s}"
+    platform    "[$WIN32]"
+}"##;
+    let config = FormatterConfig {
+        quote_style: QuoteStyle::Minimal,
+        ..Default::default()
+    };
+    assert_eq!(output, format_keyvalue(input, config).unwrap());
+}
+
+#[test]
+fn formatter_conditional_value() {
+    let input = r#""key"
+{
+    "font"    "Arial"    [$WIN32]
+    "font"    "Verdana"    [$OSX]  // comment
+}"#;
+    let output = format_keyvalue(input, FormatterConfig::default()).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn formatter_conditional_section() {
+    let input = r#""key"  [!$X360]  {
+    "font" "Arial"  [$WIN32]
+}"#;
+    let output = r#""key"    [!$X360]
+{
+    "font"    "Arial"    [$WIN32]
+}"#;
+    assert_eq!(
+        output,
+        format_keyvalue(input, FormatterConfig::default()).unwrap()
+    );
+}
+
+#[test]
+fn formatter_directives() {
+    let input = r#"#BASE   "base.res"  // comment
+#include "include.res"
+"key"
+{
+    "key"    "value"
+}"#;
+    let output = r#"#base "base.res"  // comment
+#include "include.res"
+"key"
+{
+    "key"    "value"
+}"#;
+    assert_eq!(
+        output,
+        format_keyvalue(input, FormatterConfig::default()).unwrap()
+    );
+}
+
+#[test]
+fn formatter_multiple_roots() {
+    let input = r#""versioninfo"
+{
+    "editorversion"    "400"
+}
+// entities
+"entity"
+{
+    "id"    "2"
+}
+"entity"    "3""#;
+    let output = format_keyvalue(input, FormatterConfig::default()).unwrap();
+    assert_eq!(input, output);
+}
+
+#[test]
+fn formatter_disabled_escapes() {
+    let input = r#"LightmappedGeneric
+{
+    $basetexture    "materials\models\"
+}"#;
+    let output = r#""LightmappedGeneric"
+{
+    "$basetexture"    "materials\models\"
+}"#;
+    let config = FormatterConfig {
+        quote_style: QuoteStyle::Always,
+        escape_mode: EscapeMode::Disabled,
+        ..Default::default()
+    };
+    assert_eq!(output, format_keyvalue(input, config).unwrap());
+}
+
+#[test]
+fn formatter_closing_braces() {
+    let input = r#""key" { "key" { "key" "value"
+} }
+"empty" { }"#;
+    let output = format_keyvalue(input, FormatterConfig::default()).unwrap();
+    assert_eq!(
+        output,
+        r#""key"
+{
+    "key"
+    {
+        "key"    "value"
+    }
+}
+"empty"
+{
+}"#
+    );
+}
+
+#[test]
+fn formatter_max_empty_lines() {
+    let input = r#""key"
+{
+    "a"    "1"
+
+
+    "b"    /* comment */
+    "2"
+
+    "c"    "3"
+}"#;
+    let outputs = [
+        r#""key"
+{
+    "a"    "1"
+    "b"  /* comment */
+    "2"
+    "c"    "3"
+}"#,
+        r#""key"
+{
+    "a"    "1"
+
+    "b"  /* comment */
+    "2"
+
+    "c"    "3"
+}"#,
+        r#""key"
+{
+    "a"    "1"
+
+
+    "b"  /* comment */
+    "2"
+
+    "c"    "3"
+}"#,
+    ];
+    for (max_empty_lines, output) in outputs.into_iter().enumerate() {
+        let config = FormatterConfig {
+            max_empty_lines: max_empty_lines as u32,
+            ..Default::default()
+        };
+        assert_eq!(output, format_keyvalue(input, config).unwrap());
+    }
+}
@@ -0,0 +1,118 @@
+//! The pest grammar of `valve-kv.pest` is the reference of the syntax: the hand-written
+//! parser must accept the same inputs and read the same keys and values.
+
+use pest::{iterators::Pair, Parser};
+use pest_derive::Parser;
+use valve_kv_tools::{parse_document_with_config, KeyValue, ParserConfig, Value};
+
+#[derive(Parser)]
+#[grammar = "valve-kv.pest"]
+struct KeyValueParser;
+
+const VALID: &[&str] = &[
+    r#""key" "value""#,
+    "key value",
+    r#""key" { "a" "1" "b" { } }"#,
+    r#"#base "base.res"
+#include "other.res"
+"root" // comment
+{
+    /* block
+       comment */ "a" "1" [$WIN32]
+    "b" [!$X360] { c "d" }
+    "escaped" "say \"hi\"\né"
+    "multi" "line
+text"
+}
+"second" "root""#,
+    "a\"b\" c{}",
+];
+
+const INVALID: &[&str] = &[
+    r#""key""#,
+    r#""key" {"#,
+    r#""key" "value" }"#,
+    r#"{ "a" "b" }"#,
+    r#""key" "bad \q escape""#,
+    r#""key" [$WIN32] "value""#,
+    r#""key" "unterminated"#,
+];
+
+/// Keys and raw values of the grammar, in order, with `{` and `}` for sections.
+fn pest_entries(pair: Pair<Rule>, entries: &mut Vec<String>) {
+    match pair.as_rule() {
+        Rule::key | Rule::value => {
+            let string = pair
+                .into_inner()
+                .next()
+                .expect("strings have an inner rule");
+            let text = match string.into_inner().next() {
+                Some(inner) if inner.as_rule() == Rule::quoted_string => inner
+                    .into_inner()
+                    .next()
+                    .map_or(String::new(), |inner| inner.as_str().to_string()),
+                Some(inner) => inner.as_str().to_string(),
+                None => String::new(),
+            };
+            entries.push(text);
+        }
+        Rule::l_brace => entries.push("{".to_string()),
+        Rule::r_brace => entries.push("}".to_string()),
+        Rule::directive => (),
+        _ => {
+            for child in pair.into_inner() {
+                pest_entries(child, entries);
+            }
+        }
+    }
+}
+
+fn entries(kvs: &[KeyValue], entries: &mut Vec<String>) {
+    for kv in kvs {
+        entries.push(kv.key.clone());
+        match &kv.value {
+            Value::Section(section) => {
+                entries.push("{".to_string());
+                self::entries(section, entries);
+                entries.push("}".to_string());
+            }
+            value => entries.push(value.to_text().unwrap_or_default()),
+        }
+    }
+}
+
+#[test]
+fn grammar_matches_parser() {
+    let config = ParserConfig {
+        unescape: false,
+        ..ParserConfig::default()
+    };
+    for input in VALID {
+        let document = KeyValueParser::parse(Rule::json_document, input)
+            .unwrap_or_else(|err| panic!("{input}: {err}"))
+            .next()
+            .unwrap();
+        let mut expected = vec![];
+        pest_entries(document, &mut expected);
+        let result = parse_document_with_config(input, config);
+        assert_eq!(result.errors, [], "{input}");
+        let mut actual = vec![];
+        entries(&result.document.roots, &mut actual);
+        assert_eq!(actual, expected, "{input}");
+    }
+}
+
+#[test]
+fn grammar_rejects_like_parser() {
+    for input in INVALID {
+        assert!(
+            KeyValueParser::parse(Rule::json_document, input).is_err(),
+            "{input}"
+        );
+        assert_ne!(
+            parse_document_with_config(input, ParserConfig::default()).errors,
+            [],
+            "{input}"
+        );
+    }
+}
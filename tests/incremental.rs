@@ -0,0 +1,143 @@
+use valve_kv_tools::{
+    lint_parse_result, parse_document, parse_document_with_config, reparse_document, KvErrorKind,
+    ParserConfig, Position, Range, TextEdit, Value,
+};
+
+const INPUT: &str = r##"#base "base.res"
+// Phrases
+"Phrases"
+{
+    "Hello" // greeting
+    {
+        "#format" "{1:s}"
+        "en" "Hello {1}" [$WIN32]
+        "fr" "Bonjour {1} é" /* comment */
+    }
+    "Empty" { }
+    "Bye" { "en" "Bye" }
+}
+"Other" { "a" "b" "a" "c" }
+#include "end.res"
+"##;
+
+const INVALID: &str = "\"a\" { \"b\" \"c\n\"d\" { \"e\" } }\n\"f\" { \"g\" [$X }\n} \"h\"";
+
+/// Position of a byte offset, in UTF-16 code units.
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
+
+fn check_edit(input: &str, start: usize, end: usize, text: &str) {
+    let edit = TextEdit {
+        range: Range::new(position(input, start), position(input, end)),
+        text: text.to_string(),
+    };
+    let mut expected = input.to_string();
+    expected.replace_range(start..end, text);
+    let result = reparse_document(parse_document(input), &edit, ParserConfig::default());
+    assert_eq!(
+        result,
+        parse_document(&expected),
+        "replacing {start}..{end} with {text:?}"
+    );
+}
+
+fn check_all_edits(input: &str) {
+    let offsets: Vec<usize> = (0..=input.len())
+        .filter(|i| input.is_char_boundary(*i))
+        .collect();
+    for (i, &start) in offsets.iter().enumerate() {
+        for text in [
+            "",
+            "x",
+            "\"",
+            "{",
+            "}",
+            "\n",
+            "/*",
+            "//",
+            "[$X]",
+            "é",
+            " \"k\" \"v\"\n",
+        ] {
+            for &end in offsets[i..].iter().take(3) {
+                if text.is_empty() && start == end {
+                    continue;
+                }
+                check_edit(input, start, end, text);
+            }
+        }
+    }
+}
+
+#[test]
+fn incremental_matches_full_parse() {
+    check_all_edits(INPUT);
+}
+
+#[test]
+fn incremental_matches_full_parse_with_errors() {
+    check_all_edits(INVALID);
+}
+
+#[test]
+fn incremental_matches_full_parse_with_comments() {
+    check_all_edits("// a\n\"a\" { \"b\" { } // b\n// c\n} } // d\n// e\n\"f\" { \"g\" { \"h\" \"i\" } } /* j */");
+}
+
+#[test]
+fn incremental_edits_in_sequence() {
+    let mut text = INPUT.to_string();
+    let mut result = parse_document(&text);
+    let edits = [
+        ("Bonjour", "Salut"),
+        ("{ }", "{ \"x\" \"1\" }"),
+        ("Bye\" }", "Bye\" \"en\" \"Ciao\" }"),
+    ];
+    for (old, new) in edits {
+        let start = text.find(old).unwrap();
+        let end = start + old.len();
+        let edit = TextEdit {
+            range: Range::new(position(&text, start), position(&text, end)),
+            text: new.to_string(),
+        };
+        text.replace_range(start..end, new);
+        result = reparse_document(result, &edit, ParserConfig::default());
+        assert_eq!(result, parse_document(&text));
+    }
+    let errors = lint_parse_result(&result);
+    let duplicates: Vec<_> = errors
+        .iter()
+        .filter(|error| error.kind == KvErrorKind::DuplicateError)
+        .collect();
+    assert_eq!(duplicates.len(), 2);
+}
+
+#[test]
+fn incremental_keeps_other_sections() {
+    let mut previous = parse_document_with_config(INPUT, ParserConfig::default());
+    // Only the edited section is parsed again, the other roots are kept as they are.
+    previous.document.roots[1].value = Value::String("kept".to_string());
+    let start = INPUT.find("Hello {1}").unwrap();
+    let edit = TextEdit {
+        range: Range::new(position(INPUT, start), position(INPUT, start + 5)),
+        text: "Hi".to_string(),
+    };
+    let result = reparse_document(previous, &edit, ParserConfig::default());
+    assert_eq!(
+        result.document.roots[1].value,
+        Value::String("kept".to_string())
+    );
+    let Value::Section(phrases) = &result.document.roots[0].value else {
+        panic!("expected a section");
+    };
+    let Value::Section(hello) = &phrases[0].value else {
+        panic!("expected a section");
+    };
+    assert_eq!(hello[1].value, Value::String("Hi {1}".to_string()));
+}
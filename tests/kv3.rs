@@ -0,0 +1,277 @@
+use valve_kv_tools::{
+    format_kv3, lint_kv3, lint_kv3_with_config, serialize_kv3, serialize_kv3_with_config,
+    FormatterConfig, Kv3Document, Kv3Header, Kv3Member, Kv3Value, KvErrorKind, ParserConfig,
+    Position, PositionEncoding, QuoteStyle, Range,
+};
+
+const HEADER: &str = "<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->";
+
+#[test]
+fn kv3_serialize_values() {
+    let input = format!(
+        r#"{HEADER}
+{{
+    // comment
+    string = "a \"quoted\"\nline"
+    int = -12
+    big = 18446744073709551615
+    double = 1.5e2
+    bools = [ true, false, null ]
+    "quoted key" = resource:"models/dev/error.vmdl"
+    blob = #[ 00 7f FF ]
+    multi = """
+first
+second
+"""
+    nested = {{ a = 1, b = [ ] }}
+}}"#
+    );
+    let document = serialize_kv3(&input).unwrap();
+    assert_eq!(document.header, Kv3Header::default());
+    let Kv3Value::Object(members) = &document.root else {
+        panic!("expected an object");
+    };
+    let values: Vec<(&str, &Kv3Value)> = members
+        .iter()
+        .map(|member| (member.key.as_str(), &member.value))
+        .collect();
+    assert_eq!(
+        values[..8],
+        [
+            (
+                "string",
+                &Kv3Value::String("a \"quoted\"\nline".to_string())
+            ),
+            ("int", &Kv3Value::Int(-12)),
+            ("big", &Kv3Value::UInt(u64::MAX)),
+            ("double", &Kv3Value::Double(150.0)),
+            (
+                "bools",
+                &Kv3Value::Array(vec![
+                    Kv3Value::Bool(true),
+                    Kv3Value::Bool(false),
+                    Kv3Value::Null
+                ])
+            ),
+            (
+                "quoted key",
+                &Kv3Value::Flagged(
+                    "resource".to_string(),
+                    Box::new(Kv3Value::String("models/dev/error.vmdl".to_string()))
+                )
+            ),
+            ("blob", &Kv3Value::Binary(vec![0x00, 0x7F, 0xFF])),
+            ("multi", &Kv3Value::String("first\nsecond".to_string())),
+        ]
+    );
+    assert_eq!(
+        members[1].key_range,
+        Range::new(Position::new(4, 4), Position::new(4, 7))
+    );
+    let Kv3Value::Object(nested) = &members[8].value else {
+        panic!("expected an object");
+    };
+    assert_eq!(nested[1].value, Kv3Value::Array(vec![]));
+}
+
+#[test]
+fn kv3_format() {
+    let input = format!(
+        r#"{HEADER}
+{{ // root
+  name="test" model=resource:"a.vmdl"
+
+
+  origin=[0.0,1.0,  2.0,]
+  children=[{{a=1}},
+  /* block */ "b"]
+  empty = {{}}
+  text = """
+  raw
+"""
+}}"#
+    );
+    let expected = format!(
+        r#"{HEADER}
+{{  // root
+    name = "test"
+    model = resource:"a.vmdl"
+
+    origin = [ 0.0, 1.0, 2.0 ]
+    children =
+    [
+        {{
+            a = 1
+        }},
+        /* block */  "b",
+    ]
+    empty =
+    {{
+    }}
+    text = """
+  raw
+"""
+}}"#
+    );
+    let output = format_kv3(&input, FormatterConfig::default()).unwrap();
+    assert_eq!(output, expected);
+    assert_eq!(
+        format_kv3(&output, FormatterConfig::default()).unwrap(),
+        output
+    );
+}
+
+#[test]
+fn kv3_format_quote_style() {
+    let input = format!("{HEADER}\n{{\n    a = 1\n    \"b\" = 2\n    \"c d\" = 3\n}}");
+    let config = FormatterConfig {
+        quote_style: QuoteStyle::Minimal,
+        ..Default::default()
+    };
+    let output = format_kv3(&input, config).unwrap();
+    assert!(output.ends_with("{\n    a = 1\n    b = 2\n    \"c d\" = 3\n}"));
+    let config = FormatterConfig {
+        quote_style: QuoteStyle::Always,
+        ..Default::default()
+    };
+    let output = format_kv3(&input, config).unwrap();
+    assert!(output.ends_with("{\n    \"a\" = 1\n    \"b\" = 2\n    \"c d\" = 3\n}"));
+}
+
+#[test]
+fn kv3_lint() {
+    let input = format!(
+        r#"{HEADER}
+{{
+    a = 1
+    b = {{ c = 1 c = 2 }}
+    a = 2
+}}"#
+    );
+    let errors = lint_kv3(&input);
+    let errors: Vec<(u32, Vec<u32>, &str)> = errors
+        .iter()
+        .map(|error| {
+            (
+                error.range.start.line,
+                error
+                    .additional_ranges
+                    .iter()
+                    .map(|range| range.start.line)
+                    .collect(),
+                error.message.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (3, vec![3], "Duplicate entry for key \"c\""),
+            (2, vec![4], "Duplicate entry for key \"a\""),
+        ]
+    );
+}
+
+#[test]
+fn kv3_syntax_error() {
+    let input = format!("{HEADER}\n{{\n    a = 1\n    b =\n}}");
+    let errors = lint_kv3(&input);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, KvErrorKind::SyntaxError);
+    assert_eq!(errors[0].range.start, Position::new(4, 0));
+    assert!(format_kv3(&input, FormatterConfig::default()).is_err());
+    assert!(serialize_kv3("{ a = 1 }").is_err());
+}
+
+#[test]
+fn kv3_position_encoding() {
+    let input = format!("{HEADER}\n{{\n    \"é😀\" = 1\n    \"é😀\" = 2 =\n}}");
+    for (position_encoding, character) in [
+        (PositionEncoding::Utf8, 17),
+        (PositionEncoding::Utf16, 14),
+        (PositionEncoding::Scalars, 13),
+    ] {
+        let config = ParserConfig {
+            position_encoding,
+            ..Default::default()
+        };
+        let errors = lint_kv3_with_config(&input, config);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].range.start, Position::new(3, character));
+
+        let input = input.replace(" = 2 =", " = 2");
+        let document = serialize_kv3_with_config(&input, config).unwrap();
+        let Kv3Value::Object(members) = document.root else {
+            panic!("expected an object");
+        };
+        assert_eq!(members[1].key_range.end, Position::new(3, character - 5));
+    }
+}
+
+#[test]
+fn kv3_write_invalid_documents() {
+    let member = |key: &str, value: Kv3Value, comments: &[&str]| Kv3Member {
+        key: key.to_string(),
+        key_range: Range::default(),
+        value,
+        comments: comments.iter().map(|comment| comment.to_string()).collect(),
+    };
+    let document = |root: Kv3Value| Kv3Document {
+        header: Kv3Header::default(),
+        root,
+    };
+    let valid = document(Kv3Value::Object(vec![member(
+        "model",
+        Kv3Value::Flagged(
+            "resource_name".to_string(),
+            Box::new(Kv3Value::String("a.vmdl".to_string())),
+        ),
+        &["// line", "/* block */"],
+    )]));
+    let text = valid.to_text(FormatterConfig::default()).unwrap();
+    let Kv3Value::Object(members) = serialize_kv3(&text).unwrap().root else {
+        panic!("expected an object");
+    };
+    let Kv3Value::Object(expected) = &valid.root else {
+        unreachable!();
+    };
+    assert_eq!(members[0].value, expected[0].value);
+    assert_eq!(members[0].comments, expected[0].comments);
+
+    for (root, message) in [
+        (
+            Kv3Value::Flagged("not a flag".to_string(), Box::new(Kv3Value::Null)),
+            "invalid flag \"not a flag\"",
+        ),
+        (
+            Kv3Value::Object(vec![member("a", Kv3Value::Null, &["comment"])]),
+            "invalid comment \"comment\"",
+        ),
+        (
+            Kv3Value::Object(vec![member("a", Kv3Value::Null, &["/* a */ b */"])]),
+            "invalid comment \"/* a */ b */\"",
+        ),
+        (
+            Kv3Value::Object(vec![member("a", Kv3Value::Null, &["// a\nb"])]),
+            "invalid comment \"// a\nb\"",
+        ),
+        (
+            Kv3Value::Array(vec![Kv3Value::Double(f64::NAN)]),
+            "NaN cannot be written in KeyValues3",
+        ),
+        (
+            Kv3Value::Double(f64::INFINITY),
+            "inf cannot be written in KeyValues3",
+        ),
+    ] {
+        let error = document(root)
+            .to_text(FormatterConfig::default())
+            .unwrap_err();
+        assert_eq!(error.message, message);
+    }
+
+    let mut invalid = document(Kv3Value::Null);
+    invalid.header.format = "generic -->".to_string();
+    let error = invalid.to_text(FormatterConfig::default()).unwrap_err();
+    assert_eq!(error.message, "invalid header field \"generic -->\"");
+}
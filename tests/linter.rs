@@ -1,4 +1,7 @@
-use valve_kv_tools::{lint_keyvalue, KvError, KvErrorKind, Position, Range};
+use valve_kv_tools::{
+    lint_keyvalue, lint_keyvalue_with_config, EscapeMode, KvError, KvErrorKind, ParserConfig,
+    Position, PositionEncoding, Range,
+};
 
 #[test]
 fn linter_keyvalue_ok() {
@@ -141,16 +144,16 @@ fn linter_syntax_error_1() {
         [KvError {
             range: Range {
                 start: Position {
-                    line: 2,
-                    character: 2
+                    line: 1,
+                    character: 0
                 },
                 end: Position {
-                    line: 2,
-                    character: 2
+                    line: 1,
+                    character: 1
                 }
             },
             additional_ranges: vec![],
-            message: "expected COMMENT, r_brace, or string".to_string(),
+            message: "expected '}' to close the section".to_string(),
             kind: KvErrorKind::SyntaxError
         }]
     )
@@ -164,17 +167,238 @@ fn linter_syntax_error_2() {
         [KvError {
             range: Range {
                 start: Position {
-                    line: 1,
-                    character: 7
+                    line: 0,
+                    character: 6
                 },
                 end: Position {
-                    line: 1,
-                    character: 7
+                    line: 0,
+                    character: 10
                 }
             },
             additional_ranges: vec![],
-            message: "expected COMMENT, l_brace, or string".to_string(),
+            message: "unterminated string".to_string(),
             kind: KvErrorKind::SyntaxError
         }]
     )
 }
+
+#[test]
+fn linter_unquoted_duplicate() {
+    let input = r#"key
+{
+    key     value
+    "key"   "value"
+}"#;
+    assert_eq!(
+        lint_keyvalue(input),
+        [KvError {
+            range: Range {
+                start: Position {
+                    line: 2,
+                    character: 4,
+                },
+                end: Position {
+                    line: 2,
+                    character: 7,
+                },
+            },
+            additional_ranges: vec![Range {
+                start: Position {
+                    line: 3,
+                    character: 4,
+                },
+                end: Position {
+                    line: 3,
+                    character: 9,
+                },
+            }],
+            message: "Duplicate entry for key \"key\"".to_string(),
+            kind: KvErrorKind::DuplicateError,
+        }]
+    );
+}
+
+#[test]
+fn linter_conditional_ok() {
+    let input = r#""key"
+{
+    "x"    "1"    [$WIN32]
+    "x"    "2"    [$OSX]
+    "x"    "3"
+}"#;
+    assert_eq!(lint_keyvalue(input), []);
+}
+
+#[test]
+fn linter_conditional_duplicate() {
+    let input = r#""key"
+{
+    "x"    "1"    [$WIN32]
+    "x"    "2"    [$WIN32]
+}"#;
+    assert_eq!(
+        lint_keyvalue(input),
+        [KvError {
+            range: Range {
+                start: Position {
+                    line: 2,
+                    character: 4,
+                },
+                end: Position {
+                    line: 2,
+                    character: 7,
+                },
+            },
+            additional_ranges: vec![Range {
+                start: Position {
+                    line: 3,
+                    character: 4,
+                },
+                end: Position {
+                    line: 3,
+                    character: 7,
+                },
+            }],
+            message: "Duplicate entry for key \"x\"".to_string(),
+            kind: KvErrorKind::DuplicateError,
+        }]
+    );
+}
+
+#[test]
+fn linter_multiple_roots() {
+    let input = r#""entity"
+{
+    "id"    "1"
+}
+"entity"
+{
+    "id"    "2"
+    "id"    "3"
+}"#;
+    assert_eq!(
+        lint_keyvalue(input),
+        [KvError {
+            range: Range {
+                start: Position {
+                    line: 6,
+                    character: 4,
+                },
+                end: Position {
+                    line: 6,
+                    character: 8,
+                },
+            },
+            additional_ranges: vec![Range {
+                start: Position {
+                    line: 7,
+                    character: 4,
+                },
+                end: Position {
+                    line: 7,
+                    character: 8,
+                },
+            }],
+            message: "Duplicate entry for key \"id\"".to_string(),
+            kind: KvErrorKind::DuplicateError,
+        }]
+    );
+}
+
+#[test]
+fn linter_escaped_duplicate() {
+    let input = r#""key"
+{
+    "a\u0062"    "value"
+    "ab"        "value"
+}"#;
+    assert_eq!(lint_keyvalue(input).len(), 1);
+}
+
+#[test]
+fn linter_disabled_escapes() {
+    let input = r#""path" "C:\Program Files\""#;
+    let config = ParserConfig {
+        escape_mode: EscapeMode::Disabled,
+        ..Default::default()
+    };
+    assert_eq!(lint_keyvalue_with_config(input, config), []);
+    // `\P` is not a JSON escape, and `\"` escapes the closing quote.
+    assert_eq!(lint_keyvalue(input).len(), 2);
+}
+
+#[test]
+fn linter_reports_every_syntax_error() {
+    let input = r#""key"
+{
+    "a"    "1
+    "b"    "2"
+    "c"
+}
+}
+"d"
+{
+    "e"    "3"
+    "e"    "4"
+"#;
+    let errors = lint_keyvalue(input);
+    let errors: Vec<(u32, &str)> = errors
+        .iter()
+        .map(|error| (error.range.start.line, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (2, "unterminated string"),
+            (4, "expected a value or a section"),
+            (6, "unexpected '}'"),
+            (8, "expected '}' to close the section"),
+            (9, "Duplicate entry for key \"e\""),
+        ]
+    );
+}
+
+#[test]
+fn linter_non_ascii_columns() {
+    let input = r#""lang"
+{
+    "Привет"    "мир"
+    "名前"    "値"
+    "Привет"    "😀"
+}"#;
+    let errors = lint_keyvalue(input);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].additional_ranges,
+        [Range::new(Position::new(4, 4), Position::new(4, 12))]
+    );
+    let config = ParserConfig {
+        position_encoding: PositionEncoding::Utf8,
+        ..Default::default()
+    };
+    let errors = lint_keyvalue_with_config(input, config);
+    assert_eq!(
+        errors[0].range,
+        Range::new(Position::new(2, 4), Position::new(2, 18))
+    );
+}
+
+#[test]
+fn linter_syntax_error_after_non_ascii() {
+    let input = "\"名前\" \"値\" \"😀\" \"x\"\n\"ключ\" \"a\" }";
+    let errors = lint_keyvalue(input);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].range,
+        Range::new(Position::new(1, 11), Position::new(1, 12))
+    );
+    let config = ParserConfig {
+        position_encoding: PositionEncoding::Scalars,
+        ..Default::default()
+    };
+    let errors = lint_keyvalue_with_config("\"😀\" \"x\" }", config);
+    assert_eq!(
+        errors[0].range,
+        Range::new(Position::new(0, 8), Position::new(0, 9))
+    );
+}
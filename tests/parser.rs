@@ -0,0 +1,187 @@
+use valve_kv_tools::{
+    parse_document, parse_document_with_config, serialize_document, EscapeMode, ParserConfig,
+    Position, Range, Span, Value,
+};
+
+#[test]
+fn parser_matches_serializer() {
+    let input = r#"#base "base.res"
+// comment
+"versioninfo"
+{
+    "editorversion"    "400"  [$WIN32]
+    unquoted    value
+    "nested"  [!$X360]
+    {
+        "escaped"    "say \"hi\"\n"  /* comment */
+    }
+}
+"entity" "1""#;
+    let result = parse_document(input);
+    assert_eq!(result.errors, []);
+    assert_eq!(result.document, serialize_document(input).unwrap());
+}
+
+#[test]
+fn parser_unclosed_section() {
+    let input = r#""key"
+{
+    "a"    "1"
+    "b"
+    {
+        "c"    "2"
+"#;
+    let result = parse_document(input);
+    let messages: Vec<&str> = result
+        .errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "expected '}' to close the section",
+            "expected '}' to close the section"
+        ]
+    );
+    let Value::Section(section) = &result.document.roots[0].value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section.len(), 2);
+    assert_eq!(section[1].key, "b");
+}
+
+#[test]
+fn parser_missing_value() {
+    let input = r#""key"
+{
+    "a"
+    "b"    "2"
+}"#;
+    let result = parse_document(input);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].message, "expected a value or a section");
+    assert_eq!(
+        result.errors[0].range,
+        Range {
+            start: Position::new(2, 4),
+            end: Position::new(2, 7),
+        }
+    );
+    let Value::Section(section) = &result.document.roots[0].value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section[0].value, Value::String(String::new()));
+    assert_eq!(section[1].key, "b");
+}
+
+#[test]
+fn parser_stray_tokens() {
+    let input = r#"}
+[$WIN32]
+{
+    "a"    "1"
+}
+"key"    "value""#;
+    let result = parse_document(input);
+    let messages: Vec<&str> = result
+        .errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        ["unexpected '}'", "expected a key", "expected a key"]
+    );
+    assert_eq!(result.document.roots.len(), 1);
+    assert_eq!(result.document.roots[0].key, "key");
+}
+
+#[test]
+fn parser_unterminated_tokens() {
+    let input = "\"key\" [$WIN32\n{\n    \"a\" \"1\" /* comment";
+    let result = parse_document(input);
+    let messages: Vec<&str> = result
+        .errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "unterminated conditional",
+            "expected '}' to close the section",
+            "unterminated block comment"
+        ]
+    );
+    assert_eq!(
+        result.document.roots[0].conditional.as_deref(),
+        Some("$WIN32")
+    );
+}
+
+#[test]
+fn parser_invalid_escapes() {
+    let input = r#""path" "materials\models\""#;
+    let result = parse_document(input);
+    assert_eq!(result.errors.len(), 2);
+    assert_eq!(result.errors[0].message, "unterminated string");
+    assert_eq!(result.errors[1].message, "invalid escape sequence");
+    assert_eq!(
+        result.errors[1].range,
+        Range {
+            start: Position::new(0, 17),
+            end: Position::new(0, 19),
+        }
+    );
+
+    let config = ParserConfig {
+        escape_mode: EscapeMode::Disabled,
+        ..Default::default()
+    };
+    let result = parse_document_with_config(input, config);
+    assert_eq!(result.errors, []);
+    assert_eq!(
+        result.document.roots[0].value,
+        Value::String(r#"materials\models\"#.to_string())
+    );
+}
+
+#[test]
+fn parser_value_on_next_line() {
+    let input = "\"key\"\n{\n    \"a\"\n    \"b\"    \"c\"    \"d\"\n}";
+    let result = parse_document(input);
+    assert_eq!(result.errors, []);
+    assert_eq!(result.document, serialize_document(input).unwrap());
+}
+
+#[test]
+fn parser_spans_of_incomplete_entries() {
+    let input = r#""key"
+{
+    "a"
+    "b"    "2"
+"#;
+    let result = parse_document(input);
+    let root = &result.document.roots[0];
+    assert_eq!(root.span, Span::new(0, 30));
+    assert_eq!(
+        root.l_brace_range.map(|range| range.start),
+        Some(Position::new(1, 0))
+    );
+    assert_eq!(root.r_brace_range, None);
+    let Value::Section(section) = &root.value else {
+        panic!("expected a section");
+    };
+    // A missing value is an empty range at the end of the key.
+    assert_eq!(section[0].value_span, Span::new(15, 15));
+    assert_eq!(
+        section[0].value_range,
+        Range {
+            start: Position::new(2, 7),
+            end: Position::new(2, 7),
+        }
+    );
+    assert_eq!(section[0].span, section[0].key_span);
+    assert_eq!(section[1].span, Span::new(20, 30));
+}
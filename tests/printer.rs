@@ -0,0 +1,173 @@
+use valve_kv_tools::{
+    serialize_document, serialize_keyvalue_with_config, EscapeMode, FormatterConfig, KeyValue,
+    ParserConfig, QuoteStyle, Range, Span, Value,
+};
+
+const INPUT: &str = r##"// Phrases of the plugin
+"Phrases"
+{
+    /* greeting */
+    "Hello" [$WIN32]
+    {
+        "#format"    "{1:s}"  // format
+        en    "Hello \"{1}\"\n"
+        "fr"    "[Bonjour]" [!$X360]
+        "empty"    ""
+    }
+    "Bye"
+    {
+        "en"    "Bye\\"
+        // last
+    }
+}"##;
+
+/// Clear what printing cannot keep: positions, and quotes when the style changes them.
+fn strip(kv: &mut KeyValue, quotes: bool) {
+    kv.key_range = Range::default();
+    kv.key_span = Span::default();
+    kv.value_range = Range::default();
+    kv.value_span = Span::default();
+    kv.range = Range::default();
+    kv.span = Span::default();
+    kv.l_brace_range = None;
+    kv.r_brace_range = None;
+    if quotes {
+        kv.key_quoted = false;
+        kv.value_quoted = false;
+    }
+    if let Value::Section(section) = &mut kv.value {
+        for kv in section.iter_mut() {
+            strip(kv, quotes);
+        }
+    }
+}
+
+fn check_round_trip(config: FormatterConfig) {
+    let parser_config = ParserConfig {
+        escape_mode: config.escape_mode,
+        ..Default::default()
+    };
+    let quotes = config.quote_style != QuoteStyle::Preserve;
+    let mut kv = serialize_keyvalue_with_config(INPUT, parser_config).unwrap();
+    let output = kv.to_string_with(&config).unwrap();
+    let mut printed = serialize_keyvalue_with_config(&output, parser_config).unwrap();
+    strip(&mut kv, quotes);
+    strip(&mut printed, quotes);
+    assert_eq!(printed, kv, "{config:?}\n{output}");
+}
+
+#[test]
+fn printer_round_trip() {
+    for use_tabs in [false, true] {
+        for indent_size in [0, 2, 4] {
+            for quote_style in [
+                QuoteStyle::Preserve,
+                QuoteStyle::Always,
+                QuoteStyle::Minimal,
+            ] {
+                for escape_mode in [EscapeMode::Json, EscapeMode::Valve] {
+                    check_round_trip(FormatterConfig {
+                        use_tabs,
+                        indent_size,
+                        quote_style,
+                        escape_mode,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn printer_layout() {
+    let kv = serialize_keyvalue_with_config(INPUT, ParserConfig::default()).unwrap();
+    let config = FormatterConfig {
+        use_tabs: true,
+        quote_style: QuoteStyle::Minimal,
+        ..Default::default()
+    };
+    assert_eq!(
+        kv.to_string_with(&config).unwrap(),
+        r##"// Phrases of the plugin
+Phrases
+{
+	/* greeting */
+	Hello	[$WIN32]
+	{
+		// format
+		#format	"{1:s}"
+		en	"Hello \"{1}\"\n"
+		fr	"[Bonjour]"	[!$X360]
+		empty	""
+	}
+	Bye
+	{
+		// last
+		en	"Bye\\"
+	}
+}"##
+    );
+}
+
+#[test]
+fn printer_display() {
+    let kv = KeyValue::new(
+        "root",
+        Value::Section(vec![
+            KeyValue::new("name", Value::String("two words".to_string())),
+            KeyValue::new("count", Value::Int32(3)),
+            KeyValue::new("quote", Value::String("a \"b\"".to_string())),
+            KeyValue::new("nested", Value::Section(vec![])),
+        ]),
+    );
+    assert_eq!(
+        kv.to_string(),
+        r#"root
+{
+    name    "two words"
+    count    3
+    quote    "a \"b\""
+    nested
+    {
+    }
+}"#
+    );
+
+    let config = FormatterConfig {
+        escape_mode: EscapeMode::Disabled,
+        ..Default::default()
+    };
+    assert!(kv.to_string_with(&config).is_err());
+}
+
+#[test]
+fn printer_directive_keys() {
+    for key in ["#base", "#include", "#INCLUDE"] {
+        for key_quoted in [false, true] {
+            for quote_style in [
+                QuoteStyle::Preserve,
+                QuoteStyle::Always,
+                QuoteStyle::Minimal,
+            ] {
+                let mut kv = KeyValue::new(key, Value::String("other.txt".to_string()));
+                kv.key_quoted = key_quoted;
+                let root = KeyValue::new(key, Value::Section(vec![kv.clone()]));
+                let config = FormatterConfig {
+                    quote_style,
+                    ..Default::default()
+                };
+                for kv in [kv, root] {
+                    let output = kv.to_string_with(&config).unwrap();
+                    let document = serialize_document(&output).unwrap();
+                    assert!(document.directives.is_empty(), "{output}");
+                    let mut printed = document.roots.into_iter().next().unwrap();
+                    let mut kv = kv;
+                    strip(&mut kv, true);
+                    strip(&mut printed, true);
+                    assert_eq!(printed, kv, "{output}");
+                }
+            }
+        }
+    }
+}
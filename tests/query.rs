@@ -0,0 +1,119 @@
+use valve_kv_tools::{serialize_keyvalue, KeyValue, Value};
+
+#[test]
+fn query_get() {
+    let input = r#""csgo"
+{
+    "Offsets"    "1"
+    "Keys"
+    {
+        "Signature"    "A"
+        "Signature"    "B"
+    }
+}"#;
+    let csgo = serialize_keyvalue(input).unwrap();
+    assert_eq!(csgo.keys().collect::<Vec<_>>(), vec!["Offsets", "Keys"]);
+    assert!(csgo.get("keys").is_none());
+    assert_eq!(csgo.get_ignore_case("KEYS").unwrap().key, "Keys");
+    let keys = csgo.get("Keys").unwrap();
+    assert_eq!(keys.get("Signature").unwrap().as_str(), Some("A"));
+    let signatures: Vec<_> = keys
+        .get_all("Signature")
+        .filter_map(KeyValue::as_str)
+        .collect();
+    assert_eq!(signatures, vec!["A", "B"]);
+    assert_eq!(keys.get_all_ignore_case("signature").count(), 2);
+    assert!(keys.get("Signature").unwrap().get("A").is_none());
+    assert!(Value::String("value".to_string()).entries().is_empty());
+}
+
+#[test]
+fn query_get_path() {
+    let input = r#""Games"
+{
+    "csgo"
+    {
+        "Offsets"
+        {
+            "GiveNamedItem"
+            {
+                "windows"    "400"
+            }
+            "Weapon_Drop"
+            {
+                "windows"    "263"
+            }
+        }
+    }
+    "a/b"    "slash"
+}"#;
+    let games = serialize_keyvalue(input).unwrap();
+    let windows = games
+        .get_path("csgo/Offsets/GiveNamedItem/windows")
+        .unwrap();
+    assert_eq!(windows.as_str(), Some("400"));
+    assert_eq!(windows.key_range.start.line, 8);
+    assert!(games.get_path("csgo/Offsets/Weapon_Drop/linux").is_none());
+    assert!(games.get_path("csgo/offsets").is_none());
+    assert_eq!(
+        games
+            .get_path_ignore_case("CSGO/offsets/weapon_drop/WINDOWS")
+            .and_then(KeyValue::as_str),
+        Some("263")
+    );
+    assert!(games.get_path("").is_none());
+    // Keys with a slash are only found by `get`.
+    assert!(games.get_path("a/b").is_none());
+    assert_eq!(games.get("a/b").and_then(KeyValue::as_str), Some("slash"));
+}
+
+#[test]
+fn query_get_mut() {
+    let input = r##""Games"
+{
+    "Weapon_Drop"
+    {
+        "windows"    "263"
+    }
+    "#default"    "csgo"
+}"##;
+    let mut games = serialize_keyvalue(input).unwrap();
+    games.get_path_mut("Weapon_Drop/windows").unwrap().value = Value::String("264".to_string());
+    games.get_mut_ignore_case("#DEFAULT").unwrap().key = "#default_renamed".to_string();
+    assert!(games.get_mut("missing").is_none());
+    assert!(games.get_path_mut("weapon_drop/windows").is_none());
+    games
+        .get_path_mut_ignore_case("weapon_drop/WINDOWS")
+        .unwrap()
+        .key = "linux".to_string();
+
+    assert_eq!(
+        games
+            .get_path("Weapon_Drop/linux")
+            .and_then(KeyValue::as_str),
+        Some("264")
+    );
+    assert!(games.get("#default_renamed").is_some());
+}
+
+#[test]
+fn query_descendants() {
+    let input = r##""Games"
+{
+    "csgo"
+    {
+        "Offsets"
+        {
+            "windows"    "400"
+        }
+        "Signature"    "A"
+    }
+    "#default"    "csgo"
+}"##;
+    let games = serialize_keyvalue(input).unwrap();
+    let keys: Vec<_> = games.descendants().map(|kv| kv.key.as_str()).collect();
+    assert_eq!(
+        keys,
+        vec!["csgo", "Offsets", "windows", "Signature", "#default"]
+    );
+}
@@ -0,0 +1,260 @@
+use valve_kv_tools::{
+    lint_keyvalue_with_resolver, lint_keyvalue_with_resolver_with_config, resolve_document,
+    resolve_document_with_config, serialize_keyvalue, FileSystemResolver, KvError, KvErrorKind,
+    MemoryResolver, ParserConfig, Position, PositionEncoding, Range, Value,
+};
+
+fn section_keys(value: &Value) -> Vec<(&str, Option<&str>)> {
+    let Value::Section(section) = value else {
+        panic!("expected a section");
+    };
+    section
+        .iter()
+        .map(|kv| {
+            let value = match &kv.value {
+                Value::String(value) => Some(value.as_str()),
+                _ => None,
+            };
+            (kv.key.as_str(), value)
+        })
+        .collect()
+}
+
+#[test]
+fn resolver_base_does_not_override() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "resource/ui/base.res",
+        r#""base" { "wide" "100" "tall" "20" }"#,
+    );
+    let input = r#"#base "base.res"
+"hud"
+{
+    "wide" "200"
+}"#;
+    let resolved = resolve_document(input, "resource/ui/hud.res", &resolver).unwrap();
+    assert_eq!(resolved.errors, []);
+    assert_eq!(resolved.document.roots[0].key, "hud");
+    assert_eq!(
+        section_keys(&resolved.document.roots[0].value),
+        [("wide", Some("200")), ("tall", Some("20"))]
+    );
+}
+
+#[test]
+fn resolver_base_merges_recursively() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "base.res",
+        r#""base" { "panel" { "xpos" "0" "ypos" "0" } "other" { "visible" "1" } }"#,
+    );
+    let input = r#"#base "base.res"
+"hud" { "panel" { "xpos" "10" } }"#;
+    let resolved = resolve_document(input, "hud.res", &resolver).unwrap();
+    let Value::Section(section) = &resolved.document.roots[0].value else {
+        panic!("expected a section");
+    };
+    assert_eq!(
+        section_keys(&section[0].value),
+        [("xpos", Some("10")), ("ypos", Some("0"))]
+    );
+    assert_eq!(section[1].key, "other");
+}
+
+#[test]
+fn resolver_base_merges_ignoring_case() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "base.res",
+        r#""base" { "Panel" { "XPos" "0" "ypos" "0" } "Other" "1" }"#,
+    );
+    let input = r#"#base "base.res"
+"hud" { "panel" { "xpos" "10" } "other" "2" }"#;
+    let resolved = resolve_document(input, "hud.res", &resolver).unwrap();
+    let Value::Section(section) = &resolved.document.roots[0].value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section.len(), 2);
+    assert_eq!(section[0].key, "panel");
+    assert_eq!(
+        section_keys(&section[0].value),
+        [("xpos", Some("10")), ("ypos", Some("0"))]
+    );
+    assert_eq!(
+        section_keys(&resolved.document.roots[0].value)[1],
+        ("other", Some("2"))
+    );
+}
+
+#[test]
+fn resolver_include_appends() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("scripts/extra.txt", r#""extra" { "a" "2" "b" "3" }"#);
+    let input = r#"#include "extra.txt"
+"root" { "a" "1" }"#;
+    let resolved = resolve_document(input, "scripts/root.txt", &resolver).unwrap();
+    let roots = &resolved.document.roots;
+    assert_eq!(roots.len(), 2);
+    assert_eq!(roots[0].key, "root");
+    assert_eq!(section_keys(&roots[0].value), [("a", Some("1"))]);
+    assert_eq!(roots[1].key, "extra");
+    assert_eq!(
+        section_keys(&roots[1].value),
+        [("a", Some("2")), ("b", Some("3"))]
+    );
+}
+
+#[test]
+fn resolver_nested_relative_paths() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "a/b/first.res",
+        r#"#base "../second.res" "first" { "x" "1" }"#,
+    );
+    resolver.insert("a/second.res", r#""second" { "y" "2" }"#);
+    let input = r#"#base "b\first.res" "root" { }"#;
+    let resolved = resolve_document(input, "a/root.res", &resolver).unwrap();
+    assert_eq!(resolved.errors, []);
+    assert_eq!(
+        section_keys(&resolved.document.roots[0].value),
+        [("x", Some("1")), ("y", Some("2"))]
+    );
+}
+
+#[test]
+fn resolver_missing_file() {
+    let resolver = MemoryResolver::new();
+    let input = r#"#base "missing.res"
+"root" { }"#;
+    assert_eq!(
+        lint_keyvalue_with_resolver(input, "ui/root.res", &resolver),
+        [KvError {
+            range: Range {
+                start: Position::new(0, 6),
+                end: Position::new(0, 19),
+            },
+            additional_ranges: vec![],
+            message: "File \"ui/missing.res\" not found".to_string(),
+            kind: KvErrorKind::MissingFileError,
+        }]
+    );
+}
+
+#[test]
+fn resolver_missing_file_with_syntax_errors() {
+    let resolver = MemoryResolver::new();
+    let input = r#"#base "missing.res"
+"root" { "key" }"#;
+    let kinds: Vec<KvErrorKind> = lint_keyvalue_with_resolver(input, "root.res", &resolver)
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [KvErrorKind::SyntaxError, KvErrorKind::MissingFileError]
+    );
+}
+
+#[test]
+fn resolver_with_config() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("base.res", r#""base" { "a\tb" "1" }"#);
+    let config = ParserConfig {
+        unescape: false,
+        position_encoding: PositionEncoding::Utf8,
+        ..Default::default()
+    };
+    let input = r#"#base "base.res" #base "dé.res" "root" { }"#;
+    let resolved = resolve_document_with_config(input, "root.res", &resolver, config).unwrap();
+    assert_eq!(
+        section_keys(&resolved.document.roots[0].value),
+        [("a\\tb", Some("1"))]
+    );
+    let errors = lint_keyvalue_with_resolver_with_config(input, "root.res", &resolver, config);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].range,
+        Range {
+            start: Position::new(0, 23),
+            end: Position::new(0, 32),
+        }
+    );
+}
+
+#[test]
+fn resolver_nested_missing_file() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("base.res", r#"#include "missing.res" "base" { }"#);
+    let input = r#"#base "base.res" "root" { }"#;
+    let errors = lint_keyvalue_with_resolver(input, "root.res", &resolver);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, KvErrorKind::MissingFileError);
+    assert_eq!(
+        errors[0].range,
+        Range {
+            start: Position::new(0, 6),
+            end: Position::new(0, 16),
+        }
+    );
+}
+
+#[test]
+fn resolver_include_cycle() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("a.res", r#"#include "b.res" "a" { "a" "1" }"#);
+    resolver.insert("b.res", r#"#include "a.res" "b" { "b" "1" }"#);
+    let input = r#"#include "a.res" "root" { }"#;
+    let resolved = resolve_document(input, "root.res", &resolver).unwrap();
+    assert_eq!(resolved.errors.len(), 1);
+    assert_eq!(resolved.errors[0].kind, KvErrorKind::IncludeCycleError);
+    assert_eq!(
+        resolved.errors[0].message,
+        "Include cycle: \"root.res\" -> \"a.res\" -> \"b.res\" -> \"a.res\""
+    );
+    let keys: Vec<&str> = resolved
+        .document
+        .roots
+        .iter()
+        .map(|kv| kv.key.as_str())
+        .collect();
+    assert_eq!(keys, ["root", "a", "b"]);
+}
+
+#[test]
+fn resolver_syntax_error_in_included_file() {
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("broken.res", r#""broken" {"#);
+    let input = r#"#include "broken.res" "root" { }"#;
+    let errors = lint_keyvalue_with_resolver(input, "root.res", &resolver);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, KvErrorKind::SyntaxError);
+    assert!(errors[0]
+        .message
+        .starts_with("Syntax error in \"broken.res\""));
+}
+
+#[test]
+fn resolver_file_system() {
+    let root = std::env::temp_dir().join(format!("valve_kv_tools_resolver_{}", std::process::id()));
+    std::fs::create_dir_all(root.join("ui")).unwrap();
+    std::fs::write(root.join("ui/base.res"), r#""base" { "tall" "20" }"#).unwrap();
+    let input = r#"#base "base.res" "hud" { "wide" "200" }"#;
+    let resolved = resolve_document(input, "ui/hud.res", &FileSystemResolver::new(&root)).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(resolved.errors, []);
+    assert_eq!(
+        section_keys(&resolved.document.roots[0].value),
+        [("wide", Some("200")), ("tall", Some("20"))]
+    );
+}
+
+#[test]
+fn resolver_directive_is_not_a_key() {
+    let kv = serialize_keyvalue(
+        r#"#base "base.res"
+#include "include.res"
+"root" { }"#,
+    )
+    .unwrap();
+    assert_eq!(kv.key, "root");
+}
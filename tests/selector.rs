@@ -0,0 +1,260 @@
+use valve_kv_tools::{select, select_ignore_case, serialize_document, KeyValue, Selector};
+
+fn keys(roots: &[KeyValue], selector: &str) -> Vec<String> {
+    select(roots, selector)
+        .unwrap()
+        .into_iter()
+        .map(|kv| match kv.as_str() {
+            Some(value) => format!("{}={}", kv.key, value),
+            None => kv.key.clone(),
+        })
+        .collect()
+}
+
+#[test]
+fn selector_paths_and_wildcards() {
+    let input = r##""Games"
+{
+    "csgo"
+    {
+        "Offsets"
+        {
+            "GiveNamedItem"
+            {
+                "linux"    "401"
+            }
+            "Weapon_Drop"    "263"
+        }
+        "Keys"
+        {
+            "Signature"    "A"
+            "Signature"    "B"
+        }
+    }
+    "#default"
+    {
+        "Offsets"
+        {
+            "#format"    "1"
+        }
+    }
+}"##;
+    let roots = serialize_document(input).unwrap().roots;
+    assert_eq!(
+        keys(&roots, "Games/csgo/Offsets/GiveNamedItem/linux"),
+        ["linux=401"]
+    );
+    assert_eq!(
+        keys(&roots, "Games/*/Offsets/*"),
+        ["GiveNamedItem", "Weapon_Drop=263", "#format=1"]
+    );
+    assert_eq!(
+        keys(&roots, "Games/csgo/Offsets/*_Drop"),
+        ["Weapon_Drop=263"]
+    );
+    assert_eq!(
+        keys(&roots, "Games/csgo/Keys/Signatur?"),
+        ["Signature=A", "Signature=B"]
+    );
+    assert_eq!(
+        keys(&roots, "Games/csgo/Offsets/Missing"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        keys(&roots, "Games/csgo/Keys/Signature/*"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        keys(&roots, "\"Games\"/\"csgo\"/Keys/\"Sig*\""),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn selector_recursive_descent() {
+    let input = r##""Games"
+{
+    "csgo"
+    {
+        "Offsets"
+        {
+            "linux"    "401"
+            "#format"    "1"
+        }
+        "Signature"    "A"
+    }
+    "#default"
+    {
+        "#format"    "2"
+        "linux"    "80"
+    }
+}"##;
+    let roots = serialize_document(input).unwrap().roots;
+    assert_eq!(keys(&roots, "**/#format"), ["#format=1", "#format=2"]);
+    assert_eq!(keys(&roots, "**/Games"), ["Games"]);
+    assert_eq!(keys(&roots, "Games/**/linux"), ["linux=401", "linux=80"]);
+    assert_eq!(keys(&roots, "**/**/#format"), ["#format=1", "#format=2"]);
+    assert_eq!(keys(&roots, "Games/#default/**").len(), 2);
+    // Matches come out in document order, not grouped by section.
+    assert_eq!(
+        keys(&roots, "Games/csgo/**/*[.]"),
+        ["linux=401", "#format=1", "Signature=A"]
+    );
+}
+
+#[test]
+fn selector_predicates() {
+    let input = r#""Offsets"
+{
+    "GiveNamedItem"
+    {
+        "windows"    "400"
+        "linux"    "401"
+    }
+    "Weapon_Drop"
+    {
+        "windows"    "263"
+    }
+    "Touch"
+    {
+        "linux"    "80"
+    }
+    "1v1"
+    {
+        "1v1"    "1"
+    }
+    "format"    "2"
+}"#;
+    let roots = serialize_document(input).unwrap().roots;
+    assert_eq!(
+        keys(&roots, "Offsets/*[!linux]"),
+        ["Weapon_Drop", "1v1", "format=2"]
+    );
+    assert_eq!(keys(&roots, "Offsets/*[!linux][windows]"), ["Weapon_Drop"]);
+    assert_eq!(
+        keys(&roots, "Offsets/*[windows=\"400\"]"),
+        ["GiveNamedItem"]
+    );
+    assert_eq!(keys(&roots, "Offsets/*[ windows != 400 ]"), ["Weapon_Drop"]);
+    assert_eq!(
+        keys(&roots, "Offsets/*[!windows=4*]"),
+        ["Weapon_Drop", "Touch", "1v1", "format=2"]
+    );
+    assert_eq!(keys(&roots, "*[*[linux=80]]"), ["Offsets"]);
+    assert_eq!(keys(&roots, "**/*[.=\"2\"]"), ["format=2"]);
+    assert_eq!(keys(&roots, "Offsets/*[.!=2][.]"), Vec::<String>::new());
+    assert_eq!(keys(&roots, "Offsets/*[!.]").len(), 4);
+    // Keys starting like a number are keys, not indexes.
+    assert_eq!(keys(&roots, "Offsets/*[1v1]"), ["1v1"]);
+    assert_eq!(keys(&roots, "Offsets/*[-1v1]"), Vec::<String>::new());
+}
+
+#[test]
+fn selector_indexes() {
+    let input = r#""Keys"
+{
+    "Signature"    "A"
+    "Signature"    "B"
+    "Signature"    "C"
+    "Offsets"
+    {
+        "windows"    "400"
+    }
+}
+"Other"
+{
+    "windows"    "263"
+}"#;
+    let roots = serialize_document(input).unwrap().roots;
+    assert_eq!(keys(&roots, "Keys/Signature[1]"), ["Signature=B"]);
+    assert_eq!(keys(&roots, "Keys/Signature[ -1 ]"), ["Signature=C"]);
+    assert_eq!(keys(&roots, "Keys/Signature[3]"), Vec::<String>::new());
+    assert_eq!(keys(&roots, "Keys/Signature[.!=A][0]"), ["Signature=B"]);
+    assert_eq!(
+        keys(&roots, "Keys/Signature[0][.!=A]"),
+        Vec::<String>::new()
+    );
+    // Indexes count the matches of each section.
+    assert_eq!(
+        keys(&roots, "**/windows[0]"),
+        ["windows=400", "windows=263"]
+    );
+}
+
+#[test]
+fn selector_matches_carry_key_ranges() {
+    let input = r#""Keys"
+{
+    "Signature"    "A"
+    "Signature"    "B"
+}"#;
+    let roots = serialize_document(input).unwrap().roots;
+    let matches = select(&roots, "Keys/Signature[1]").unwrap();
+    assert_eq!(matches.len(), 1);
+    let range = matches[0].key_range;
+    assert_eq!(range.start.line, 3);
+    assert_eq!(range.start.character, 4);
+    assert_eq!(range.end.character, 15);
+}
+
+#[test]
+fn selector_ignore_case() {
+    let input = r#""Games"
+{
+    "csgo"
+    {
+        "Offsets"
+        {
+            "Touch"
+            {
+                "linux"    "80"
+            }
+        }
+        "Signature"    "A"
+        "Signature"    "B"
+    }
+}"#;
+    let roots = serialize_document(input).unwrap().roots;
+    assert!(select(&roots, "games/CSGO").unwrap().is_empty());
+    assert_eq!(select_ignore_case(&roots, "games/CSGO").unwrap().len(), 1);
+    assert_eq!(
+        select_ignore_case(&roots, "**/offs*/*[Linux]")
+            .unwrap()
+            .len(),
+        1
+    );
+    let selector = Selector::parse("**/SIGNATURE[1]").unwrap();
+    assert_eq!(selector.select_ignore_case(&roots)[0].as_str(), Some("B"));
+}
+
+#[test]
+fn selector_errors() {
+    for (selector, offset, message) in [
+        ("", 0, "expected a key"),
+        ("Games//csgo", 6, "expected a key"),
+        ("Games/", 6, "expected a key"),
+        ("**[0]", 2, "'**' cannot be filtered"),
+        ("Games[0", 7, "expected ']'"),
+        (
+            "Games[99999999999999999999]",
+            6,
+            "invalid index '99999999999999999999'",
+        ),
+        ("Games[linux=]", 12, "expected a value"),
+        ("Games[linux!4]", 12, "expected '='"),
+        ("\"Games", 6, "unterminated string"),
+        ("Games]", 5, "unexpected character ']'"),
+        ("Games csgo", 5, "unexpected character ' '"),
+    ] {
+        let error = Selector::parse(selector).unwrap_err();
+        assert_eq!(
+            (error.offset, error.message.as_str()),
+            (offset, message),
+            "{selector}"
+        );
+    }
+    assert_eq!(
+        select(&[], "Games]").unwrap_err().to_string(),
+        "invalid selector `Games]` at offset 5: unexpected character ']'"
+    );
+}
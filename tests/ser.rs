@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use valve_kv_tools::{
+    from_str, from_str_with_config, to_string, to_string_with_config, to_writer,
+    DeserializerConfig, FormatterConfig, QuoteStyle, SequenceStyle, SerializerConfig,
+};
+
+fn indexed(start: u32) -> DeserializerConfig {
+    DeserializerConfig {
+        sequence_style: SequenceStyle::Indexed(start),
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Phrases {
+    #[serde(rename = "Phrases")]
+    phrases: BTreeMap<String, Phrase>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Phrase {
+    #[serde(rename = "#format", skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+    en: String,
+    fr: Option<String>,
+}
+
+#[test]
+fn ser_struct() {
+    let phrases = Phrases {
+        phrases: BTreeMap::from([
+            (
+                "Hello".to_string(),
+                Phrase {
+                    format: Some("{1:s}".to_string()),
+                    en: "Hello \"{1}\"".to_string(),
+                    fr: None,
+                },
+            ),
+            (
+                "Bye".to_string(),
+                Phrase {
+                    format: None,
+                    en: "Bye".to_string(),
+                    fr: Some("Au revoir".to_string()),
+                },
+            ),
+        ]),
+    };
+    let output = to_string(&phrases).unwrap();
+    assert_eq!(
+        output,
+        r##""Phrases"
+{
+    "Bye"
+    {
+        "en"    "Bye"
+        "fr"    "Au revoir"
+    }
+    "Hello"
+    {
+        "#format"    "{1:s}"
+        "en"    "Hello \"{1}\""
+    }
+}"##
+    );
+    assert_eq!(from_str::<Phrases>(&output).unwrap(), phrases);
+
+    let mut writer = vec![];
+    to_writer(&mut writer, &phrases).unwrap();
+    assert_eq!(writer, output.as_bytes());
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Databases {
+    driver_default: String,
+    include: Vec<String>,
+    retry: bool,
+    timeout: f32,
+}
+
+#[test]
+fn ser_sequences() {
+    let databases = Databases {
+        driver_default: "mysql".to_string(),
+        include: vec!["a.cfg".to_string(), "b.cfg".to_string()],
+        retry: true,
+        timeout: 2.5,
+    };
+    let output = to_string(&databases).unwrap();
+    assert_eq!(
+        output,
+        r#""driver_default"    "mysql"
+"include"    "a.cfg"
+"include"    "b.cfg"
+"retry"    "1"
+"timeout"    "2.5""#
+    );
+    assert_eq!(from_str::<Databases>(&output).unwrap(), databases);
+
+    let config = SerializerConfig {
+        formatter: FormatterConfig {
+            use_tabs: true,
+            quote_style: QuoteStyle::Minimal,
+            ..Default::default()
+        },
+        sequence_style: SequenceStyle::Indexed(1),
+    };
+    let output = to_string_with_config(&databases, &config).unwrap();
+    assert_eq!(
+        output,
+        "driver_default\tmysql\ninclude\n{\n\t1\ta.cfg\n\t2\tb.cfg\n}\nretry\t1\ntimeout\t2.5"
+    );
+    assert_eq!(
+        from_str_with_config::<Databases>(&output, indexed(1)).unwrap(),
+        databases
+    );
+    // The indices must match the configured style.
+    let error = from_str_with_config::<Databases>(&output, indexed(0)).unwrap_err();
+    assert_eq!(error.to_string(), "4:2: expected index \"0\", found \"1\"");
+
+    let nested = BTreeMap::from([("a", vec![vec![1, 2], vec![3]])]);
+    assert!(to_string(&nested).is_err());
+    let config = SerializerConfig {
+        sequence_style: SequenceStyle::Indexed(0),
+        ..Default::default()
+    };
+    assert_eq!(
+        to_string_with_config(&nested, &config).unwrap(),
+        r#""a"
+{
+    "0"
+    {
+        "0"    "1"
+        "1"    "2"
+    }
+    "1"
+    {
+        "0"    "3"
+    }
+}"#
+    );
+    let output = to_string_with_config(&nested, &config).unwrap();
+    assert_eq!(
+        from_str_with_config::<BTreeMap<&str, Vec<Vec<u8>>>>(&output, indexed(0)).unwrap(),
+        nested
+    );
+}
+
+#[test]
+fn ser_sequence_round_trips() {
+    let empty = Databases {
+        driver_default: "mysql".to_string(),
+        include: vec![],
+        retry: false,
+        timeout: 1.0,
+    };
+    let error = to_string(&empty).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "an empty sequence with repeated keys cannot be serialized as keyvalue"
+    );
+    let config = SerializerConfig {
+        sequence_style: SequenceStyle::Indexed(0),
+        ..Default::default()
+    };
+    let output = to_string_with_config(&empty, &config).unwrap();
+    assert_eq!(
+        from_str_with_config::<Databases>(&output, indexed(0)).unwrap(),
+        empty
+    );
+
+    let holes = BTreeMap::from([("a", vec![Some(1), None, Some(3)])]);
+    for sequence_style in [SequenceStyle::RepeatedKeys, SequenceStyle::Indexed(1)] {
+        let config = SerializerConfig {
+            sequence_style,
+            ..Default::default()
+        };
+        let error = to_string_with_config(&holes, &config).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "None in a sequence cannot be serialized as keyvalue"
+        );
+    }
+
+    // A single element is written like a value with repeated keys, even when it looks
+    // like an indexed section.
+    let single = BTreeMap::from([("a", vec![BTreeMap::from([("0", "x")])])]);
+    let output = to_string(&single).unwrap();
+    assert_eq!(output, "\"a\"\n{\n    \"0\"    \"x\"\n}");
+    assert_eq!(
+        from_str::<BTreeMap<&str, Vec<BTreeMap<&str, &str>>>>(&output).unwrap(),
+        single
+    );
+    let empty_map = BTreeMap::from([("a", vec![BTreeMap::<&str, &str>::new()])]);
+    let output = to_string(&empty_map).unwrap();
+    assert_eq!(
+        from_str::<BTreeMap<&str, Vec<BTreeMap<&str, &str>>>>(&output).unwrap(),
+        empty_map
+    );
+    let config = SerializerConfig {
+        sequence_style: SequenceStyle::Indexed(5),
+        ..Default::default()
+    };
+    let output = to_string_with_config(&single, &config).unwrap();
+    assert_eq!(
+        from_str_with_config::<BTreeMap<&str, Vec<BTreeMap<&str, &str>>>>(&output, indexed(5))
+            .unwrap(),
+        single
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Shape {
+    Circle { radius: f32 },
+    Square(u8),
+    Point,
+}
+
+#[test]
+fn ser_enums() {
+    let shapes = BTreeMap::from([(
+        "shapes",
+        vec![
+            Shape::Circle { radius: 1.5 },
+            Shape::Square(4),
+            Shape::Point,
+        ],
+    )]);
+    let output = to_string(&shapes).unwrap();
+    assert_eq!(
+        output,
+        r#""shapes"
+{
+    "Circle"
+    {
+        "radius"    "1.5"
+    }
+}
+"shapes"
+{
+    "Square"    "4"
+}
+"shapes"    "Point""#
+    );
+    assert_eq!(
+        from_str::<BTreeMap<&str, Vec<Shape>>>(&output).unwrap(),
+        shapes
+    );
+}
+
+#[test]
+fn ser_directive_keys() {
+    let base = BTreeMap::from([("#base", "base.res"), ("#format", "{1:s}")]);
+    let config = SerializerConfig {
+        formatter: FormatterConfig {
+            quote_style: QuoteStyle::Minimal,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let output = to_string_with_config(&base, &config).unwrap();
+    assert_eq!(output, "\"#base\"    base.res\n\"#format\"    \"{1:s}\"");
+    assert_eq!(from_str::<BTreeMap<&str, &str>>(&output).unwrap(), base);
+}
+
+#[test]
+fn ser_errors() {
+    assert!(to_string(&vec![1, 2]).is_err());
+    assert!(to_string(&"value").is_err());
+    assert!(to_string(&BTreeMap::from([((1, 2), "value")])).is_err());
+
+    let config = SerializerConfig {
+        formatter: FormatterConfig {
+            escape_mode: valve_kv_tools::EscapeMode::Disabled,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let error =
+        to_string_with_config(&BTreeMap::from([("key", "\"quoted\"")]), &config).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "\"\"quoted\"\" contains a quote, which cannot be written without escape sequences"
+    );
+}
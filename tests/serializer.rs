@@ -1,4 +1,7 @@
-use valve_kv_tools::{serialize_keyvalue, KeyValue, Position, Range, Value};
+use valve_kv_tools::{
+    serialize_document, serialize_keyvalue, serialize_keyvalue_with_config, Directive,
+    DirectiveKind, EscapeMode, KeyValue, ParserConfig, Position, Range, Span, Value,
+};
 
 #[test]
 fn serialize_value() {
@@ -81,7 +84,24 @@ fn serialize_section() {
             key_range: Range {
                 start: Position::new(0, 8),
                 end: Position::new(0, 14),
-            }
+            },
+            key_span: Span::new(8, 14),
+            value_range: Range {
+                start: Position::new(0, 15),
+                end: Position::new(0, 22),
+            },
+            value_span: Span::new(15, 22),
+            range: Range {
+                start: Position::new(0, 8),
+                end: Position::new(0, 22),
+            },
+            span: Span::new(8, 22),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec![],
         }])
     );
 }
@@ -102,7 +122,24 @@ fn serialize_section_multiple_lines() {
             key_range: Range {
                 start: Position::new(2, 4),
                 end: Position::new(2, 10),
-            }
+            },
+            key_span: Span::new(15, 21),
+            value_range: Range {
+                start: Position::new(2, 11),
+                end: Position::new(2, 18),
+            },
+            value_span: Span::new(22, 29),
+            range: Range {
+                start: Position::new(2, 4),
+                end: Position::new(2, 18),
+            },
+            span: Span::new(15, 29),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec![],
         }])
     );
 }
@@ -123,7 +160,24 @@ fn serialize_section_block_comment_1() {
             key_range: Range {
                 start: Position::new(2, 4),
                 end: Position::new(2, 10),
-            }
+            },
+            key_span: Span::new(29, 35),
+            value_range: Range {
+                start: Position::new(2, 11),
+                end: Position::new(2, 18),
+            },
+            value_span: Span::new(36, 43),
+            range: Range {
+                start: Position::new(2, 4),
+                end: Position::new(2, 18),
+            },
+            span: Span::new(29, 43),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec![],
         }])
     );
 }
@@ -144,7 +198,24 @@ fn serialize_section_block_comment_2() {
             key_range: Range {
                 start: Position::new(2, 4),
                 end: Position::new(2, 10),
-            }
+            },
+            key_span: Span::new(29, 35),
+            value_range: Range {
+                start: Position::new(2, 11),
+                end: Position::new(2, 18),
+            },
+            value_span: Span::new(36, 43),
+            range: Range {
+                start: Position::new(2, 4),
+                end: Position::new(2, 18),
+            },
+            span: Span::new(29, 43),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec![],
         }])
     );
 }
@@ -165,7 +236,24 @@ fn serialize_section_block_comment_3() {
             key_range: Range {
                 start: Position::new(2, 4),
                 end: Position::new(2, 10),
-            }
+            },
+            key_span: Span::new(29, 35),
+            value_range: Range {
+                start: Position::new(2, 11),
+                end: Position::new(2, 18),
+            },
+            value_span: Span::new(36, 43),
+            range: Range {
+                start: Position::new(2, 4),
+                end: Position::new(2, 18),
+            },
+            span: Span::new(29, 43),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec!["/* comment */".to_string()],
         }])
     );
 }
@@ -186,7 +274,24 @@ fn serialize_section_block_comment_4() {
             key_range: Range {
                 start: Position::new(2, 18),
                 end: Position::new(2, 24),
-            }
+            },
+            key_span: Span::new(29, 35),
+            value_range: Range {
+                start: Position::new(2, 25),
+                end: Position::new(2, 32),
+            },
+            value_span: Span::new(36, 43),
+            range: Range {
+                start: Position::new(2, 18),
+                end: Position::new(2, 32),
+            },
+            span: Span::new(29, 43),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec!["/* comment */".to_string()],
         }])
     );
 }
@@ -207,7 +312,24 @@ fn serialize_section_block_comment_5() {
             key_range: Range {
                 start: Position::new(2, 3),
                 end: Position::new(2, 9),
-            }
+            },
+            key_span: Span::new(14, 20),
+            value_range: Range {
+                start: Position::new(2, 10),
+                end: Position::new(2, 17),
+            },
+            value_span: Span::new(21, 28),
+            range: Range {
+                start: Position::new(2, 3),
+                end: Position::new(2, 17),
+            },
+            span: Span::new(14, 28),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec!["/* comment */".to_string()],
         }])
     );
 }
@@ -228,7 +350,24 @@ fn serialize_section_block_comment_6() {
             key_range: Range {
                 start: Position::new(2, 3),
                 end: Position::new(2, 9),
-            }
+            },
+            key_span: Span::new(14, 20),
+            value_range: Range {
+                start: Position::new(2, 10),
+                end: Position::new(2, 17),
+            },
+            value_span: Span::new(21, 28),
+            range: Range {
+                start: Position::new(2, 3),
+                end: Position::new(2, 17),
+            },
+            span: Span::new(14, 28),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: true,
+            value_quoted: true,
+            conditional: None,
+            comments: vec![],
         }])
     );
 }
@@ -251,7 +390,24 @@ fn serialize_section_multiple_key_values() {
                 key_range: Range {
                     start: Position::new(2, 4),
                     end: Position::new(2, 10),
-                }
+                },
+                key_span: Span::new(15, 21),
+                value_range: Range {
+                    start: Position::new(2, 11),
+                    end: Position::new(2, 18),
+                },
+                value_span: Span::new(22, 29),
+                range: Range {
+                    start: Position::new(2, 4),
+                    end: Position::new(2, 18),
+                },
+                span: Span::new(15, 29),
+                l_brace_range: None,
+                r_brace_range: None,
+                key_quoted: true,
+                value_quoted: true,
+                conditional: None,
+                comments: vec![],
             },
             KeyValue {
                 key: "key3".to_string(),
@@ -259,7 +415,24 @@ fn serialize_section_multiple_key_values() {
                 key_range: Range {
                     start: Position::new(3, 4),
                     end: Position::new(3, 10),
-                }
+                },
+                key_span: Span::new(34, 40),
+                value_range: Range {
+                    start: Position::new(3, 11),
+                    end: Position::new(3, 18),
+                },
+                value_span: Span::new(41, 48),
+                range: Range {
+                    start: Position::new(3, 4),
+                    end: Position::new(3, 18),
+                },
+                span: Span::new(34, 48),
+                l_brace_range: None,
+                r_brace_range: None,
+                key_quoted: true,
+                value_quoted: true,
+                conditional: None,
+                comments: vec![],
             }
         ])
     );
@@ -285,7 +458,24 @@ fn serialize_section_nested() {
                 key_range: Range {
                     start: Position::new(2, 4),
                     end: Position::new(2, 10),
-                }
+                },
+                key_span: Span::new(15, 21),
+                value_range: Range {
+                    start: Position::new(2, 11),
+                    end: Position::new(2, 18),
+                },
+                value_span: Span::new(22, 29),
+                range: Range {
+                    start: Position::new(2, 4),
+                    end: Position::new(2, 18),
+                },
+                span: Span::new(15, 29),
+                l_brace_range: None,
+                r_brace_range: None,
+                key_quoted: true,
+                value_quoted: true,
+                conditional: None,
+                comments: vec![],
             },
             KeyValue {
                 key: "key3".to_string(),
@@ -295,12 +485,52 @@ fn serialize_section_nested() {
                     key_range: Range {
                         start: Position::new(4, 6),
                         end: Position::new(4, 12),
-                    }
+                    },
+                    key_span: Span::new(49, 55),
+                    value_range: Range {
+                        start: Position::new(4, 13),
+                        end: Position::new(4, 20),
+                    },
+                    value_span: Span::new(56, 63),
+                    range: Range {
+                        start: Position::new(4, 6),
+                        end: Position::new(4, 20),
+                    },
+                    span: Span::new(49, 63),
+                    l_brace_range: None,
+                    r_brace_range: None,
+                    key_quoted: true,
+                    value_quoted: true,
+                    conditional: None,
+                    comments: vec![],
                 }]),
                 key_range: Range {
                     start: Position::new(3, 4),
                     end: Position::new(3, 10),
-                }
+                },
+                key_span: Span::new(34, 40),
+                value_range: Range {
+                    start: Position::new(3, 11),
+                    end: Position::new(5, 5),
+                },
+                value_span: Span::new(41, 69),
+                range: Range {
+                    start: Position::new(3, 4),
+                    end: Position::new(5, 5),
+                },
+                span: Span::new(34, 69),
+                l_brace_range: Some(Range {
+                    start: Position::new(3, 11),
+                    end: Position::new(3, 12),
+                }),
+                r_brace_range: Some(Range {
+                    start: Position::new(5, 4),
+                    end: Position::new(5, 5),
+                }),
+                key_quoted: true,
+                value_quoted: false,
+                conditional: None,
+                comments: vec![],
             }
         ])
     );
@@ -326,7 +556,24 @@ fn serialize_section_repeated_keys() {
                 key_range: Range {
                     start: Position::new(2, 4),
                     end: Position::new(2, 9),
-                }
+                },
+                key_span: Span::new(14, 19),
+                value_range: Range {
+                    start: Position::new(2, 10),
+                    end: Position::new(2, 17),
+                },
+                value_span: Span::new(20, 27),
+                range: Range {
+                    start: Position::new(2, 4),
+                    end: Position::new(2, 17),
+                },
+                span: Span::new(14, 27),
+                l_brace_range: None,
+                r_brace_range: None,
+                key_quoted: true,
+                value_quoted: true,
+                conditional: None,
+                comments: vec![],
             },
             KeyValue {
                 key: "key".to_string(),
@@ -336,13 +583,402 @@ fn serialize_section_repeated_keys() {
                     key_range: Range {
                         start: Position::new(4, 6),
                         end: Position::new(4, 11),
-                    }
+                    },
+                    key_span: Span::new(46, 51),
+                    value_range: Range {
+                        start: Position::new(4, 12),
+                        end: Position::new(4, 19),
+                    },
+                    value_span: Span::new(52, 59),
+                    range: Range {
+                        start: Position::new(4, 6),
+                        end: Position::new(4, 19),
+                    },
+                    span: Span::new(46, 59),
+                    l_brace_range: None,
+                    r_brace_range: None,
+                    key_quoted: true,
+                    value_quoted: true,
+                    conditional: None,
+                    comments: vec![],
                 }]),
                 key_range: Range {
                     start: Position::new(3, 4),
                     end: Position::new(3, 9),
-                }
+                },
+                key_span: Span::new(32, 37),
+                value_range: Range {
+                    start: Position::new(3, 10),
+                    end: Position::new(5, 5),
+                },
+                value_span: Span::new(38, 65),
+                range: Range {
+                    start: Position::new(3, 4),
+                    end: Position::new(5, 5),
+                },
+                span: Span::new(32, 65),
+                l_brace_range: Some(Range {
+                    start: Position::new(3, 10),
+                    end: Position::new(3, 11),
+                }),
+                r_brace_range: Some(Range {
+                    start: Position::new(5, 4),
+                    end: Position::new(5, 5),
+                }),
+                key_quoted: true,
+                value_quoted: false,
+                conditional: None,
+                comments: vec![],
             }
         ])
     );
 }
+
+#[test]
+fn serialize_unquoted_value() {
+    let input = r#""$basetexture" foo/bar"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, "$basetexture");
+    assert!(kv.key_quoted);
+    assert_eq!(kv.value, Value::String("foo/bar".to_string()));
+    assert!(!kv.value_quoted);
+}
+
+#[test]
+fn serialize_unquoted_section() {
+    let input = r#"LightmappedGeneric
+{
+    $basetexture foo/bar
+    "$surfaceprop"  metal
+}"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, "LightmappedGeneric");
+    assert!(!kv.key_quoted);
+    assert_eq!(
+        kv.value,
+        Value::Section(vec![
+            KeyValue {
+                key: "$basetexture".to_string(),
+                value: Value::String("foo/bar".to_string()),
+                key_range: Range {
+                    start: Position::new(2, 4),
+                    end: Position::new(2, 16),
+                },
+                key_span: Span::new(25, 37),
+                value_range: Range {
+                    start: Position::new(2, 17),
+                    end: Position::new(2, 24),
+                },
+                value_span: Span::new(38, 45),
+                range: Range {
+                    start: Position::new(2, 4),
+                    end: Position::new(2, 24),
+                },
+                span: Span::new(25, 45),
+                l_brace_range: None,
+                r_brace_range: None,
+                key_quoted: false,
+                value_quoted: false,
+                conditional: None,
+                comments: vec![],
+            },
+            KeyValue {
+                key: "$surfaceprop".to_string(),
+                value: Value::String("metal".to_string()),
+                key_range: Range {
+                    start: Position::new(3, 4),
+                    end: Position::new(3, 18),
+                },
+                key_span: Span::new(50, 64),
+                value_range: Range {
+                    start: Position::new(3, 20),
+                    end: Position::new(3, 25),
+                },
+                value_span: Span::new(66, 71),
+                range: Range {
+                    start: Position::new(3, 4),
+                    end: Position::new(3, 25),
+                },
+                span: Span::new(50, 71),
+                l_brace_range: None,
+                r_brace_range: None,
+                key_quoted: true,
+                value_quoted: false,
+                conditional: None,
+                comments: vec![],
+            }
+        ])
+    );
+}
+
+#[test]
+fn serialize_unquoted_no_whitespace() {
+    let input = r#"key{inner value}"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, "key");
+    assert_eq!(
+        kv.value,
+        Value::Section(vec![KeyValue {
+            key: "inner".to_string(),
+            value: Value::String("value".to_string()),
+            key_range: Range {
+                start: Position::new(0, 4),
+                end: Position::new(0, 9),
+            },
+            key_span: Span::new(4, 9),
+            value_range: Range {
+                start: Position::new(0, 10),
+                end: Position::new(0, 15),
+            },
+            value_span: Span::new(10, 15),
+            range: Range {
+                start: Position::new(0, 4),
+                end: Position::new(0, 15),
+            },
+            span: Span::new(4, 15),
+            l_brace_range: None,
+            r_brace_range: None,
+            key_quoted: false,
+            value_quoted: false,
+            conditional: None,
+            comments: vec![],
+        }])
+    );
+}
+
+#[test]
+fn serialize_unquoted_suffix_comment() {
+    let input = r#"key value// comment"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, "key");
+    assert_eq!(kv.value, Value::String("value".to_string()));
+}
+
+#[test]
+fn serialize_conditional_value() {
+    let input = r#""font" "Arial" [$WIN32]"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, "font");
+    assert_eq!(kv.value, Value::String("Arial".to_string()));
+    assert_eq!(kv.conditional, Some("$WIN32".to_string()));
+}
+
+#[test]
+fn serialize_conditional_section() {
+    let input = r#""key" [!$X360]
+{
+    "font" "Arial" [$WIN32]
+    "font" "Verdana" [$OSX]
+}"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, "key");
+    assert_eq!(kv.conditional, Some("!$X360".to_string()));
+    let Value::Section(section) = kv.value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section[0].conditional, Some("$WIN32".to_string()));
+    assert_eq!(section[1].conditional, Some("$OSX".to_string()));
+}
+
+#[test]
+fn serialize_conditional_unquoted() {
+    let input = r#"key
+{
+    font Arial [$WIN32]
+    size 12
+}"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    let Value::Section(section) = kv.value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section.len(), 2);
+    assert_eq!(section[0].value, Value::String("Arial".to_string()));
+    assert_eq!(section[0].conditional, Some("$WIN32".to_string()));
+    assert_eq!(section[1].key, "size");
+    assert_eq!(section[1].conditional, None);
+}
+
+#[test]
+fn serialize_document_directives() {
+    let input = r#"#base "base.res"
+#INCLUDE include.res
+"key" "value""#;
+    let document = serialize_document(input).unwrap();
+    assert_eq!(
+        document.directives,
+        [
+            Directive {
+                kind: DirectiveKind::Base,
+                path: "base.res".to_string(),
+                path_range: Range {
+                    start: Position::new(0, 6),
+                    end: Position::new(0, 16),
+                },
+                path_span: Span::new(6, 16),
+                range: Range {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 16),
+                },
+                span: Span::new(0, 16),
+            },
+            Directive {
+                kind: DirectiveKind::Include,
+                path: "include.res".to_string(),
+                path_range: Range {
+                    start: Position::new(1, 9),
+                    end: Position::new(1, 20),
+                },
+                path_span: Span::new(26, 37),
+                range: Range {
+                    start: Position::new(1, 0),
+                    end: Position::new(1, 20),
+                },
+                span: Span::new(17, 37),
+            }
+        ]
+    );
+    assert_eq!(document.roots.len(), 1);
+    assert_eq!(document.roots[0].key, "key");
+}
+
+#[test]
+fn serialize_document_multiple_roots() {
+    let input = r#"versioninfo
+{
+    "editorversion" "400"
+}
+world
+{
+    "id" "1"
+}
+entity
+{
+    "id" "2"
+}
+entity
+{
+    "id" "3"
+}"#;
+    let document = serialize_document(input).unwrap();
+    let keys: Vec<&str> = document.roots.iter().map(|kv| kv.key.as_str()).collect();
+    assert_eq!(keys, ["versioninfo", "world", "entity", "entity"]);
+    assert_eq!(
+        document.roots[3].key_range,
+        Range {
+            start: Position::new(12, 0),
+            end: Position::new(12, 6),
+        }
+    );
+}
+
+#[test]
+fn serialize_document_empty() {
+    let document = serialize_document("// nothing here\n").unwrap();
+    assert_eq!(document.roots, []);
+}
+
+#[test]
+fn serialize_keyvalue_multiple_roots() {
+    assert!(serialize_keyvalue(r#""a" "1" "b" "2""#).is_err());
+}
+
+#[test]
+fn serialize_escaped_strings() {
+    let input = r#""say \"hi\"" "a\\b\n\u00e9""#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(kv.key, r#"say "hi""#);
+    assert_eq!(kv.value, Value::String("a\\b\n\u{e9}".to_string()));
+}
+
+#[test]
+fn serialize_raw_strings() {
+    let input = r#""say \"hi\"" "a\\b\n""#;
+    let config = ParserConfig {
+        unescape: false,
+        ..Default::default()
+    };
+    let kv = serialize_keyvalue_with_config(input, config).unwrap();
+    assert_eq!(kv.key, r#"say \"hi\""#);
+    assert_eq!(kv.value, Value::String(r#"a\\b\n"#.to_string()));
+}
+
+#[test]
+fn serialize_invalid_json_escape() {
+    assert!(serialize_keyvalue(r#""path" "materials\models""#).is_err());
+}
+
+#[test]
+fn serialize_valve_escapes() {
+    let input = r#""path" "materials\models\n \"quoted\" a\\b""#;
+    let config = ParserConfig {
+        escape_mode: EscapeMode::Valve,
+        ..Default::default()
+    };
+    let kv = serialize_keyvalue_with_config(input, config).unwrap();
+    assert_eq!(
+        kv.value,
+        Value::String("materials\\models\n \"quoted\" a\\b".to_string())
+    );
+}
+
+#[test]
+fn serialize_disabled_escapes() {
+    let input = r#""path" "C:\Program Files\Steam\""#;
+    let config = ParserConfig {
+        escape_mode: EscapeMode::Disabled,
+        ..Default::default()
+    };
+    let kv = serialize_keyvalue_with_config(input, config).unwrap();
+    assert_eq!(
+        kv.value,
+        Value::String("C:\\Program Files\\Steam\\".to_string())
+    );
+}
+
+#[test]
+fn serialize_spans() {
+    let input = "\"key\" // comment\n{\n    \"a\" \"1\" [$WIN32]\n}";
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(&input[kv.span.start..kv.span.end], input);
+    assert_eq!(&input[kv.value_span.start..kv.value_span.end], &input[17..]);
+    let Value::Section(section) = &kv.value else {
+        panic!("expected a section");
+    };
+    let entry = &section[0];
+    assert_eq!(&input[entry.key_span.start..entry.key_span.end], "\"a\"");
+    assert_eq!(
+        &input[entry.value_span.start..entry.value_span.end],
+        "\"1\""
+    );
+    assert_eq!(
+        &input[entry.span.start..entry.span.end],
+        "\"a\" \"1\" [$WIN32]"
+    );
+    assert_eq!(entry.l_brace_range, None);
+}
+
+#[test]
+fn serialize_comments() {
+    let input = r#"// leading
+"key1" /* inside */
+{
+    "key2" "value" // trailing
+    // before key3
+    "key3" "value"
+    // end of section
+} // after the section
+"#;
+    let kv = serialize_keyvalue(input).unwrap();
+    assert_eq!(
+        kv.comments,
+        ["// leading", "/* inside */", "// after the section"]
+    );
+    let Value::Section(section) = kv.value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section[0].comments, ["// trailing"]);
+    assert_eq!(section[1].comments, ["// before key3", "// end of section"]);
+
+    let kv = serialize_keyvalue("\"key\"\n{\n    // empty\n}").unwrap();
+    assert_eq!(kv.comments, ["// empty"]);
+}
@@ -0,0 +1,165 @@
+use std::io::Read;
+
+use valve_kv_tools::{
+    read_events, serialize_document, Event, EventReader, KeyValue, KvErrorKind, ParserConfig,
+    Position, Range, Span, StreamError, Value,
+};
+
+/// Reader returning its input one byte at a time, to split every token across reads.
+struct ByteReader<'a>(&'a [u8]);
+
+impl Read for ByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Ok(0);
+        };
+        buf[0] = *first;
+        self.0 = rest;
+        Ok(1)
+    }
+}
+
+/// Clear what events do not carry, to compare with the serializer.
+fn without_spans(mut kv: KeyValue) -> KeyValue {
+    kv.key_span = Span::default();
+    kv.value_span = Span::default();
+    kv.span = Span::default();
+    kv.key_quoted = false;
+    kv.value_quoted = false;
+    if let Value::Section(section) = kv.value {
+        kv.value = Value::Section(section.into_iter().map(without_spans).collect());
+    }
+    kv
+}
+
+const ITEMS: &str = r#"// Items
+"items_game"
+{
+    "items" // all items
+    {
+        "1" { "name" "Crowbar" } /* melee */
+        "2" [$WIN32]
+        {
+            "name"	"Pistol \"9mm\""
+            "desc"	"Lé pistolet 😀
+sur deux lignes"
+        }
+    }
+    "empty" { // nothing
+    }
+}
+"#;
+
+#[test]
+fn stream_events() {
+    let input = r#"#base "base.txt"
+"root" // comment
+{
+    "key" "value" [$X360]
+}"#;
+    let events: Vec<Event> = read_events(input).collect::<Result<_, _>>().unwrap();
+    let range = |a, b, c, d| Range::new(Position::new(a, b), Position::new(c, d));
+    assert_eq!(
+        events,
+        [
+            Event::Directive {
+                kind: valve_kv_tools::DirectiveKind::Base,
+                path: "base.txt".to_string(),
+                path_range: range(0, 6, 0, 16),
+            },
+            Event::Comment {
+                text: "// comment".to_string(),
+                range: range(1, 7, 1, 17),
+            },
+            Event::StartSection {
+                key: "root".to_string(),
+                key_range: range(1, 0, 1, 6),
+                conditional: None,
+                l_brace_range: range(2, 0, 2, 1),
+            },
+            Event::KeyValue {
+                key: "key".to_string(),
+                value: "value".to_string(),
+                key_range: range(3, 4, 3, 9),
+                value_range: range(3, 10, 3, 17),
+                conditional: Some("$X360".to_string()),
+            },
+            Event::EndSection {
+                r_brace_range: range(4, 0, 4, 1),
+            },
+        ]
+    );
+}
+
+#[test]
+fn stream_matches_serializer() {
+    let expected: Vec<KeyValue> = serialize_document(ITEMS)
+        .unwrap()
+        .roots
+        .into_iter()
+        .map(without_spans)
+        .collect();
+    let from_str = read_events(ITEMS).read_section().unwrap();
+    assert_eq!(from_str, expected);
+    let mut reader = EventReader::new(ByteReader(ITEMS.as_bytes()), ParserConfig::default());
+    assert_eq!(reader.read_section().unwrap(), expected);
+}
+
+#[test]
+fn stream_extract_section() {
+    let mut reader = EventReader::new(ByteReader(ITEMS.as_bytes()), ParserConfig::default());
+    let mut names = vec![];
+    while let Some(event) = reader.next() {
+        match event.unwrap() {
+            Event::StartSection { key, .. } if key == "items_game" || key == "items" => (),
+            Event::StartSection { key, .. } if key == "2" => {
+                let section = reader.read_section().unwrap();
+                names.push(section[0].value.clone());
+            }
+            Event::StartSection { .. } => reader.skip_section().unwrap(),
+            _ => (),
+        }
+    }
+    assert_eq!(names, [Value::String("Pistol \"9mm\"".to_string())]);
+}
+
+#[test]
+fn stream_errors() {
+    let error = |input: &[u8]| -> (Vec<Event>, StreamError) {
+        let mut events = vec![];
+        let reader = EventReader::new(ByteReader(input), ParserConfig::default());
+        for event in reader {
+            match event {
+                Ok(event) => events.push(event),
+                Err(err) => return (events, err),
+            }
+        }
+        panic!("expected an error");
+    };
+
+    let (events, err) = error(b"\"a\" {\n\"b\" \"c\"");
+    assert_eq!(events.len(), 2);
+    let StreamError::Syntax(err) = err else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.message, "expected '}' to close the section");
+    assert_eq!(err.range.start, Position::new(0, 4));
+
+    let (_, err) = error(b"\"a\" \"b\\q\"");
+    let StreamError::Syntax(err) = err else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.message, "invalid escape sequence");
+    assert_eq!(
+        err.range,
+        Range::new(Position::new(0, 6), Position::new(0, 8))
+    );
+
+    let (events, err) = error(b"\"a\" \"b\"\n\"c\" \"\xFF\"");
+    assert_eq!(events.len(), 1);
+    let StreamError::Syntax(err) = err else {
+        panic!("expected an encoding error");
+    };
+    assert_eq!(err.kind, KvErrorKind::EncodingError);
+    assert_eq!(err.range.start, Position::new(1, 5));
+}
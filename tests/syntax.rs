@@ -0,0 +1,88 @@
+use valve_kv_tools::{parse_document, SyntaxElement, SyntaxKind, SyntaxNode};
+
+const INPUTS: &[&str] = &[
+    "",
+    "\"key\"    \"value\"",
+    "#base \"base.res\"  // comment\r\n\"key\"\r\n{\r\n\t\"a\"  \"1\"  [$WIN32]\r\n}\r\n",
+    "/* comment */ key [!$X360] { unquoted value \"say \\\"hi\\\"\" \"\" }",
+    "\"key\"\n{\n    \"a\"    \"1\n    \"b\"\n}\n}\n[$OSX\n{ /* unterminated",
+    "\u{feff}\"clé\"    \"значение\"    // コメント",
+];
+
+#[test]
+fn syntax_tree_round_trip() {
+    for input in INPUTS {
+        let tree = parse_document(input).tree;
+        assert_eq!(&tree.to_string(), input);
+    }
+}
+
+#[test]
+fn syntax_tree_tokens_are_contiguous() {
+    for input in INPUTS {
+        let tree = parse_document(input).tree;
+        let mut offset = 0;
+        for token in tree.root.descendant_tokens() {
+            assert_eq!(token.start, offset);
+            assert!(token.end > token.start);
+            offset = token.end;
+        }
+        assert_eq!(offset, input.len());
+        assert_eq!((tree.root.start, tree.root.end), (0, input.len()));
+    }
+}
+
+/// Returns the kinds of the nodes and significant tokens of a node, as an s-expression.
+fn shape(node: &SyntaxNode) -> String {
+    let children: Vec<String> = node
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(shape(node)),
+            SyntaxElement::Token(token) if !token.kind.is_trivia() => {
+                Some(format!("{:?}", token.kind))
+            }
+            SyntaxElement::Token(_) => None,
+        })
+        .collect();
+    format!("({:?} {})", node.kind, children.join(" "))
+}
+
+#[test]
+fn syntax_tree_shape() {
+    let input = r#"#include "a.res"
+"key" [$WIN32]
+{
+    // comment
+    "a"    b    [$OSX]
+}
+}"#;
+    let tree = parse_document(input).tree;
+    assert_eq!(
+        shape(&tree.root),
+        "(Document (Directive UnquotedString QuotedString) \
+         (KeyValue QuotedString Conditional \
+         (Section LBrace (KeyValue QuotedString UnquotedString Conditional) RBrace)) \
+         (Error RBrace))"
+    );
+}
+
+#[test]
+fn syntax_tree_trivia() {
+    let input = "// leading\n\"key\" /* inner */ \"value\" // trailing\n";
+    let tree = parse_document(input).tree;
+    let kinds: Vec<SyntaxKind> = tree.root.child_tokens().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            SyntaxKind::LineComment,
+            SyntaxKind::Whitespace,
+            SyntaxKind::Whitespace,
+            SyntaxKind::LineComment,
+            SyntaxKind::Whitespace
+        ]
+    );
+    let kv = tree.root.child_nodes().next().unwrap();
+    assert_eq!(kv.kind, SyntaxKind::KeyValue);
+    assert_eq!(&input[kv.start..kv.end], "\"key\" /* inner */ \"value\"");
+}
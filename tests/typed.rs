@@ -0,0 +1,89 @@
+use valve_kv_tools::{serialize_keyvalue, Position, Range, Value};
+
+fn string(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+#[test]
+fn typed_numbers() {
+    assert_eq!(string("42").as_i64(), Ok(42));
+    assert_eq!(string("  -7 units").as_i64(), Ok(-7));
+    assert_eq!(string("1.5").as_i64(), Ok(1));
+    assert_eq!(Value::Float32(2.9).as_i64(), Ok(2));
+    assert_eq!(Value::UInt64(3).as_i64(), Ok(3));
+    assert!(string("x1").as_i64().is_err());
+    assert!(string("99999999999999999999").as_i64().is_err());
+
+    assert_eq!(string("0.5").as_f32(), Ok(0.5));
+    assert_eq!(string(".5").as_f32(), Ok(0.5));
+    assert_eq!(string("5.").as_f32(), Ok(5.0));
+    assert_eq!(string("-1e2f").as_f32(), Ok(-100.0));
+    assert_eq!(string("3e").as_f32(), Ok(3.0));
+    assert_eq!(Value::Int32(4).as_f32(), Ok(4.0));
+    assert!(string(".").as_f32().is_err());
+    assert!(string("").as_f32().is_err());
+    assert!(Value::Section(vec![]).as_f32().is_err());
+}
+
+#[test]
+fn typed_bools() {
+    assert_eq!(string("1").as_bool(), Ok(true));
+    assert_eq!(string("0").as_bool(), Ok(false));
+    assert_eq!(string("-2").as_bool(), Ok(true));
+    assert_eq!(string("0.5").as_bool(), Ok(false));
+    assert!(string("true").as_bool().is_err());
+}
+
+#[test]
+fn typed_vectors() {
+    assert_eq!(string("0 1 0").as_vector3(), Ok([0.0, 1.0, 0.0]));
+    assert_eq!(string("[0 1 0]").as_vector3(), Ok([0.0, 1.0, 0.0]));
+    assert_eq!(
+        string(" {-1.5\t2 3e1} ").as_vector3(),
+        Ok([-1.5, 2.0, 30.0])
+    );
+    assert_eq!(string("90 180 0").as_qangle(), Ok([90.0, 180.0, 0.0]));
+    assert!(string("0 1").as_vector3().is_err());
+    assert!(string("0 1 0 1").as_vector3().is_err());
+    assert!(string("0 1x 0").as_vector3().is_err());
+    assert!(string("[0 1 0").as_vector3().is_err());
+    assert!(Value::Int32(0).as_qangle().is_err());
+}
+
+#[test]
+fn typed_colors() {
+    assert_eq!(string("255 128 0 255").as_color(), Ok([255, 128, 0, 255]));
+    assert_eq!(string("{255 0 0}").as_color(), Ok([255, 0, 0, 255]));
+    assert_eq!(string("[10.7 20 30 40]").as_color(), Ok([10, 20, 30, 40]));
+    assert_eq!(Value::Color([1, 2, 3, 4]).as_color(), Ok([1, 2, 3, 4]));
+    assert!(string("256 0 0").as_color().is_err());
+    assert!(string("-1 0 0").as_color().is_err());
+    assert!(string("255 0").as_color().is_err());
+}
+
+#[test]
+fn typed_errors_carry_value_range() {
+    let kv = serialize_keyvalue(
+        r#""Entity"
+{
+    "health" "100"
+    "speed" "fast"
+    "origin" { }
+}"#,
+    )
+    .unwrap();
+    let Value::Section(section) = &kv.value else {
+        panic!("expected a section");
+    };
+    assert_eq!(section[0].as_i64(), Ok(100));
+    let error = section[1].as_f32().unwrap_err();
+    assert_eq!(
+        error.range,
+        Range::new(Position::new(3, 12), Position::new(3, 18))
+    );
+    assert_eq!(error.message, "expected a number, found \"fast\"");
+    assert_eq!(error.to_string(), "4:13: expected a number, found \"fast\"");
+    let error = section[2].as_vector3().unwrap_err();
+    assert_eq!(error.range, section[2].value_range);
+    assert_eq!(error.message, "expected a vector, found a section");
+}